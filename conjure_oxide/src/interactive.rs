@@ -0,0 +1,108 @@
+//! Step-through rewriting, where the user picks each rule application.
+
+use std::io::{self, BufRead, Write};
+
+use crate::rule_engine::{CandidateFinder, RewriteStep, Rule};
+use crate::Model;
+
+/// Rewrite the model one rule application at a time, reading the user's choices from `input`.
+///
+/// At each step the current model and every applicable rule application are written to `output`. The user
+/// enters the number of an application to make it, `u` to undo the last step, `r` to run to completion by
+/// always making the first application, or `q` (or end of input) to stop.
+///
+/// # Returns
+/// The model when the user stopped, and the steps that led to it from the given model.
+pub fn rewrite_interactively<'a>(
+    model: &Model,
+    rules: &[&'a Rule<'a>],
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<(Model, Vec<RewriteStep>)> {
+    let mut model = model.clone();
+    let mut steps: Vec<RewriteStep> = Vec::new();
+    // The model before each of the steps, for undo
+    let mut history: Vec<Model> = Vec::new();
+    let mut finder = CandidateFinder::new(rules);
+
+    loop {
+        writeln!(output, "\nModel after {} steps:", steps.len())?;
+        write_model(&mut output, &model)?;
+
+        let mut candidates = finder.all(&model);
+        if candidates.is_empty() {
+            writeln!(output, "\nNo rules apply.")?;
+        } else {
+            writeln!(output, "\nApplicable rules:")?;
+            for (i, candidate) in candidates.iter().enumerate() {
+                writeln!(
+                    output,
+                    "  {}: {} ~> {}",
+                    i,
+                    candidate.step(),
+                    candidate.reduction.new_expression
+                )?;
+                writeln!(output, "       rewrites {}", candidate.expression)?;
+            }
+        }
+        write!(
+            output,
+            "\nChoose a rule by number, (u)ndo, (r)un to completion or (q)uit: "
+        )?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            break;
+        }
+
+        match line.trim() {
+            "q" => break,
+            "u" => match history.pop() {
+                Some(previous) => {
+                    model = previous;
+                    steps.pop();
+                    finder.clear_cache();
+                }
+                None => writeln!(output, "Nothing to undo.")?,
+            },
+            "r" => {
+                while let Some(candidate) = finder.first(&model) {
+                    history.push(model.clone());
+                    steps.push(candidate.step());
+                    finder
+                        .apply(candidate, &mut model)
+                        .map_err(io::Error::other)?;
+                }
+            }
+            choice => match choice.parse::<usize>() {
+                Ok(i) if i < candidates.len() => {
+                    let candidate = candidates.swap_remove(i);
+                    history.push(model.clone());
+                    steps.push(candidate.step());
+                    finder
+                        .apply(candidate, &mut model)
+                        .map_err(io::Error::other)?;
+                }
+                _ => writeln!(output, "Unknown choice: {}", choice)?,
+            },
+        }
+    }
+
+    Ok((model, steps))
+}
+
+/// Write the variables and constraints of a model, one variable per line.
+pub fn write_model(output: &mut impl Write, model: &Model) -> io::Result<()> {
+    let mut variables: Vec<String> = model
+        .variables
+        .iter()
+        .map(|(name, var)| format!("find {}: {}", name, var))
+        .collect();
+    variables.sort();
+    for variable in variables {
+        writeln!(output, "  {}", variable)?;
+    }
+    writeln!(output, "  such that {}", model.constraints)
+}
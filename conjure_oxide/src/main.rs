@@ -1,14 +1,15 @@
 // (niklasdewally): temporary, gut this if you want!
 
 use std::fs::File;
-use std::io::stdout;
 use std::io::Write;
-use std::path::PathBuf;
+use std::io::{stdin, stdout};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::{Arc, RwLock};
 
 use anyhow::Result as AnyhowResult;
 use anyhow::{anyhow, bail};
-use clap::{arg, command, Parser};
+use clap::{arg, command, Parser, Subcommand, ValueEnum};
 use schemars::schema_for;
 use serde_json::json;
 use serde_json::to_string_pretty;
@@ -16,16 +17,25 @@ use structured_logger::{json::new_writer, Builder};
 
 use conjure_core::context::Context;
 use conjure_oxide::find_conjure::conjure_executable;
-use conjure_oxide::model_from_json;
+use conjure_oxide::interactive::{rewrite_interactively, write_model};
+use conjure_oxide::propagate::Unsatisfiable;
 use conjure_oxide::rule_engine::{
-    get_rule_priorities, get_rules_vec, resolve_rule_sets, rewrite_model,
+    get_rule_priorities, get_rules, get_rules_vec, load_rule_file, resolve_rule_sets,
+    rewrite_model, rewrite_model_all, rule_set_graph, ResolveRulesError, RewritePolicy,
+    RewriteScript, Rule, RuleOverrides, RuleSet,
+};
+use conjure_oxide::utils::conjure::{
+    minion_solutions_to_json, solve_with_minion, user_variables_only, SolveResult,
 };
-use conjure_oxide::utils::conjure::{get_minion_solutions, minion_solutions_to_json};
 use conjure_oxide::SolverFamily;
+use conjure_oxide::{model_from_json, Model};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(
         value_name = "INPUT_ESSENCE",
         default_value = "./conjure_oxide/tests/integration/xyz/input.essence",
@@ -35,6 +45,7 @@ struct Cli {
 
     #[arg(
         long,
+        global = true,
         value_name = "EXTRA_RULE_SETS",
         help = "Names of extra rule sets to enable"
     )]
@@ -42,6 +53,72 @@ struct Cli {
 
     #[arg(
         long,
+        global = true,
+        value_name = "RULE_FILE",
+        help = "Load extra rules and rule sets from the given rule file"
+    )]
+    rule_file: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "RULE",
+        help = "Never apply the given rule"
+    )]
+    disable_rule: Vec<String>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATTERN",
+        help = "Only apply rules whose names match one of the given patterns ('*' matches anything)"
+    )]
+    only_rules: Vec<String>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "RULE=PRIORITY",
+        value_parser = parse_rule_priority,
+        help = "Apply the given rule with the given priority, even if its rule sets are not enabled"
+    )]
+    rule_priority: Vec<(String, u8)>,
+
+    #[arg(
+        long,
+        global = true,
+        default_value_t = false,
+        help = "Do not narrow domains or eliminate fixed variables once the model is rewritten (the Simplify rule set)"
+    )]
+    no_simplify: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = RewritePolicy::First,
+        help = "How to pick between the alternatives offered by a rule; 'all' saves one model per combination of alternatives to --models-dir instead of solving"
+    )]
+    rewrite_policy: RewritePolicy,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "models",
+        help = "Where to save the rewritten models with --rewrite-policy all"
+    )]
+    models_dir: PathBuf,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 100,
+        help = "Save at most this many models with --rewrite-policy all, or 0 for no limit"
+    )]
+    max_models: usize,
+
+    #[arg(
+        long,
+        global = true,
         value_enum,
         value_name = "SOLVER",
         short = 's',
@@ -57,6 +134,16 @@ struct Cli {
     )]
     print_info_schema: bool,
 
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "text",
+        help = "Print the rule set dependency graph (as text by default) and exit"
+    )]
+    print_rule_set_graph: Option<GraphFormat>,
+
     #[arg(long, help = "Save execution info as JSON to the given file-path.")]
     info_json_path: Option<PathBuf>,
 
@@ -66,6 +153,100 @@ struct Cli {
         help = "Save solutions to a JSON file (prints to stdin by default)"
     )]
     output: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Include the auxiliary variables introduced while rewriting in the solutions"
+    )]
+    output_aux: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Inspect the registered rules")]
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+
+    #[command(about = "Inspect the registered rule sets")]
+    RuleSets {
+        #[command(subcommand)]
+        command: RuleSetsCommand,
+    },
+
+    #[command(about = "Rewrite the input model and print the result, without solving it")]
+    Rewrite {
+        #[arg(
+            value_name = "INPUT_ESSENCE",
+            help = "The input Essence file, if not given before the subcommand"
+        )]
+        input_file: Option<PathBuf>,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Choose each rule application, with undo, instead of letting the rewriter choose"
+        )]
+        interactive: bool,
+
+        #[arg(
+            long,
+            value_name = "SCRIPT",
+            help = "Replay the rule applications in the given script before rewriting"
+        )]
+        replay: Option<PathBuf>,
+
+        #[arg(
+            long,
+            value_name = "SCRIPT",
+            default_value = "rewrite_script.json",
+            help = "Where to save the rule applications chosen with --interactive, as a script for --replay"
+        )]
+        script: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum RulesCommand {
+    #[command(about = "List all rules, marking those active for the chosen solver and rule sets")]
+    List {
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Only list the active rules, in the order the rewriter tries them"
+        )]
+        active: bool,
+    },
+
+    #[command(about = "Show a rule's rule sets, priorities and documentation")]
+    Show {
+        #[arg(value_name = "RULE", help = "The name of the rule")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RuleSetsCommand {
+    #[command(about = "List all rule sets, marking those enabled for the chosen solver")]
+    List,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    Text,
+    Dot,
+}
+
+fn parse_rule_priority(arg: &str) -> Result<(String, u8), String> {
+    let (rule, priority) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected RULE=PRIORITY, got {}", arg))?;
+    let priority = priority
+        .parse()
+        .map_err(|e| format!("invalid priority {}: {}", priority, e))?;
+    Ok((rule.to_string(), priority))
 }
 
 #[allow(clippy::unwrap_used)]
@@ -81,6 +262,11 @@ pub fn main() -> AnyhowResult<()> {
 
     let target_family = cli.solver.unwrap_or(SolverFamily::Minion);
     let extra_rule_sets: Vec<String> = cli.extra_rule_sets;
+    let rule_overrides = RuleOverrides {
+        disabled_rules: cli.disable_rule,
+        only_rules: cli.only_rules,
+        priorities: cli.rule_priority.into_iter().collect(),
+    };
     let out_file: Option<File> = match &cli.output {
         None => None,
         Some(pth) => Some(
@@ -102,12 +288,47 @@ pub fn main() -> AnyhowResult<()> {
         .with_target_writer("file", new_writer(log_file))
         .init();
 
+    for rule_file in &cli.rule_file {
+        if let Err(e) = load_rule_file(rule_file) {
+            log::error!("Error loading rule file {}: {}", rule_file.display(), e);
+            exit(1);
+        }
+    }
+
+    match &cli.command {
+        Some(Command::Rewrite { .. }) | None => {}
+        Some(command) => {
+            return run_command(
+                command,
+                target_family,
+                &extra_rule_sets,
+                cli.no_simplify,
+                &rule_overrides,
+            )
+        }
+    }
+
+    if let Some(format) = cli.print_rule_set_graph {
+        let graph = match rule_set_graph() {
+            Ok(graph) => graph,
+            Err(e) => {
+                log::error!("Error resolving rule sets: {}", e);
+                exit(1);
+            }
+        };
+        match format {
+            GraphFormat::Text => print!("{}", graph),
+            GraphFormat::Dot => println!("{}", graph.to_dot()),
+        }
+        return Ok(());
+    }
+
     if target_family != SolverFamily::Minion {
         log::error!("Only the Minion solver is currently supported!");
         exit(1);
     }
 
-    let rule_sets = match resolve_rule_sets(target_family, &extra_rule_sets) {
+    let rule_sets = match resolve_cli_rule_sets(target_family, &extra_rule_sets, cli.no_simplify) {
         Ok(rs) => rs,
         Err(e) => {
             log::error!("Error resolving rule sets: {}", e);
@@ -128,7 +349,7 @@ pub fn main() -> AnyhowResult<()> {
         pretty_rule_sets
     );
 
-    let rule_priorities = get_rule_priorities(&rule_sets)?;
+    let rule_priorities = rule_overrides.apply(get_rule_priorities(&rule_sets)?)?;
     let rules_vec = get_rules_vec(&rule_priorities);
 
     log::info!(target: "file", 
@@ -138,8 +359,15 @@ pub fn main() -> AnyhowResult<()> {
             .collect::<Vec<_>>()
             .join(", "));
 
-    log::info!(target: "file", "Input file: {}", cli.input_file.display());
-    let input_file: &str = cli.input_file.to_str().ok_or(anyhow!(
+    let input_path = match &cli.command {
+        Some(Command::Rewrite {
+            input_file: Some(path),
+            ..
+        }) => path.clone(),
+        _ => cli.input_file.clone(),
+    };
+    log::info!(target: "file", "Input file: {}", input_path.display());
+    let input_file: &str = input_path.to_str().ok_or(anyhow!(
         "Given input_file could not be converted to a string"
     ))?;
 
@@ -171,19 +399,74 @@ pub fn main() -> AnyhowResult<()> {
         rule_sets.clone(),
     );
 
-    context.write().unwrap().file_name = Some(cli.input_file.to_str().expect("").into());
+    context.write().unwrap().file_name = Some(input_file.into());
+    context.write().unwrap().rule_overrides = rule_overrides;
+    context.write().unwrap().rewrite_policy = cli.rewrite_policy;
 
     let mut model = model_from_json(&astjson, context.clone())?;
 
     log::info!(target: "file", "Initial model: {}", json!(model));
 
     log::info!(target: "file", "Rewriting model...");
+
+    if let Some(Command::Rewrite {
+        interactive,
+        replay,
+        script,
+        ..
+    }) = &cli.command
+    {
+        run_rewrite(
+            &model,
+            &rules_vec,
+            &rule_sets,
+            *interactive,
+            replay.as_deref(),
+            script,
+        )?;
+        if let Some(path) = cli.info_json_path {
+            save_info_json(&context, path)?;
+        }
+        return Ok(());
+    }
+
+    if cli.rewrite_policy == RewritePolicy::All {
+        let max_models = (cli.max_models > 0).then_some(cli.max_models);
+        let models = rewrite_model_all(&model, &rule_sets, max_models)?;
+        std::fs::create_dir_all(&cli.models_dir)?;
+        for (i, model) in models.iter().enumerate() {
+            let path = cli.models_dir.join(format!("model{:06}.json", i + 1));
+            File::create(path)?.write_all(to_string_pretty(&json!(model))?.as_bytes())?;
+        }
+        println!(
+            "{} models saved to {:?}",
+            models.len(),
+            cli.models_dir.canonicalize()?
+        );
+        if max_models == Some(models.len()) {
+            println!("Stopped at --max-models; there may be more models");
+        }
+        if let Some(path) = cli.info_json_path {
+            save_info_json(&context, path)?;
+        }
+        return Ok(());
+    }
+
     model = rewrite_model(&model, &rule_sets)?;
 
     log::info!(target: "file", "Rewritten model: {}", json!(model));
 
-    let solutions = get_minion_solutions(model)?; // ToDo we need to properly set the solver adaptor here, not hard code minion
+    // ToDo we need to properly set the solver adaptor here, not hard code minion
+    let mut solutions = match solve_with_minion(model)? {
+        SolveResult::Solutions(solutions) => solutions,
+        SolveResult::UnsatisfiableDuringRewriting(reason) => {
+            return report_unsatisfiable(&reason, &context, cli.info_json_path);
+        }
+    };
     log::info!(target: "file", "Solutions: {}", minion_solutions_to_json(&solutions));
+    if !cli.output_aux {
+        solutions = user_variables_only(&solutions);
+    }
 
     let solutions_json = minion_solutions_to_json(&solutions);
     let solutions_str = to_string_pretty(&solutions_json)?;
@@ -202,15 +485,190 @@ pub fn main() -> AnyhowResult<()> {
     }
 
     if let Some(path) = cli.info_json_path {
-        #[allow(clippy::unwrap_used)]
-        let context_obj = context.read().unwrap().clone();
-        let generated_json = &serde_json::to_value(context_obj)?;
-        let pretty_json = serde_json::to_string_pretty(&generated_json)?;
-        File::create(path)?.write_all(pretty_json.as_bytes())?;
+        save_info_json(&context, path)?;
+    }
+    Ok(())
+}
+
+/// Report a model found to have no solutions while rewriting, before the solver was run.
+fn report_unsatisfiable(
+    reason: &Unsatisfiable,
+    context: &Arc<RwLock<Context<'static>>>,
+    info_json_path: Option<PathBuf>,
+) -> AnyhowResult<()> {
+    log::info!(target: "file", "Unsatisfiable during rewriting: {}", reason);
+    println!(
+        "The model has no solutions (unsatisfiable during rewriting): {}",
+        reason
+    );
+    if let Some(path) = info_json_path {
+        save_info_json(context, path)?;
     }
     Ok(())
 }
 
+fn save_info_json(context: &Arc<RwLock<Context<'static>>>, path: PathBuf) -> AnyhowResult<()> {
+    #[allow(clippy::unwrap_used)]
+    let context_obj = context.read().unwrap().clone();
+    let generated_json = &serde_json::to_value(context_obj)?;
+    let pretty_json = serde_json::to_string_pretty(&generated_json)?;
+    File::create(path)?.write_all(pretty_json.as_bytes())?;
+    Ok(())
+}
+
+/// Replay the given script, if any, then finish rewriting the model interactively or with the rewriter,
+/// and print the result.
+fn run_rewrite(
+    model: &Model,
+    rules: &[&'static Rule<'static>],
+    rule_sets: &Vec<&'static RuleSet<'static>>,
+    interactive: bool,
+    replay: Option<&Path>,
+    script_path: &Path,
+) -> AnyhowResult<()> {
+    let mut script = match replay {
+        Some(path) => serde_json::from_reader(File::open(path)?)
+            .map_err(|e| anyhow!("Error reading script {}: {}", path.display(), e))?,
+        None => RewriteScript::default(),
+    };
+    let mut model = script.replay(model, rules)?;
+    if let Some(path) = replay {
+        println!(
+            "Replayed {} steps from {}",
+            script.steps.len(),
+            path.display()
+        );
+    }
+
+    if interactive {
+        let (new_model, steps) = rewrite_interactively(&model, rules, stdin().lock(), stdout())?;
+        model = new_model;
+        script.steps.extend(steps);
+        File::create(script_path)?.write_all(to_string_pretty(&script)?.as_bytes())?;
+        println!(
+            "Script of {} steps saved to {}",
+            script.steps.len(),
+            script_path.display()
+        );
+    } else {
+        model = rewrite_model(&model, rule_sets)?;
+    }
+
+    println!("Rewritten model:");
+    write_model(&mut stdout(), &model)?;
+    Ok(())
+}
+
+/// The rule sets for the solver family and the extra rule sets, leaving out the Simplify rule set if
+/// `no_simplify` is set.
+fn resolve_cli_rule_sets(
+    target_family: SolverFamily,
+    extra_rule_sets: &Vec<String>,
+    no_simplify: bool,
+) -> Result<Vec<&'static RuleSet<'static>>, ResolveRulesError> {
+    let mut rule_sets = resolve_rule_sets(target_family, extra_rule_sets)?;
+    if no_simplify {
+        rule_sets.retain(|rule_set| rule_set.name != "Simplify");
+    }
+    Ok(rule_sets)
+}
+
+fn run_command(
+    command: &Command,
+    target_family: SolverFamily,
+    extra_rule_sets: &Vec<String>,
+    no_simplify: bool,
+    rule_overrides: &RuleOverrides,
+) -> AnyhowResult<()> {
+    let rule_sets = resolve_cli_rule_sets(target_family, extra_rule_sets, no_simplify)
+        .map_err(|e| anyhow!("Error resolving rule sets: {}", e))?;
+    let rule_priorities = get_rule_priorities(&rule_sets)
+        .and_then(|priorities| rule_overrides.apply(priorities))
+        .map_err(|e| anyhow!("Error resolving rules: {}", e))?;
+
+    let mut enabled_rule_sets: Vec<&str> = rule_sets.iter().map(|rs| rs.name).collect();
+    enabled_rule_sets.sort();
+
+    match command {
+        Command::Rules {
+            command: RulesCommand::List { active: true },
+        } => {
+            for rule in get_rules_vec(&rule_priorities) {
+                println!("{} (priority {})", rule.name, rule_priorities[rule]);
+            }
+        }
+        Command::Rules {
+            command: RulesCommand::List { active: false },
+        } => {
+            println!(
+                "Rules marked * are active for {} with rule sets [{}]",
+                target_family,
+                enabled_rule_sets.join(", ")
+            );
+            let mut rules = get_rules();
+            rules.sort_by_key(|rule| rule.name);
+            for rule in rules {
+                let marker = if rule_priorities.contains_key(rule) {
+                    '*'
+                } else {
+                    ' '
+                };
+                println!("{} {} ({})", marker, rule.name, pretty_rule_sets(rule));
+            }
+        }
+        Command::Rules {
+            command: RulesCommand::Show { name },
+        } => {
+            let Some(rule) = get_rules().into_iter().find(|rule| rule.name == name) else {
+                bail!("No rule named {}", name);
+            };
+            println!("Rule: {}", rule.name);
+            println!("Rule sets: {}", pretty_rule_sets(rule));
+            if rule.is_model_rule() {
+                println!("Applies to: the whole model");
+            } else if let Some(variants) = rule.applicable_variants {
+                println!("Applies to: {}", variants.join(", "));
+            }
+            match rule_priorities.get(rule) {
+                Some(priority) => {
+                    println!("Active for {}: yes, priority {}", target_family, priority)
+                }
+                None => println!("Active for {}: no", target_family),
+            }
+            if !rule.doc.is_empty() {
+                println!("\n{}", rule.doc);
+            }
+        }
+        Command::Rewrite { .. } => unreachable!("the rewrite command is run by main"),
+        Command::RuleSets {
+            command: RuleSetsCommand::List,
+        } => {
+            let graph =
+                rule_set_graph().map_err(|e| anyhow!("Error resolving rule sets: {}", e))?;
+            println!("Rule sets marked * are enabled for {}", target_family);
+            for node in &graph.nodes {
+                let marker = if enabled_rule_sets.contains(&node.name.as_str()) {
+                    '*'
+                } else {
+                    ' '
+                };
+                println!("{} {}", marker, node);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The rule sets of a rule and its priority in each, e.g. `Base: 100, Minion: 90`.
+fn pretty_rule_sets(rule: &Rule) -> String {
+    rule.rule_sets
+        .iter()
+        .map(|(name, priority)| format!("{}: {}", name, priority))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use conjure_oxide::{get_example_model, get_example_model_by_path};
@@ -2,10 +2,13 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
 
 use conjure_core::context::Context;
+use conjure_core::propagate::{detect_unsatisfiable, Unsatisfiable};
+use conjure_core::stats::SolverStats;
 use serde_json::{Map, Value as JsonValue};
 use thiserror::Error as ThisError;
 
 use crate::ast::{Constant, Name};
+use crate::eliminate::restore_eliminated;
 use crate::model_from_json;
 use crate::solver::adaptors::Minion;
 use crate::solver::Solver;
@@ -63,8 +66,37 @@ pub fn parse_essence_file(
     Ok(parsed_model)
 }
 
+/// The outcome of solving a rewritten model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolveResult {
+    /// The solver ran and found these solutions, which may be none.
+    Solutions(Vec<HashMap<Name, Constant>>),
+    /// Rewriting showed the model has no solutions, so the solver was not run.
+    UnsatisfiableDuringRewriting(Unsatisfiable),
+}
+
+/// Solve the model with Minion, returning every solution. Variables eliminated from the model are added
+/// back to each solution.
+///
+/// A model found to be unsatisfiable during rewriting has no solutions; use [`solve_with_minion`] to tell
+/// this apart from a search that found none.
 pub fn get_minion_solutions(model: Model) -> Result<Vec<HashMap<Name, Constant>>, anyhow::Error> {
+    match solve_with_minion(model)? {
+        SolveResult::Solutions(solutions) => Ok(solutions),
+        SolveResult::UnsatisfiableDuringRewriting(_) => Ok(vec![]),
+    }
+}
+
+/// Solve the model with Minion, unless a top-level constraint has been reduced to `false` or a domain
+/// emptied during rewriting. In that case the reason is recorded in the solver stats and returned instead.
+pub fn solve_with_minion(model: Model) -> Result<SolveResult, anyhow::Error> {
+    if let Some(reason) = detect_unsatisfiable(&model) {
+        record_unsatisfiable(&model.context, &reason);
+        return Ok(SolveResult::UnsatisfiableDuringRewriting(reason));
+    }
+
     let solver = Solver::new(Minion::new());
+    let eliminated = model.eliminated.clone();
 
     println!("Building Minion model...");
     let solver = solver.load_model(model)?;
@@ -85,9 +117,37 @@ pub fn get_minion_solutions(model: Model) -> Result<Vec<HashMap<Name, Constant>>
     solver.save_stats_to_context();
 
     #[allow(clippy::unwrap_used)]
-    let sols = (*all_solutions_ref).lock().unwrap();
+    let mut sols = (*all_solutions_ref).lock().unwrap().clone();
+    for solution in sols.iter_mut() {
+        restore_eliminated(&eliminated, solution);
+    }
+
+    Ok(SolveResult::Solutions(sols))
+}
+
+/// Record in the context's stats a solver run that was skipped because the model is unsatisfiable.
+pub fn record_unsatisfiable(context: &Arc<RwLock<Context<'static>>>, reason: &Unsatisfiable) {
+    #[allow(clippy::unwrap_used)]
+    context.write().unwrap().stats.add_solver_run(SolverStats {
+        satisfiable: Some(false),
+        unsatisfiable_during_rewriting: Some(reason.to_string()),
+        ..Default::default()
+    });
+}
 
-    Ok((*sols).clone())
+/// The solutions restricted to the user's `find` variables, leaving out the auxiliary variables introduced
+/// while rewriting.
+pub fn user_variables_only(solutions: &[HashMap<Name, Constant>]) -> Vec<HashMap<Name, Constant>> {
+    solutions
+        .iter()
+        .map(|solution| {
+            solution
+                .iter()
+                .filter(|(name, _)| matches!(name, Name::UserName(_)))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect()
+        })
+        .collect()
 }
 
 pub fn minion_solutions_to_json(solutions: &Vec<HashMap<Name, Constant>>) -> JsonValue {
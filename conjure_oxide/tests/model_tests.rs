@@ -1,10 +1,13 @@
 // Tests for various functionalities of the Model
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use conjure_core::metadata::Metadata;
 use conjure_core::model::Model;
 use conjure_oxide::ast::*;
+use conjure_oxide::propagate::Unsatisfiable;
+use conjure_oxide::utils::conjure::{solve_with_minion, user_variables_only, SolveResult};
 
 #[test]
 fn modify_domain() {
@@ -18,7 +21,7 @@ fn modify_domain() {
 
     let mut m = Model::new(
         variables,
-        Expression::And(Metadata::new(), Vec::new()),
+        Expression::And(Metadata::new(), Vec::new().into()),
         Default::default(),
     );
 
@@ -28,3 +31,45 @@ fn modify_domain() {
 
     assert_eq!(m.variables.get(&a).unwrap().domain, d2);
 }
+
+#[test]
+fn solutions_leave_out_auxiliary_variables() {
+    let a = Name::UserName(String::from("a"));
+    let aux = Name::MachineName(0);
+    let solutions = vec![HashMap::from([
+        (a.clone(), Constant::Int(1)),
+        (aux, Constant::Int(2)),
+    ])];
+
+    let projected = user_variables_only(&solutions);
+
+    assert_eq!(projected, vec![HashMap::from([(a, Constant::Int(1))])]);
+}
+
+#[test]
+fn constraint_folded_to_false_is_reported_without_solving() {
+    let a = Name::UserName(String::from("a"));
+    let collapsed = Expression::Lt(
+        Metadata::new(),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(1))),
+    );
+    let mut variables = HashMap::new();
+    variables.insert(
+        a,
+        DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(1, 3)])),
+    );
+    let m = Model::new(variables, collapsed.clone(), Default::default());
+    let context = m.context.clone();
+
+    let result = solve_with_minion(m).unwrap();
+
+    assert_eq!(
+        result,
+        SolveResult::UnsatisfiableDuringRewriting(Unsatisfiable::FalseConstraint(collapsed))
+    );
+    let stats = &context.read().unwrap().stats.solver_runs;
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].satisfiable, Some(false));
+    assert!(stats[0].unsatisfiable_during_rewriting.is_some());
+}
@@ -2,16 +2,27 @@ use core::panic;
 use std::collections::HashMap;
 use std::env;
 use std::process::exit;
+use std::sync::Arc;
 
+use conjure_core::eliminate::restore_eliminated;
+use conjure_core::propagate::detect_unsatisfiable;
+use conjure_core::rule_engine::{
+    pattern_rule, register_rule, ApplicationError, ApplicationResult, Reduction,
+};
 use conjure_core::rules::eval_constant;
 use conjure_core::solver::SolverFamily;
 use conjure_oxide::{
     ast::*,
     get_rule_by_name, get_rules,
-    rule_engine::{resolve_rule_sets, rewrite_model},
+    interactive::rewrite_interactively,
+    rule_engine::{
+        self, get_rule_priorities, get_rules_vec, load_rules, resolve_rule_sets, rewrite_model,
+        rewrite_model_all, rule_set_graph, ResolveRulesError, RewriteError, RewritePolicy,
+        RewriteScript, RewriteStep, RuleFileError, RuleOverrides,
+    },
     solver::{adaptors, Solver},
     utils::testing::save_stats_json,
-    Metadata, Model, Rule,
+    Metadata, Model, Rule, RuleSet,
 };
 use uniplate::uniplate::Uniplate;
 
@@ -21,6 +32,27 @@ fn rules_present() {
     assert!(!rules.is_empty());
 }
 
+#[test]
+fn rules_applicable_variants() {
+    let not_expr = Expression::Not(
+        Metadata::new(),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
+    );
+    let sum_expr = Expression::Sum(
+        Metadata::new(),
+        vec![Expression::Constant(Metadata::new(), Constant::Int(1))].into(),
+    );
+
+    let remove_double_negation = get_rule_by_name("remove_double_negation").unwrap();
+    assert!(remove_double_negation.applies_to(&not_expr));
+    assert!(!remove_double_negation.applies_to(&sum_expr));
+
+    // Rules without declared variants are candidates for any expression
+    let apply_eval_constant = get_rule_by_name("apply_eval_constant").unwrap();
+    assert!(apply_eval_constant.applies_to(&not_expr));
+    assert!(apply_eval_constant.applies_to(&sum_expr));
+}
+
 #[test]
 fn sum_of_constants() {
     let valid_sum_expression = Expression::Sum(
@@ -29,7 +61,8 @@ fn sum_of_constants() {
             Expression::Constant(Metadata::new(), Constant::Int(1)),
             Expression::Constant(Metadata::new(), Constant::Int(2)),
             Expression::Constant(Metadata::new(), Constant::Int(3)),
-        ],
+        ]
+        .into(),
     );
 
     let invalid_sum_expression = Expression::Sum(
@@ -37,7 +70,8 @@ fn sum_of_constants() {
         vec![
             Expression::Constant(Metadata::new(), Constant::Int(1)),
             Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
-        ],
+        ]
+        .into(),
     );
 
     match evaluate_sum_of_constants(&valid_sum_expression) {
@@ -54,7 +88,7 @@ fn evaluate_sum_of_constants(expr: &Expression) -> Option<i32> {
     match expr {
         Expression::Sum(_metadata, expressions) => {
             let mut sum = 0;
-            for e in expressions {
+            for e in expressions.iter() {
                 match e {
                     Expression::Constant(_, Constant::Int(value)) => {
                         sum += value;
@@ -72,7 +106,7 @@ fn evaluate_sum_of_constants(expr: &Expression) -> Option<i32> {
 fn recursive_sum_of_constants() {
     let complex_expression = Expression::Eq(
         Metadata::new(),
-        Box::new(Expression::Sum(
+        Arc::new(Expression::Sum(
             Metadata::new(),
             vec![
                 Expression::Constant(Metadata::new(), Constant::Int(1)),
@@ -82,25 +116,28 @@ fn recursive_sum_of_constants() {
                     vec![
                         Expression::Constant(Metadata::new(), Constant::Int(1)),
                         Expression::Constant(Metadata::new(), Constant::Int(2)),
-                    ],
+                    ]
+                    .into(),
                 ),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
-            ],
+            ]
+            .into(),
         )),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
     );
     let correct_simplified_expression = Expression::Eq(
         Metadata::new(),
-        Box::new(Expression::Sum(
+        Arc::new(Expression::Sum(
             Metadata::new(),
             vec![
                 Expression::Constant(Metadata::new(), Constant::Int(1)),
                 Expression::Constant(Metadata::new(), Constant::Int(2)),
                 Expression::Constant(Metadata::new(), Constant::Int(3)),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
-            ],
+            ]
+            .into(),
         )),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
     );
 
     let simplified_expression = simplify_expression(complex_expression.clone());
@@ -117,19 +154,24 @@ fn simplify_expression(expr: Expression) -> Expression {
             } else {
                 Expression::Sum(
                     Metadata::new(),
-                    expressions.into_iter().map(simplify_expression).collect(),
+                    expressions
+                        .iter()
+                        .cloned()
+                        .map(simplify_expression)
+                        .collect::<Vec<_>>()
+                        .into(),
                 )
             }
         }
         Expression::Eq(_metadata, left, right) => Expression::Eq(
             Metadata::new(),
-            Box::new(simplify_expression(*left)),
-            Box::new(simplify_expression(*right)),
+            Arc::new(simplify_expression(left.as_ref().clone())),
+            Arc::new(simplify_expression(right.as_ref().clone())),
         ),
         Expression::Geq(_metadata, left, right) => Expression::Geq(
             Metadata::new(),
-            Box::new(simplify_expression(*left)),
-            Box::new(simplify_expression(*right)),
+            Arc::new(simplify_expression(left.as_ref().clone())),
+            Arc::new(simplify_expression(right.as_ref().clone())),
         ),
         _ => expr,
     }
@@ -146,7 +188,8 @@ fn rule_sum_constants() {
             Expression::Constant(Metadata::new(), Constant::Int(1)),
             Expression::Constant(Metadata::new(), Constant::Int(2)),
             Expression::Constant(Metadata::new(), Constant::Int(3)),
-        ],
+        ]
+        .into(),
     );
 
     expr = sum_constants
@@ -174,7 +217,8 @@ fn rule_sum_mixed() {
             Expression::Constant(Metadata::new(), Constant::Int(1)),
             Expression::Constant(Metadata::new(), Constant::Int(2)),
             Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
-        ],
+        ]
+        .into(),
     );
 
     expr = sum_constants
@@ -190,6 +234,7 @@ fn rule_sum_mixed() {
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
                 Expression::Constant(Metadata::new(), Constant::Int(3)),
             ]
+            .into()
         )
     );
 }
@@ -200,14 +245,15 @@ fn rule_sum_geq() {
 
     let mut expr = Expression::Geq(
         Metadata::new(),
-        Box::new(Expression::Sum(
+        Arc::new(Expression::Sum(
             Metadata::new(),
             vec![
                 Expression::Constant(Metadata::new(), Constant::Int(1)),
                 Expression::Constant(Metadata::new(), Constant::Int(2)),
-            ],
+            ]
+            .into(),
         )),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
     );
 
     expr = flatten_sum_geq
@@ -222,10 +268,1205 @@ fn rule_sum_geq() {
             vec![
                 Expression::Constant(Metadata::new(), Constant::Int(1)),
                 Expression::Constant(Metadata::new(), Constant::Int(2)),
-            ],
-            Box::new(Expression::Constant(Metadata::new(), Constant::Int(3)))
+            ]
+            .into(),
+            Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3)))
+        )
+    );
+}
+
+#[test]
+fn rule_sum_eq_either_side() {
+    let sum_eq_to_sumeq = get_rule_by_name("sum_eq_to_sumeq").unwrap();
+
+    let sum = Expression::Sum(
+        Metadata::new(),
+        vec![
+            Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
+            Expression::Reference(Metadata::new(), Name::UserName(String::from("b"))),
+        ]
+        .into(),
+    );
+    let c = Expression::Reference(Metadata::new(), Name::UserName(String::from("c")));
+    let expected = Expression::SumEq(Metadata::new(), sum.children().into(), Arc::new(c.clone()));
+
+    for expr in [
+        Expression::Eq(Metadata::new(), Arc::new(sum.clone()), Arc::new(c.clone())),
+        Expression::Eq(Metadata::new(), Arc::new(c.clone()), Arc::new(sum.clone())),
+    ] {
+        let result = sum_eq_to_sumeq
+            .apply(&expr, &Model::new_empty(Default::default()))
+            .unwrap()
+            .new_expression;
+        assert_eq!(result, expected);
+    }
+
+    let nested = Expression::Eq(
+        Metadata::new(),
+        Arc::new(Expression::Sum(Metadata::new(), vec![sum.clone()].into())),
+        Arc::new(c),
+    );
+    assert!(sum_eq_to_sumeq
+        .apply(&nested, &Model::new_empty(Default::default()))
+        .is_err());
+}
+
+fn negate(expr: &Expression) -> Expression {
+    Expression::Not(Metadata::new(), Arc::new(expr.clone()))
+}
+
+pattern_rule! {
+    #[register_rule(("TestPatterns", 10))]
+    fn test_negate_after_true {
+        and([true, x, ..rest]) if !rest.is_empty() => and([{ negate(x) }, ..rest]),
+    }
+}
+
+#[test]
+fn pattern_rule_list_tail_and_helper() {
+    let rule = get_rule_by_name("test_negate_after_true").unwrap();
+    assert_eq!(rule.applicable_variants, Some(&["And"][..]));
+
+    let a = Expression::Reference(Metadata::new(), Name::UserName(String::from("a")));
+    let b = Expression::Reference(Metadata::new(), Name::UserName(String::from("b")));
+    let t = Expression::Constant(Metadata::new(), Constant::Bool(true));
+
+    let expr = Expression::And(
+        Metadata::new(),
+        vec![t.clone(), a.clone(), b.clone()].into(),
+    );
+    let result = rule
+        .apply(&expr, &Model::new_empty(Default::default()))
+        .unwrap()
+        .new_expression;
+    assert_eq!(
+        result,
+        Expression::And(Metadata::new(), vec![negate(&a), b].into())
+    );
+
+    // The guard rejects an empty tail
+    let expr = Expression::And(Metadata::new(), vec![t, a].into());
+    assert!(rule
+        .apply(&expr, &Model::new_empty(Default::default()))
+        .is_err());
+}
+
+/// The value of `expr` if it is a variable with only one value in the model.
+fn fixed_value(expr: &Expression, model: &Model) -> Option<i32> {
+    let Expression::Reference(_, name) = expr else {
+        return None;
+    };
+    match model.variables.get(name)?.domain.values_i32()?.as_slice() {
+        [value] => Some(*value),
+        _ => None,
+    }
+}
+
+pattern_rule! {
+    #[register_rule(("TestPatterns", 10))]
+    fn test_eq_of_fixed_variables {
+        x == y if fixed_value(x, model).is_some_and(|v| fixed_value(y, model) == Some(v)) => true,
+    }
+}
+
+#[test]
+fn pattern_rule_guard_sees_the_model() {
+    let rule = get_rule_by_name("test_eq_of_fixed_variables").unwrap();
+    let a = Expression::Reference(Metadata::new(), Name::UserName(String::from("a")));
+    let b = Expression::Reference(Metadata::new(), Name::UserName(String::from("b")));
+    let expr = Expression::Eq(Metadata::new(), Arc::new(a), Arc::new(b));
+
+    let mut model = Model::new_empty(Default::default());
+    for name in ["a", "b"] {
+        model.variables.insert(
+            Name::UserName(String::from(name)),
+            DecisionVariable::new(Domain::IntDomain(vec![Range::Single(2)])),
+        );
+    }
+    assert_eq!(
+        rule.apply(&expr, &model).unwrap().new_expression,
+        Expression::Constant(Metadata::new(), Constant::Bool(true))
+    );
+
+    model.update_domain(
+        &Name::UserName(String::from("b")),
+        Domain::IntDomain(vec![Range::Bounded(1, 2)]),
+    );
+    assert!(rule.apply(&expr, &model).is_err());
+}
+
+#[test]
+fn pattern_rules_keep_the_metadata_of_the_matched_expression() {
+    // flatten_sum_geq is a pattern rule in the Minion rule set
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let priorities = get_rule_priorities(&rule_sets).unwrap();
+    assert!(priorities.contains_key(get_rule_by_name("flatten_sum_geq").unwrap()));
+
+    let a = Expression::Reference(Metadata::new(), Name::UserName(String::from("a")));
+    let b = Expression::Reference(Metadata::new(), Name::UserName(String::from("b")));
+    let c = Expression::Reference(Metadata::new(), Name::UserName(String::from("c")));
+    let metadata = Metadata {
+        clean: true,
+        etype: Some(ReturnType::Bool),
+    };
+    let geq = Expression::Geq(
+        metadata,
+        Arc::new(Expression::Sum(Metadata::new(), vec![a, b].into())),
+        Arc::new(c),
+    );
+
+    let mut model = Model::new_empty(Default::default());
+    for name in ["a", "b", "c"] {
+        model.variables.insert(
+            Name::UserName(String::from(name)),
+            DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(1, 3)])),
+        );
+    }
+    model.constraints = geq;
+
+    let rewritten = rewrite_model(&model, &rule_sets).unwrap().constraints;
+    let Expression::SumGeq(metadata, _, _) = &rewritten else {
+        panic!("expected a SumGeq, got {:?}", rewritten);
+    };
+    assert_eq!(metadata.etype, Some(ReturnType::Bool));
+}
+
+#[test]
+fn rule_file_rules_are_registered() {
+    load_rules(
+        r#"
+        rule_set "TestRuleFile", 200, ()
+
+        rule test_file_negated_lt ("TestRuleFile", 10):
+            !(a < b) => a >= b
+        "#,
+    )
+    .unwrap();
+
+    let rule_sets =
+        resolve_rule_sets(SolverFamily::Minion, &vec!["TestRuleFile".to_string()]).unwrap();
+    let priorities = get_rule_priorities(&rule_sets).unwrap();
+    let rule = get_rule_by_name("test_file_negated_lt").unwrap();
+    assert_eq!(priorities.get(rule), Some(&10));
+    assert_eq!(rule.applicable_variants, Some(&["Not"][..]));
+    assert_eq!(rule.doc, "!(a < b) => a >= b");
+
+    let a = Arc::new(Expression::Reference(
+        Metadata::new(),
+        Name::UserName(String::from("a")),
+    ));
+    let b = Arc::new(Expression::Reference(
+        Metadata::new(),
+        Name::UserName(String::from("b")),
+    ));
+    let expr = Expression::Not(
+        Metadata::new(),
+        Arc::new(Expression::Lt(Metadata::new(), a.clone(), b.clone())),
+    );
+    let result = rule
+        .apply(&expr, &Model::new_empty(Default::default()))
+        .unwrap()
+        .new_expression;
+    assert_eq!(result, Expression::Geq(Metadata::new(), a, b));
+
+    // Loading the same rule set again is rejected, and registers nothing
+    let reloaded = load_rules(
+        r#"
+        rule_set "TestRuleFile", 200, ()
+
+        rule test_file_unused ("TestRuleFile", 10):
+            !!x => x
+        "#,
+    );
+    assert!(matches!(reloaded, Err(RuleFileError::DuplicateRuleSet(_))));
+    assert!(get_rule_by_name("test_file_unused").is_none());
+}
+
+#[test]
+fn rule_doc_comments_are_recorded() {
+    // A `/** ... */` comment on a pattern rule, with the leading `*`s removed
+    let lt_to_ineq = get_rule_by_name("lt_to_ineq").unwrap();
+    assert!(lt_to_ineq.doc.starts_with("Convert a Lt to an Ineq:"));
+    assert!(lt_to_ineq.doc.contains("\na < b => a - b < -1\n"));
+
+    // An indented block comment on a `register_rule` function
+    let expand_bubble = get_rule_by_name("expand_bubble").unwrap();
+    assert!(expand_bubble
+        .doc
+        .starts_with("Reduce bubbles with a boolean expression"));
+    assert!(!expand_bubble.doc.ends_with('\n'));
+}
+
+#[test]
+fn rule_overrides_apply() {
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let priorities = get_rule_priorities(&rule_sets).unwrap();
+    let lt_to_ineq = get_rule_by_name("lt_to_ineq").unwrap();
+    let eval_constant = get_rule_by_name("apply_eval_constant").unwrap();
+    assert!(priorities.contains_key(lt_to_ineq));
+    assert!(!priorities.contains_key(eval_constant));
+
+    // A rule given a priority is enabled, even though the Constant rule set is not
+    let overrides = RuleOverrides {
+        disabled_rules: vec!["lt_to_ineq".to_string()],
+        priorities: [("apply_eval_constant".to_string(), 5)].into(),
+        ..Default::default()
+    };
+    let overridden = overrides.apply(priorities.clone()).unwrap();
+    assert!(!overridden.contains_key(lt_to_ineq));
+    assert_eq!(overridden.get(eval_constant), Some(&5));
+    assert_eq!(overridden.len(), priorities.len());
+
+    let overrides = RuleOverrides {
+        only_rules: vec!["*_to_ineq".to_string()],
+        ..Default::default()
+    };
+    let overridden = overrides.apply(priorities.clone()).unwrap();
+    assert_eq!(overridden.len(), 4);
+    assert!(overridden
+        .keys()
+        .all(|rule| rule.name.ends_with("_to_ineq")));
+
+    let overrides = RuleOverrides {
+        disabled_rules: vec!["no_such_rule".to_string()],
+        ..Default::default()
+    };
+    assert!(matches!(
+        overrides.apply(priorities.clone()),
+        Err(ResolveRulesError::RuleNotFound(_))
+    ));
+
+    let overrides = RuleOverrides {
+        only_rules: vec!["no_such_*".to_string()],
+        ..Default::default()
+    };
+    assert!(matches!(
+        overrides.apply(priorities),
+        Err(ResolveRulesError::NoRulesMatch(_))
+    ));
+}
+
+#[test]
+fn rewrite_uses_rule_overrides_from_context() {
+    let lt = Expression::Lt(
+        Metadata::new(),
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(String::from("a")),
+        )),
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(String::from("b")),
+        )),
+    );
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+
+    let model = Model::new(HashMap::new(), lt.clone(), Default::default());
+    let rewritten = rewrite_model(&model, &rule_sets).unwrap();
+    assert!(matches!(rewritten.constraints, Expression::Ineq(..)));
+
+    let model = Model::new(HashMap::new(), lt.clone(), Default::default());
+    model.context.write().unwrap().rule_overrides.disabled_rules = vec!["lt_to_ineq".to_string()];
+    let rewritten = rewrite_model(&model, &rule_sets).unwrap();
+    assert_eq!(rewritten.constraints, lt);
+}
+
+#[test]
+fn rewriter_records_rule_stats() {
+    let lt = Expression::Lt(
+        Metadata::new(),
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(String::from("a")),
+        )),
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(String::from("b")),
+        )),
+    );
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let model = Model::new(HashMap::new(), lt, Default::default());
+    rewrite_model(&model, &rule_sets).unwrap();
+
+    let context = model.context.read().unwrap();
+    let stats = context.stats.rewriter_runs.last().unwrap();
+
+    let lt_to_ineq = &stats.rule_stats["lt_to_ineq"];
+    assert_eq!(lt_to_ineq.attempts, 1);
+    assert_eq!(lt_to_ineq.applications, 1);
+
+    let attempts: usize = stats.rule_stats.values().map(|s| s.attempts).sum();
+    let applications: usize = stats.rule_stats.values().map(|s| s.applications).sum();
+    assert_eq!(Some(attempts), stats.rewriter_rule_application_attempts);
+    assert_eq!(Some(applications), stats.rewriter_rule_applications);
+    assert_eq!(stats.rewriter_passes, Some(applications + 1));
+
+    let minion = &stats.rule_set_stats["Minion"];
+    assert!(minion.applications >= 1);
+    assert!(minion.attempts >= lt_to_ineq.attempts);
+    assert!(stats.rule_set_stats.contains_key("Base"));
+}
+
+/// Offers two encodings of `a != b`: `a < b \/ a > b`, or the cheaper `!(a = b)`.
+#[register_rule(("TestChoice", 10), applies_to(Neq))]
+fn test_neq_choice(expr: &Expression, _: &Model) -> ApplicationResult {
+    let Expression::Neq(_, a, b) = expr else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    Reduction::choice([
+        Reduction::pure(Expression::Or(
+            Metadata::new(),
+            vec![
+                Expression::Lt(Metadata::new(), a.clone(), b.clone()),
+                Expression::Gt(Metadata::new(), a.clone(), b.clone()),
+            ]
+            .into(),
+        )),
+        Reduction::pure(Expression::Not(
+            Metadata::new(),
+            Arc::new(Expression::Eq(Metadata::new(), a.clone(), b.clone())),
+        )),
+    ])
+}
+
+/// Rewrites `expr` with only `test_neq_choice` enabled, using the given policy.
+#[allow(clippy::unwrap_used)]
+fn rewrite_with_choices(
+    expr: &Expression,
+    policy: RewritePolicy,
+    max_models: Option<usize>,
+) -> Vec<Expression> {
+    let model = Model::new(HashMap::new(), expr.clone(), Default::default());
+    {
+        let mut context = model.context.write().unwrap();
+        context.rule_overrides = RuleOverrides {
+            only_rules: vec!["test_neq_choice".to_string()],
+            priorities: [("test_neq_choice".to_string(), 10)].into(),
+            ..Default::default()
+        };
+        context.rewrite_policy = policy;
+    }
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    rewrite_model_all(&model, &rule_sets, max_models)
+        .unwrap()
+        .into_iter()
+        .map(|model| model.constraints)
+        .collect()
+}
+
+#[test]
+fn rewrite_policies_choose_between_alternatives() {
+    let var = |name: &str| {
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(String::from(name)),
+        ))
+    };
+    let (a, b, c) = (var("a"), var("b"), var("c"));
+    let lt_or_gt = |x: &Arc<Expression>, y: &Arc<Expression>| {
+        Expression::Or(
+            Metadata::new(),
+            vec![
+                Expression::Lt(Metadata::new(), x.clone(), y.clone()),
+                Expression::Gt(Metadata::new(), x.clone(), y.clone()),
+            ]
+            .into(),
+        )
+    };
+    let not_eq = |x: &Arc<Expression>, y: &Arc<Expression>| {
+        Expression::Not(
+            Metadata::new(),
+            Arc::new(Expression::Eq(Metadata::new(), x.clone(), y.clone())),
+        )
+    };
+    let and = |x: Expression, y: Expression| Expression::And(Metadata::new(), vec![x, y].into());
+
+    let expr = and(
+        Expression::Neq(Metadata::new(), a.clone(), b.clone()),
+        Expression::Neq(Metadata::new(), b.clone(), c.clone()),
+    );
+
+    assert_eq!(
+        rewrite_with_choices(&expr, RewritePolicy::First, None),
+        vec![and(lt_or_gt(&a, &b), lt_or_gt(&b, &c))]
+    );
+    assert_eq!(
+        rewrite_with_choices(&expr, RewritePolicy::Cheapest, None),
+        vec![and(not_eq(&a, &b), not_eq(&b, &c))]
+    );
+
+    // Alternatives are found below the root, and every combination is emitted, in order
+    assert_eq!(
+        rewrite_with_choices(&expr, RewritePolicy::All, None),
+        vec![
+            and(lt_or_gt(&a, &b), lt_or_gt(&b, &c)),
+            and(lt_or_gt(&a, &b), not_eq(&b, &c)),
+            and(not_eq(&a, &b), lt_or_gt(&b, &c)),
+            and(not_eq(&a, &b), not_eq(&b, &c)),
+        ]
+    );
+
+    // A limit keeps the first models
+    assert_eq!(
+        rewrite_with_choices(&expr, RewritePolicy::All, Some(2)),
+        vec![
+            and(lt_or_gt(&a, &b), lt_or_gt(&b, &c)),
+            and(lt_or_gt(&a, &b), not_eq(&b, &c)),
+        ]
+    );
+
+    // With the default policy, `rewrite_model` takes the first alternative
+    let model = Model::new(HashMap::new(), expr.clone(), Default::default());
+    model.context.write().unwrap().rule_overrides = RuleOverrides {
+        only_rules: vec!["test_neq_choice".to_string()],
+        priorities: [("test_neq_choice".to_string(), 10)].into(),
+        ..Default::default()
+    };
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    assert_eq!(
+        rewrite_model(&model, &rule_sets).unwrap().constraints,
+        and(lt_or_gt(&a, &b), lt_or_gt(&b, &c))
+    );
+}
+
+/// Rewrites `constraints` over `a`, `b` and `c` in `1..3` into every model the Minion rules offer.
+#[allow(clippy::unwrap_used)]
+fn rewrite_all_minion(constraints: Expression) -> Vec<Expression> {
+    let domain = Domain::IntDomain(vec![Range::Bounded(1, 3)]);
+    let variables = ["a", "b", "c"]
+        .into_iter()
+        .map(|name| {
+            (
+                Name::UserName(name.to_string()),
+                DecisionVariable::new(domain.clone()),
+            )
+        })
+        .collect();
+    let model = Model::new(variables, constraints, Default::default());
+    model.context.write().unwrap().rewrite_policy = RewritePolicy::All;
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    rewrite_model_all(&model, &rule_sets, None)
+        .unwrap()
+        .into_iter()
+        .map(|model| model.constraints)
+        .collect()
+}
+
+fn count(expr: &Expression, variant: &str) -> usize {
+    expr.universe()
+        .iter()
+        .filter(|e| e.variant_name() == variant)
+        .count()
+}
+
+#[test]
+fn all_diff_is_native_or_pairwise_neq() {
+    let var = |name: &str| Expression::Reference(Metadata::new(), Name::UserName(name.into()));
+    let models = rewrite_all_minion(Expression::AllDiff(
+        Metadata::new(),
+        vec![var("a"), var("b"), var("c")].into(),
+    ));
+
+    assert_eq!(models.len(), 2);
+    assert_eq!(count(&models[0], "AllDiff"), 1);
+    assert_eq!(count(&models[1], "AllDiff"), 0);
+    assert_eq!(count(&models[1], "Neq"), 3);
+}
+
+#[test]
+fn min_is_decomposed_or_native() {
+    let var = |name: &str| Expression::Reference(Metadata::new(), Name::UserName(name.into()));
+    let min = Expression::Min(Metadata::new(), vec![var("a"), var("b")].into());
+    let models = rewrite_all_minion(Expression::Leq(
+        Metadata::new(),
+        Arc::new(min),
+        Arc::new(var("c")),
+    ));
+
+    assert_eq!(models.len(), 2);
+    for model in &models {
+        assert_eq!(count(model, "Min"), 0);
+    }
+    assert_eq!(count(&models[0], "MinEq"), 0);
+    assert_eq!(count(&models[1], "MinEq"), 1);
+}
+
+/// `!!(a < b) /\\ a <= 3`, which the Minion rules take several steps to rewrite.
+fn double_negation_model() -> Model {
+    let var = |name: &str| {
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(String::from(name)),
+        ))
+    };
+    let not = |expr: Expression| Expression::Not(Metadata::new(), Arc::new(expr));
+    let constraints = Expression::And(
+        Metadata::new(),
+        vec![
+            not(not(Expression::Lt(Metadata::new(), var("a"), var("b")))),
+            Expression::Leq(
+                Metadata::new(),
+                var("a"),
+                Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
+            ),
+        ]
+        .into(),
+    );
+    Model::new(HashMap::new(), constraints, Default::default())
+}
+
+#[test]
+fn apply_all_rules_follows_rewriter_order() {
+    let model = double_negation_model();
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let rules = get_rules_vec(&get_rule_priorities(&rule_sets).unwrap());
+
+    let candidates = rule_engine::apply_all_rules(&model, &rules);
+    assert!(candidates.len() > 1);
+    for candidate in &candidates {
+        assert!(candidate.rule.apply(&candidate.expression, &model).is_ok());
+    }
+
+    // Always making the first rule application is what the rewriter does
+    let mut stepped = model.clone();
+    while let Some(candidate) = rule_engine::apply_all_rules(&stepped, &rules)
+        .into_iter()
+        .next()
+    {
+        candidate.apply(&mut stepped).unwrap();
+    }
+    let rewritten = rewrite_model(&model, &rule_sets).unwrap();
+    assert_eq!(stepped.constraints, rewritten.constraints);
+
+    // A finder kept between steps finds the same first rule application each time
+    let mut finder = rule_engine::CandidateFinder::new(&rules);
+    let mut stepped = model.clone();
+    while let Some(candidate) = finder.first(&stepped) {
+        let expected = rule_engine::apply_all_rules(&stepped, &rules);
+        assert_eq!(candidate.step(), expected[0].step());
+        assert_eq!(finder.all(&stepped).len(), expected.len());
+        finder.apply(candidate, &mut stepped).unwrap();
+    }
+    assert_eq!(stepped.constraints, rewritten.constraints);
+}
+
+#[test]
+fn applying_a_candidate_at_a_missing_path_is_an_error() {
+    let model = double_negation_model();
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let rules = get_rules_vec(&get_rule_priorities(&rule_sets).unwrap());
+    let mut candidate = rule_engine::apply_all_rules(&model, &rules).remove(0);
+    candidate.path = vec![5];
+
+    let mut applied = model.clone();
+    assert!(matches!(
+        candidate.apply(&mut applied),
+        Err(RewriteError::PathNotFound(path)) if path == vec![5]
+    ));
+    assert_eq!(applied, model);
+}
+
+#[test]
+fn interactive_rewrite_records_replayable_script() {
+    let model = double_negation_model();
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let rules = get_rules_vec(&get_rule_priorities(&rule_sets).unwrap());
+    let candidates = rule_engine::apply_all_rules(&model, &rules);
+
+    let input = "1\nu\nu\nnonsense\n1\nr\n";
+    let mut output = Vec::new();
+    let (rewritten, steps) =
+        rewrite_interactively(&model, &rules, input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("Nothing to undo."));
+    assert!(output.contains("Unknown choice: nonsense"));
+    assert!(output.contains("No rules apply."));
+    assert_eq!(steps[0], candidates[1].step());
+    assert!(rule_engine::apply_all_rules(&rewritten, &rules).is_empty());
+
+    let script = RewriteScript { steps };
+    let json = serde_json::to_string(&script).unwrap();
+    let script: RewriteScript = serde_json::from_str(&json).unwrap();
+    assert_eq!(script.replay(&model, &rules).unwrap(), rewritten);
+
+    let bad_script = RewriteScript {
+        steps: vec![RewriteStep {
+            rule: "lt_to_ineq".to_string(),
+            path: vec![5],
+            alternative: 0,
+        }],
+    };
+    assert!(matches!(
+        bad_script.replay(&model, &rules),
+        Err(RewriteError::StepNotApplicable(1, _))
+    ));
+}
+
+/// `x <= k` tightens the domain of `x` to at most `k`.
+#[register_rule(("TestModelRules", 10), applies_to(Leq))]
+fn test_bound_from_leq(expr: &Expression, model: &Model) -> ApplicationResult {
+    let Expression::Leq(_, x, k) = expr else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    let (Expression::Reference(_, name), Expression::Constant(_, Constant::Int(k))) =
+        (x.as_ref(), k.as_ref())
+    else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    let Some(Domain::IntDomain(ranges)) = model.get_domain(name) else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    let [Range::Bounded(lo, hi)] = ranges.as_slice() else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    if hi <= k || lo > k {
+        return Err(ApplicationError::RuleNotApplicable);
+    }
+    Ok(Reduction::pure(true.into()).update_domain(
+        name.clone(),
+        Domain::IntDomain(vec![Range::Bounded(*lo, *k)]),
+    ))
+}
+
+/// Removes the variables that no constraint refers to.
+#[register_rule(("TestModelRules", 10))]
+fn test_remove_unused_variables(model: &Model) -> Result<Model, ApplicationError> {
+    let used: Vec<Name> = model
+        .constraints
+        .universe()
+        .into_iter()
+        .filter_map(|expr| match expr {
+            Expression::Reference(_, name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    let mut new_model = model.clone();
+    new_model.variables.retain(|name, _| used.contains(name));
+    if new_model.variables.len() == model.variables.len() {
+        return Err(ApplicationError::RuleNotApplicable);
+    }
+    Ok(new_model)
+}
+
+#[test]
+fn rules_can_change_and_remove_variables() {
+    let var = |name: &str| {
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(String::from(name)),
+        ))
+    };
+    let one_to_ten = || DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(1, 10)]));
+    let variables = ["x", "y", "z"]
+        .into_iter()
+        .map(|name| (Name::UserName(String::from(name)), one_to_ten()))
+        .collect();
+    let constraints = Expression::And(
+        Metadata::new(),
+        vec![
+            Expression::Leq(
+                Metadata::new(),
+                var("x"),
+                Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3))),
+            ),
+            Expression::Neq(Metadata::new(), var("x"), var("z")),
+        ]
+        .into(),
+    );
+    let model = Model::new(variables, constraints, Default::default());
+    model.context.write().unwrap().rule_overrides = RuleOverrides {
+        only_rules: vec![
+            "test_*_from_leq".to_string(),
+            "test_remove_unused_*".to_string(),
+        ],
+        priorities: [
+            ("test_bound_from_leq".to_string(), 10),
+            ("test_remove_unused_variables".to_string(), 20),
+        ]
+        .into(),
+        ..Default::default()
+    };
+
+    let remove_unused = get_rule_by_name("test_remove_unused_variables").unwrap();
+    assert!(remove_unused.is_model_rule());
+    assert!(!remove_unused.applies_to(&model.constraints));
+
+    // Expression rules come first, even though the model rule has a higher priority
+    let rules = vec![
+        remove_unused,
+        get_rule_by_name("test_bound_from_leq").unwrap(),
+    ];
+    let candidates = rule_engine::apply_all_rules(&model, &rules);
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0].rule.name, "test_bound_from_leq");
+    assert_eq!(candidates[1].rule.name, "test_remove_unused_variables");
+    assert!(candidates[1].path.is_empty());
+
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let rewritten = rewrite_model(&model, &rule_sets).unwrap();
+
+    assert_eq!(
+        rewritten.constraints,
+        Expression::And(
+            Metadata::new(),
+            vec![
+                true.into(),
+                Expression::Neq(Metadata::new(), var("x"), var("z"))
+            ]
+            .into()
+        )
+    );
+    assert_eq!(rewritten.variables.len(), 2);
+    assert!(!rewritten
+        .variables
+        .contains_key(&Name::UserName(String::from("y"))));
+    assert_eq!(
+        rewritten.get_domain(&Name::UserName(String::from("x"))),
+        Some(&Domain::IntDomain(vec![Range::Bounded(1, 3)]))
+    );
+
+    let context = rewritten.context.read().unwrap();
+    let stats = context.stats.rewriter_runs.last().unwrap();
+    assert_eq!(stats.rule_stats["test_bound_from_leq"].applications, 1);
+    assert_eq!(
+        stats.rule_stats["test_remove_unused_variables"].applications,
+        1
+    );
+}
+
+#[test]
+fn equal_subexpressions_share_an_aux_var() {
+    let var =
+        |name: &str| Expression::Reference(Metadata::new(), Name::UserName(String::from(name)));
+    let constant = |n: i32| Arc::new(Expression::Constant(Metadata::new(), Constant::Int(n)));
+    let min = |a: &str, b: &str| Expression::Min(Metadata::new(), vec![var(a), var(b)].into());
+    let safe_div = |a: &str, b: &str| {
+        Expression::SafeDiv(
+            Metadata::new(),
+            Arc::new(Expression::Sum(
+                Metadata::new(),
+                vec![var(a), var(b)].into(),
+            )),
+            Arc::new(var(b)),
+        )
+    };
+    let variables: SymbolTable = ["a", "b", "c"]
+        .into_iter()
+        .map(|name| {
+            (
+                Name::UserName(String::from(name)),
+                DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(1, 5)])),
+            )
+        })
+        .collect();
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+
+    assert_eq!(
+        Expression::And(Metadata::new(), vec![var("a"), min("b", "c")].into())
+            .commutative_normal_form(),
+        Expression::And(Metadata::new(), vec![min("c", "b"), var("a")].into())
+            .commutative_normal_form()
+    );
+    assert_ne!(
+        min("a", "b").commutative_normal_form(),
+        min("a", "c").commutative_normal_form()
+    );
+
+    // min(a, b) <= c /\ min(b, a) >= 2 /\ min(a, c) >= 1
+    let constraints = Expression::And(
+        Metadata::new(),
+        vec![
+            Expression::Leq(Metadata::new(), Arc::new(min("a", "b")), Arc::new(var("c"))),
+            Expression::Geq(Metadata::new(), Arc::new(min("b", "a")), constant(2)),
+            Expression::Geq(Metadata::new(), Arc::new(min("a", "c")), constant(1)),
+        ]
+        .into(),
+    );
+    let model = Model::new(variables.clone(), constraints, Default::default());
+    let rewritten = rewrite_model(&model, &rule_sets).unwrap();
+    assert_eq!(rewritten.variables.len(), 5);
+
+    // (a + b) / b <= c /\ (b + a) / b != 3, within a single constraint and across constraints
+    let constraints = Expression::And(
+        Metadata::new(),
+        vec![
+            Expression::Leq(
+                Metadata::new(),
+                Arc::new(safe_div("a", "b")),
+                Arc::new(var("c")),
+            ),
+            Expression::Neq(Metadata::new(), Arc::new(safe_div("a", "b")), constant(3)),
+            Expression::Neq(
+                Metadata::new(),
+                Arc::new(safe_div("b", "a")),
+                Arc::new(safe_div("a", "b")),
+            ),
+        ]
+        .into(),
+    );
+    let model = Model::new(variables, constraints, Default::default());
+    let rewritten = rewrite_model(&model, &rule_sets).unwrap();
+    assert_eq!(rewritten.variables.len(), 5);
+    let div_eqs = rewritten
+        .constraints
+        .universe()
+        .into_iter()
+        .filter(|expr| matches!(expr, Expression::DivEq(..)))
+        .count();
+    assert_eq!(div_eqs, 2);
+}
+
+#[test]
+fn reductions_reuse_the_aux_var_of_an_equal_expression() {
+    let var = |name: Name| Expression::Reference(Metadata::new(), name);
+    let a = Name::UserName(String::from("a"));
+    let b = Name::UserName(String::from("b"));
+    let sum = |x: &Name, y: &Name| {
+        Expression::Sum(Metadata::new(), vec![var(x.clone()), var(y.clone())].into())
+    };
+    // An aux var `aux` defined by `aux = sum`, replacing `sum` in `target`
+    let define = |model: &Model, sum: Expression, target: Expression| {
+        let aux = model.gensym();
+        let mut symbols = SymbolTable::new();
+        symbols.insert(
+            aux.clone(),
+            DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(0, 10)])),
+        );
+        let new_top = Expression::Eq(
+            Metadata::new(),
+            Arc::new(var(aux.clone())),
+            Arc::new(sum.clone()),
+        );
+        let new_expression = Expression::Leq(
+            Metadata::new(),
+            Arc::new(var(aux.clone())),
+            Arc::new(target),
+        );
+        Reduction::new(new_expression, new_top, symbols).define_aux_var(aux, sum)
+    };
+    let variables: SymbolTable = [a.clone(), b.clone()]
+        .into_iter()
+        .map(|name| {
+            (
+                name,
+                DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(0, 5)])),
+            )
+        })
+        .collect();
+    let mut model = Model::new(variables, Expression::Nothing, Default::default());
+
+    define(&model, sum(&a, &b), var(a.clone())).apply(&mut model);
+    assert_eq!(model.variables.len(), 3);
+    let first = model.constraints.clone();
+
+    // b + a is the same sum, so its variable and definition are dropped in favour of the first one
+    model.constraints = Expression::Nothing;
+    define(&model, sum(&b, &a), var(b.clone())).apply(&mut model);
+    assert_eq!(model.variables.len(), 3);
+    let Expression::Leq(_, aux, target) = &model.constraints else {
+        panic!(
+            "expected only the rewritten expression, got {}",
+            model.constraints
+        );
+    };
+    assert!(first.mentions(&match aux.as_ref() {
+        Expression::Reference(_, name) => name.clone(),
+        _ => panic!("expected a reference to the aux var"),
+    }));
+    assert_eq!(**target, var(b));
+}
+
+#[test]
+fn simplify_rule_set_narrows_domains_and_eliminates_fixed_variables() {
+    let name = |name: &str| Name::UserName(String::from(name));
+    let var = |n: &str| Arc::new(Expression::Reference(Metadata::new(), name(n)));
+    let int = |lo, hi| Domain::IntDomain(vec![Range::Bounded(lo, hi)]);
+    let model = |constraints: Vec<Expression>| {
+        let variables: SymbolTable = [("x", int(0, 10)), ("y", int(1, 10))]
+            .into_iter()
+            .map(|(n, domain)| (name(n), DecisionVariable::new(domain)))
+            .collect();
+        Model::new(
+            variables,
+            Expression::And(Metadata::new(), constraints.into()),
+            Default::default(),
+        )
+    };
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let without_simplify: Vec<_> = rule_sets
+        .iter()
+        .copied()
+        .filter(|rule_set| rule_set.name != "Simplify")
+        .collect();
+
+    // x <= y, y <= 3
+    let three = Arc::new(Expression::Constant(Metadata::new(), Constant::Int(3)));
+    let satisfiable = model(vec![
+        Expression::Leq(Metadata::new(), var("x"), var("y")),
+        Expression::Leq(Metadata::new(), var("y"), three),
+    ]);
+    let rewritten = rewrite_model(&satisfiable, &rule_sets).unwrap();
+    assert_eq!(rewritten.get_domain(&name("x")), Some(&int(0, 3)));
+    assert_eq!(rewritten.get_domain(&name("y")), Some(&int(1, 3)));
+    let rewritten = rewrite_model(&satisfiable, &without_simplify).unwrap();
+    assert_eq!(rewritten.get_domain(&name("x")), Some(&int(0, 10)));
+
+    // x < y, y < x
+    let unsatisfiable = model(vec![
+        Expression::Lt(Metadata::new(), var("x"), var("y")),
+        Expression::Lt(Metadata::new(), var("y"), var("x")),
+    ]);
+    let rewritten = rewrite_model(&unsatisfiable, &rule_sets).unwrap();
+    assert!(detect_unsatisfiable(&rewritten).is_some());
+    let rewritten = rewrite_model(&unsatisfiable, &without_simplify).unwrap();
+    assert_eq!(detect_unsatisfiable(&rewritten), None);
+
+    // x = y, y <= 1 eliminates x, and then y, as y can only be 1
+    let one = Arc::new(Expression::Constant(Metadata::new(), Constant::Int(1)));
+    let fixed = model(vec![
+        Expression::Eq(Metadata::new(), var("x"), var("y")),
+        Expression::Leq(Metadata::new(), var("y"), one),
+    ]);
+    let rewritten = rewrite_model(&fixed, &rule_sets).unwrap();
+    assert!(rewritten.variables.is_empty());
+    let mut solution = HashMap::new();
+    restore_eliminated(&rewritten.eliminated, &mut solution);
+    assert_eq!(
+        solution,
+        HashMap::from([(name("x"), Constant::Int(1)), (name("y"), Constant::Int(1))])
+    );
+    let rewritten = rewrite_model(&fixed, &without_simplify).unwrap();
+    assert_eq!(rewritten.variables.len(), 2);
+}
+
+#[test]
+fn normalise_rule_set_gives_canonical_forms() {
+    let var =
+        |name: &str| Expression::Reference(Metadata::new(), Name::UserName(String::from(name)));
+    let int = |n: i32| Expression::Constant(Metadata::new(), Constant::Int(n));
+    let sum = |exprs: Vec<Expression>| Expression::Sum(Metadata::new(), exprs.into());
+    let eq =
+        |a: Expression, b: Expression| Expression::Eq(Metadata::new(), Arc::new(a), Arc::new(b));
+    let neq =
+        |a: Expression, b: Expression| Expression::Neq(Metadata::new(), Arc::new(a), Arc::new(b));
+    let and = |exprs: Vec<Expression>| Expression::And(Metadata::new(), exprs.into());
+    let apply = |rule: &str, expr: &Expression| {
+        get_rule_by_name(rule)
+            .unwrap()
+            .apply(expr, &Model::new_empty(Default::default()))
+            .map(|reduction| reduction.new_expression)
+    };
+
+    // Constants whose sum overflows are left alone
+    assert!(matches!(
+        apply(
+            "normalise_commutative",
+            &sum(vec![var("a"), int(i32::MAX), int(1)])
+        ),
+        Err(ApplicationError::RuleNotApplicable)
+    ));
+    assert_eq!(
+        apply(
+            "normalise_commutative",
+            &sum(vec![var("b"), int(2), var("a"), int(3)])
+        )
+        .unwrap(),
+        sum(vec![var("a"), var("b"), int(5)])
+    );
+    assert!(apply(
+        "normalise_commutative",
+        &sum(vec![var("a"), var("b"), int(5)])
+    )
+    .is_err());
+    // Compound operands are ordered by their structure
+    assert_eq!(
+        apply(
+            "normalise_commutative",
+            &and(vec![eq(var("x"), int(2)), eq(var("x"), int(1))])
         )
+        .unwrap(),
+        and(vec![eq(var("x"), int(1)), eq(var("x"), int(2))])
+    );
+    assert_eq!(
+        apply(
+            "normalise_commutative",
+            &Expression::Or(
+                Metadata::new(),
+                vec![var("q"), true.into(), var("p")].into()
+            )
+        )
+        .unwrap(),
+        true.into()
+    );
+    assert_eq!(
+        apply("normalise_eq_neq", &eq(int(3), var("x"))).unwrap(),
+        eq(var("x"), int(3))
+    );
+    assert_eq!(
+        apply(
+            "normalise_all_diff",
+            &Expression::AllDiff(Metadata::new(), vec![var("b"), int(1), var("a")].into())
+        )
+        .unwrap(),
+        Expression::AllDiff(Metadata::new(), vec![var("a"), var("b"), int(1)].into())
+    );
+
+    // Normalise is enabled by default, so models that differ only in the order of operands are rewritten
+    // to the same model
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    let rewrite = |constraints: Expression| {
+        rewrite_model(
+            &Model::new(HashMap::new(), constraints, Default::default()),
+            &rule_sets,
+        )
+        .unwrap()
+        .constraints
+    };
+    let first = rewrite(and(vec![
+        eq(sum(vec![var("b"), int(2), var("a")]), var("c")),
+        neq(int(1), var("x")),
+    ]));
+    let second = rewrite(and(vec![
+        neq(var("x"), int(1)),
+        eq(var("c"), sum(vec![var("a"), var("b"), int(2)])),
+    ]));
+    assert_eq!(first, second);
+}
+
+/// Rewrite the constraints with the Bubble rules for partial operators, and the Base and Constant rules,
+/// without lowering to a solver.
+#[allow(clippy::unwrap_used)]
+fn rewrite_with_bubbles(constraints: Expression) -> Expression {
+    let rule_sets = resolve_rule_sets(
+        SolverFamily::SAT,
+        &vec!["Constant".to_string(), "Bubble".to_string()],
+    )
+    .unwrap();
+    rewrite_model(
+        &Model::new(HashMap::new(), constraints, Default::default()),
+        &rule_sets,
+    )
+    .unwrap()
+    .constraints
+}
+
+fn int(n: i32) -> Expression {
+    Expression::Constant(Metadata::new(), Constant::Int(n))
+}
+
+fn eq(a: Expression, b: Expression) -> Expression {
+    Expression::Eq(Metadata::new(), Arc::new(a), Arc::new(b))
+}
+
+fn not(a: Expression) -> Expression {
+    Expression::Not(Metadata::new(), Arc::new(a))
+}
+
+/// Check that `defined = value` holds and `defined = value + 1` does not, and that under relational semantics
+/// `undefined = 0` is false and its negation true.
+fn assert_relational_semantics(defined: Expression, value: i32, undefined: Expression) {
+    assert_eq!(
+        rewrite_with_bubbles(eq(defined.clone(), int(value))),
+        true.into()
+    );
+    assert_eq!(
+        rewrite_with_bubbles(eq(defined, int(value + 1))),
+        false.into()
+    );
+    assert_eq!(
+        rewrite_with_bubbles(eq(undefined.clone(), int(0))),
+        false.into()
     );
+    // The nearest boolean context is the equality, so its negation holds
+    assert_eq!(
+        rewrite_with_bubbles(not(eq(undefined, int(0)))),
+        true.into()
+    );
+}
+
+#[test]
+fn partial_division_is_false_in_its_boolean_context() {
+    let div = |a, b| Expression::UnsafeDiv(Metadata::new(), Arc::new(int(a)), Arc::new(int(b)));
+    assert_relational_semantics(div(7, 2), 3, div(7, 0));
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn partial_modulo_is_false_in_its_boolean_context() {
+    let modulo = |a, b| Expression::UnsafeMod(Metadata::new(), Arc::new(int(a)), Arc::new(int(b)));
+    assert_relational_semantics(modulo(7, 3), 1, modulo(7, 0));
+    // The result takes the sign of the divisor
+    assert_relational_semantics(modulo(-7, 3), 2, modulo(-7, 0));
+
+    // The definedness condition is conjoined with the nearest boolean expression
+    let var = |name: &str| {
+        Arc::new(Expression::Reference(
+            Metadata::new(),
+            Name::UserName(name.to_string()),
+        ))
+    };
+    let apply = |rule: &str, expr: &Expression| {
+        get_rule_by_name(rule)
+            .unwrap()
+            .apply(expr, &Model::new_empty(Default::default()))
+            .unwrap()
+            .new_expression
+    };
+    let bubble = apply(
+        "partial_to_bubble",
+        &Expression::UnsafeMod(Metadata::new(), var("x"), var("y")),
+    );
+    let expr = apply("bubble_up", &eq(bubble, int(1)));
+    assert_eq!(
+        apply("expand_bubble", &expr),
+        Expression::And(
+            Metadata::new(),
+            vec![
+                eq(
+                    Expression::SafeMod(Metadata::new(), var("x"), var("y")),
+                    int(1)
+                ),
+                Expression::And(
+                    Metadata::new(),
+                    vec![Expression::Neq(Metadata::new(), var("y"), Arc::new(int(0)))].into()
+                ),
+            ]
+            .into()
+        )
+    );
+}
+
+#[test]
+fn partial_power_is_false_in_its_boolean_context() {
+    let pow = |a, b| Expression::UnsafePow(Metadata::new(), Arc::new(int(a)), Arc::new(int(b)));
+    assert_relational_semantics(pow(2, 3), 8, pow(2, -1));
+    assert_relational_semantics(pow(-3, 0), 1, pow(0, -2));
+}
+
+#[test]
+fn min_of_empty_list_is_false_in_its_boolean_context() {
+    let min = |exprs: Vec<Expression>| Expression::Min(Metadata::new(), exprs.into());
+    assert_relational_semantics(min(vec![int(5), int(2)]), 2, min(vec![]));
+}
+
+#[test]
+fn rule_set_graph_lists_dependencies() {
+    let graph = rule_set_graph().unwrap();
+
+    let minion = graph.nodes.iter().find(|n| n.name == "Minion").unwrap();
+    assert_eq!(minion.order, 100);
+    assert_eq!(minion.solver_families, vec![SolverFamily::Minion]);
+    assert_eq!(minion.dependencies, vec!["Base"]);
+    assert!(minion.rule_count > 0);
+
+    let text = graph.to_string();
+    assert!(text.contains("Minion: order 100, "));
+    assert!(text.contains("solvers [Minion], depends on [Base]"));
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph rule_sets {"));
+    assert!(dot.contains("\"Minion\" -> \"Base\";"));
 }
 
 ///
@@ -250,7 +1491,8 @@ fn reduce_solve_xyz() {
             Expression::Constant(Metadata::new(), Constant::Int(2)),
             Expression::Constant(Metadata::new(), Constant::Int(3)),
             Expression::Constant(Metadata::new(), Constant::Int(-1)),
-        ],
+        ]
+        .into(),
     );
 
     expr1 = sum_constants
@@ -269,15 +1511,16 @@ fn reduce_solve_xyz() {
     // a + b + c = 4
     expr1 = Expression::Leq(
         Metadata::new(),
-        Box::new(Expression::Sum(
+        Arc::new(Expression::Sum(
             Metadata::new(),
             vec![
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("b"))),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("c"))),
-            ],
+            ]
+            .into(),
         )),
-        Box::new(expr1),
+        Arc::new(expr1),
     );
     expr1 = sum_leq_to_sumleq
         .apply(&expr1, &Model::new_empty(Default::default()))
@@ -291,19 +1534,20 @@ fn reduce_solve_xyz() {
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("b"))),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("c"))),
-            ],
-            Box::new(Expression::Constant(Metadata::new(), Constant::Int(4)))
+            ]
+            .into(),
+            Arc::new(Expression::Constant(Metadata::new(), Constant::Int(4)))
         )
     );
 
     // a < b
     let mut expr2 = Expression::Lt(
         Metadata::new(),
-        Box::new(Expression::Reference(
+        Arc::new(Expression::Reference(
             Metadata::new(),
             Name::UserName(String::from("a")),
         )),
-        Box::new(Expression::Reference(
+        Arc::new(Expression::Reference(
             Metadata::new(),
             Name::UserName(String::from("b")),
         )),
@@ -316,21 +1560,21 @@ fn reduce_solve_xyz() {
         expr2,
         Expression::Ineq(
             Metadata::new(),
-            Box::new(Expression::Reference(
+            Arc::new(Expression::Reference(
                 Metadata::new(),
                 Name::UserName(String::from("a"))
             )),
-            Box::new(Expression::Reference(
+            Arc::new(Expression::Reference(
                 Metadata::new(),
                 Name::UserName(String::from("b"))
             )),
-            Box::new(Expression::Constant(Metadata::new(), Constant::Int(-1)))
+            Arc::new(Expression::Constant(Metadata::new(), Constant::Int(-1)))
         )
     );
 
     let mut model = Model::new(
         HashMap::new(),
-        Expression::And(Metadata::new(), vec![expr1, expr2]),
+        Expression::And(Metadata::new(), vec![expr1, expr2].into()),
         Default::default(),
     );
     model.variables.insert(
@@ -363,9 +1607,9 @@ fn rule_remove_double_negation() {
 
     let mut expr = Expression::Not(
         Metadata::new(),
-        Box::new(Expression::Not(
+        Arc::new(Expression::Not(
             Metadata::new(),
-            Box::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
+            Arc::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
         )),
     );
 
@@ -392,10 +1636,12 @@ fn rule_unwrap_nested_or() {
                 vec![
                     Expression::Constant(Metadata::new(), Constant::Bool(true)),
                     Expression::Constant(Metadata::new(), Constant::Bool(false)),
-                ],
+                ]
+                .into(),
             ),
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
-        ],
+        ]
+        .into(),
     );
 
     expr = unwrap_nested_or
@@ -412,6 +1658,7 @@ fn rule_unwrap_nested_or() {
                 Expression::Constant(Metadata::new(), Constant::Bool(false)),
                 Expression::Constant(Metadata::new(), Constant::Bool(true)),
             ]
+            .into()
         )
     );
 }
@@ -428,10 +1675,12 @@ fn rule_unwrap_nested_and() {
                 vec![
                     Expression::Constant(Metadata::new(), Constant::Bool(true)),
                     Expression::Constant(Metadata::new(), Constant::Bool(false)),
-                ],
+                ]
+                .into(),
             ),
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
-        ],
+        ]
+        .into(),
     );
 
     expr = unwrap_nested_and
@@ -448,6 +1697,7 @@ fn rule_unwrap_nested_and() {
                 Expression::Constant(Metadata::new(), Constant::Bool(false)),
                 Expression::Constant(Metadata::new(), Constant::Bool(true)),
             ]
+            .into()
         )
     );
 }
@@ -461,7 +1711,8 @@ fn unwrap_nested_or_not_changed() {
         vec![
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
             Expression::Constant(Metadata::new(), Constant::Bool(false)),
-        ],
+        ]
+        .into(),
     );
 
     let result = unwrap_nested_or.apply(&expr, &Model::new_empty(Default::default()));
@@ -478,7 +1729,8 @@ fn unwrap_nested_and_not_changed() {
         vec![
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
             Expression::Constant(Metadata::new(), Constant::Bool(false)),
-        ],
+        ]
+        .into(),
     );
 
     let result = unwrap_nested_and.apply(&expr, &Model::new_empty(Default::default()));
@@ -493,11 +1745,11 @@ fn remove_trivial_and_or() {
 
     let mut expr_and = Expression::And(
         Metadata::new(),
-        vec![Expression::Constant(Metadata::new(), Constant::Bool(true))],
+        vec![Expression::Constant(Metadata::new(), Constant::Bool(true))].into(),
     );
     let mut expr_or = Expression::Or(
         Metadata::new(),
-        vec![Expression::Constant(Metadata::new(), Constant::Bool(false))],
+        vec![Expression::Constant(Metadata::new(), Constant::Bool(false))].into(),
     );
 
     expr_and = remove_trivial_and
@@ -529,7 +1781,8 @@ fn rule_remove_constants_from_or() {
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
             Expression::Constant(Metadata::new(), Constant::Bool(false)),
             Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
-        ],
+        ]
+        .into(),
     );
 
     expr = remove_constants_from_or
@@ -553,7 +1806,8 @@ fn rule_remove_constants_from_and() {
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
             Expression::Constant(Metadata::new(), Constant::Bool(false)),
             Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
-        ],
+        ]
+        .into(),
     );
 
     expr = remove_constants_from_and
@@ -576,7 +1830,8 @@ fn remove_constants_from_or_not_changed() {
         vec![
             Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
             Expression::Reference(Metadata::new(), Name::UserName(String::from("b"))),
-        ],
+        ]
+        .into(),
     );
 
     let result = remove_constants_from_or.apply(&expr, &Model::new_empty(Default::default()));
@@ -593,7 +1848,8 @@ fn remove_constants_from_and_not_changed() {
         vec![
             Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
             Expression::Reference(Metadata::new(), Name::UserName(String::from("b"))),
-        ],
+        ]
+        .into(),
     );
 
     let result = remove_constants_from_and.apply(&expr, &Model::new_empty(Default::default()));
@@ -607,12 +1863,13 @@ fn rule_distribute_not_over_and() {
 
     let mut expr = Expression::Not(
         Metadata::new(),
-        Box::new(Expression::And(
+        Arc::new(Expression::And(
             Metadata::new(),
             vec![
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("b"))),
-            ],
+            ]
+            .into(),
         )),
     );
 
@@ -628,19 +1885,20 @@ fn rule_distribute_not_over_and() {
             vec![
                 Expression::Not(
                     Metadata::new(),
-                    Box::new(Expression::Reference(
+                    Arc::new(Expression::Reference(
                         Metadata::new(),
                         Name::UserName(String::from("a"))
                     ))
                 ),
                 Expression::Not(
                     Metadata::new(),
-                    Box::new(Expression::Reference(
+                    Arc::new(Expression::Reference(
                         Metadata::new(),
                         Name::UserName(String::from("b"))
                     ))
                 ),
             ]
+            .into()
         )
     );
 }
@@ -651,12 +1909,13 @@ fn rule_distribute_not_over_or() {
 
     let mut expr = Expression::Not(
         Metadata::new(),
-        Box::new(Expression::Or(
+        Arc::new(Expression::Or(
             Metadata::new(),
             vec![
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
                 Expression::Reference(Metadata::new(), Name::UserName(String::from("b"))),
-            ],
+            ]
+            .into(),
         )),
     );
 
@@ -672,19 +1931,20 @@ fn rule_distribute_not_over_or() {
             vec![
                 Expression::Not(
                     Metadata::new(),
-                    Box::new(Expression::Reference(
+                    Arc::new(Expression::Reference(
                         Metadata::new(),
                         Name::UserName(String::from("a"))
                     ))
                 ),
                 Expression::Not(
                     Metadata::new(),
-                    Box::new(Expression::Reference(
+                    Arc::new(Expression::Reference(
                         Metadata::new(),
                         Name::UserName(String::from("b"))
                     ))
                 ),
             ]
+            .into()
         )
     );
 }
@@ -695,7 +1955,7 @@ fn rule_distribute_not_over_and_not_changed() {
 
     let expr = Expression::Not(
         Metadata::new(),
-        Box::new(Expression::Reference(
+        Arc::new(Expression::Reference(
             Metadata::new(),
             Name::UserName(String::from("a")),
         )),
@@ -712,7 +1972,7 @@ fn rule_distribute_not_over_or_not_changed() {
 
     let expr = Expression::Not(
         Metadata::new(),
-        Box::new(Expression::Reference(
+        Arc::new(Expression::Reference(
             Metadata::new(),
             Name::UserName(String::from("a")),
         )),
@@ -735,10 +1995,12 @@ fn rule_distribute_or_over_and() {
                 vec![
                     Expression::Reference(Metadata::new(), Name::MachineName(1)),
                     Expression::Reference(Metadata::new(), Name::MachineName(2)),
-                ],
+                ]
+                .into(),
             ),
             Expression::Reference(Metadata::new(), Name::MachineName(3)),
-        ],
+        ]
+        .into(),
     );
 
     let red = distribute_or_over_and
@@ -756,6 +2018,7 @@ fn rule_distribute_or_over_and() {
                         Expression::Reference(Metadata::new(), Name::MachineName(3)),
                         Expression::Reference(Metadata::new(), Name::MachineName(1)),
                     ]
+                    .into()
                 ),
                 Expression::Or(
                     Metadata::new(),
@@ -763,8 +2026,10 @@ fn rule_distribute_or_over_and() {
                         Expression::Reference(Metadata::new(), Name::MachineName(3)),
                         Expression::Reference(Metadata::new(), Name::MachineName(2)),
                     ]
+                    .into()
                 ),
             ]
+            .into()
         ),
     );
 }
@@ -775,11 +2040,11 @@ fn rule_distribute_or_over_and() {
 
 //     let expr = Expression::Div(
 //         Metadata::new(),
-//         Box::new(Expression::Reference(
+//         Arc::new(Expression::Reference(
 //             Metadata::new(),
 //             Name::UserName("a".to_string()),
 //         )),
-//         Box::new(Expression::Reference(
+//         Arc::new(Expression::Reference(
 //             Metadata::new(),
 //             Name::UserName("b".to_string()),
 //         )),
@@ -791,11 +2056,11 @@ fn rule_distribute_or_over_and() {
 //         red.new_expression,
 //         Expression::SafeDiv(
 //             Metadata::new(),
-//             Box::new(Expression::Reference(
+//             Arc::new(Expression::Reference(
 //                 Metadata::new(),
 //                 Name::UserName("a".to_string())
 //             )),
-//             Box::new(Expression::Reference(
+//             Arc::new(Expression::Reference(
 //                 Metadata::new(),
 //                 Name::UserName("b".to_string())
 //             )),
@@ -805,11 +2070,11 @@ fn rule_distribute_or_over_and() {
 //         red.new_top,
 //         Expression::Neq(
 //             Metadata::new(),
-//             Box::new(Expression::Reference(
+//             Arc::new(Expression::Reference(
 //                 Metadata::new(),
 //                 Name::UserName("b".to_string())
 //             )),
-//             Box::new(Expression::Constant(Metadata::new(), Constant::Int(0)))
+//             Arc::new(Expression::Constant(Metadata::new(), Constant::Int(0)))
 //         )
 //     );
 // }
@@ -849,22 +2114,24 @@ fn rewrite_solve_xyz() {
         vec![
             Expression::Eq(
                 Metadata::new(),
-                Box::new(Expression::Sum(
+                Arc::new(Expression::Sum(
                     Metadata::new(),
                     vec![
                         Expression::Reference(Metadata::new(), variable_a.clone()),
                         Expression::Reference(Metadata::new(), variable_b.clone()),
                         Expression::Reference(Metadata::new(), variable_c.clone()),
-                    ],
+                    ]
+                    .into(),
                 )),
-                Box::new(Expression::Constant(Metadata::new(), Constant::Int(4))),
+                Arc::new(Expression::Constant(Metadata::new(), Constant::Int(4))),
             ),
             Expression::Lt(
                 Metadata::new(),
-                Box::new(Expression::Reference(Metadata::new(), variable_a.clone())),
-                Box::new(Expression::Reference(Metadata::new(), variable_b.clone())),
+                Arc::new(Expression::Reference(Metadata::new(), variable_a.clone())),
+                Arc::new(Expression::Reference(Metadata::new(), variable_b.clone())),
             ),
-        ],
+        ]
+        .into(),
     );
 
     let rule_sets = match resolve_rule_sets(SolverFamily::Minion, &vec!["Constant".to_string()]) {
@@ -885,7 +2152,7 @@ fn rewrite_solve_xyz() {
 
     // Check if the expression is in its simplest form
     let expr = rewritten_expr.clone();
-    assert!(is_simple(&expr));
+    assert!(is_simple(&expr, &rule_sets));
 
     // Create model with variables and constraints
     let mut model = Model::new(HashMap::new(), rewritten_expr, Default::default());
@@ -946,32 +2213,36 @@ fn rewrite_solve_xyz_parameterized() {
                 vec![
                     Expression::Eq(
                         Metadata::new(),
-                        Box::new(Expression::Sum(
+                        Arc::new(Expression::Sum(
                             Metadata::new(),
                             vec![
                                 Expression::Reference(Metadata::new(), variable_a.clone()),
                                 Expression::Reference(Metadata::new(), variable_b.clone()),
                                 Expression::Reference(Metadata::new(), variable_c.clone()),
-                            ],
+                            ]
+                            .into(),
                         )),
-                        Box::new(Expression::Constant(Metadata::new(), Constant::Int(4))),
+                        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(4))),
                     ),
                     Expression::Lt(
                         Metadata::new(),
-                        Box::new(Expression::Reference(Metadata::new(), variable_a.clone())),
-                        Box::new(Expression::Reference(Metadata::new(), variable_b.clone())),
+                        Arc::new(Expression::Reference(Metadata::new(), variable_a.clone())),
+                        Arc::new(Expression::Reference(Metadata::new(), variable_b.clone())),
                     ),
-                ],
+                ]
+                .into(),
             );
             or_exprs.push(expr);
         }
-        let nested_expr = Expression::Or(Metadata::new(), or_exprs);
+        let nested_expr = Expression::Or(Metadata::new(), or_exprs.into());
 
         let model_for_rewrite = Model::new(HashMap::new(), nested_expr.clone(), Default::default());
         let model_for_rewrite_unoptimized =
             Model::new(HashMap::new(), nested_expr.clone(), Default::default());
 
         // Apply rewrite function to the nested expression
+        env::set_var("OPTIMIZATIONS", "1");
+
         let rewritten_expr = rewrite_model(&model_for_rewrite, &rule_sets)
             .unwrap()
             .constraints;
@@ -1003,10 +2274,10 @@ fn rewrite_solve_xyz_parameterized() {
 
         // Check if the expression is in its simplest form
         let expr = rewritten_expr.clone();
-        assert!(is_simple(&expr));
+        assert!(is_simple(&expr, &rule_sets));
 
         let expr_unoptimized = rewritten_expr_unoptimized.clone();
-        assert!(is_simple(&expr_unoptimized));
+        assert!(is_simple(&expr_unoptimized, &rule_sets));
 
         // Create model with variables and constraints
         let mut model = Model::new(HashMap::new(), rewritten_expr, Default::default());
@@ -1072,10 +2343,11 @@ struct RuleResult<'a> {
 }
 
 /// # Returns
-/// - True if `expression` is in its simplest form.
+/// - True if no rule of the given rule sets applies to `expression`.
 /// - False otherwise.
-pub fn is_simple(expression: &Expression) -> bool {
-    let rules = get_rules();
+#[allow(clippy::unwrap_used)]
+pub fn is_simple<'a>(expression: &Expression, rule_sets: &Vec<&'a RuleSet<'a>>) -> bool {
+    let rules = get_rules_vec(&get_rule_priorities(rule_sets).unwrap());
     let mut new = expression.clone();
     while let Some(step) = is_simple_iteration(&new, &rules) {
         new = step;
@@ -1162,7 +2434,8 @@ fn eval_const_and() {
         vec![
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
             Expression::Constant(Metadata::new(), Constant::Bool(false)),
-        ],
+        ]
+        .into(),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, Some(Constant::Bool(false)));
@@ -1186,9 +2459,11 @@ fn eval_const_nested_ref() {
                 vec![
                     Expression::Constant(Metadata::new(), Constant::Bool(true)),
                     Expression::Reference(Metadata::new(), Name::UserName(String::from("a"))),
-                ],
+                ]
+                .into(),
             ),
-        ],
+        ]
+        .into(),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, None);
@@ -1198,8 +2473,8 @@ fn eval_const_nested_ref() {
 fn eval_const_eq_int() {
     let expr = Expression::Eq(
         Metadata::new(),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Int(1))),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Int(1))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(1))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(1))),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, Some(Constant::Bool(true)));
@@ -1209,8 +2484,8 @@ fn eval_const_eq_int() {
 fn eval_const_eq_bool() {
     let expr = Expression::Eq(
         Metadata::new(),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, Some(Constant::Bool(true)));
@@ -1220,8 +2495,8 @@ fn eval_const_eq_bool() {
 fn eval_const_eq_mixed() {
     let expr = Expression::Eq(
         Metadata::new(),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Int(1))),
-        Box::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Int(1))),
+        Arc::new(Expression::Constant(Metadata::new(), Constant::Bool(true))),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, None);
@@ -1234,7 +2509,8 @@ fn eval_const_sum_mixed() {
         vec![
             Expression::Constant(Metadata::new(), Constant::Int(1)),
             Expression::Constant(Metadata::new(), Constant::Bool(true)),
-        ],
+        ]
+        .into(),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, None);
@@ -1247,28 +2523,30 @@ fn eval_const_sum_xyz() {
         vec![
             Expression::Eq(
                 Metadata::new(),
-                Box::new(Expression::Sum(
+                Arc::new(Expression::Sum(
                     Metadata::new(),
                     vec![
                         Expression::Reference(Metadata::new(), Name::UserName(String::from("x"))),
                         Expression::Reference(Metadata::new(), Name::UserName(String::from("y"))),
                         Expression::Reference(Metadata::new(), Name::UserName(String::from("z"))),
-                    ],
+                    ]
+                    .into(),
                 )),
-                Box::new(Expression::Constant(Metadata::new(), Constant::Int(4))),
+                Arc::new(Expression::Constant(Metadata::new(), Constant::Int(4))),
             ),
             Expression::Geq(
                 Metadata::new(),
-                Box::new(Expression::Reference(
+                Arc::new(Expression::Reference(
                     Metadata::new(),
                     Name::UserName(String::from("x")),
                 )),
-                Box::new(Expression::Reference(
+                Arc::new(Expression::Reference(
                     Metadata::new(),
                     Name::UserName(String::from("y")),
                 )),
             ),
-        ],
+        ]
+        .into(),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, None);
@@ -1281,7 +2559,8 @@ fn eval_const_or() {
         vec![
             Expression::Constant(Metadata::new(), Constant::Bool(false)),
             Expression::Constant(Metadata::new(), Constant::Bool(false)),
-        ],
+        ]
+        .into(),
     );
     let result = eval_constant(&expr);
     assert_eq!(result, Some(Constant::Bool(false)));
@@ -0,0 +1,66 @@
+// Tests for resolving rule set dependencies.
+//
+// These rule sets are broken on purpose, so they live in their own test binary.
+
+use conjure_core::rule_engine::{
+    register_rule_set, resolve_rule_sets, rule_set_graph, ResolveRulesError,
+};
+use conjure_core::solver::SolverFamily;
+
+register_rule_set!("TestMisspeltDependency", 100, ("Bsae"));
+register_rule_set!("TestCycleA", 100, ("TestCycleB"));
+register_rule_set!("TestCycleB", 100, ("Base", "TestCycleA"));
+register_rule_set!("TestDependsOnCycle", 100, ("TestCycleB"));
+
+#[test]
+fn missing_dependency_is_an_error() {
+    let result = resolve_rule_sets(
+        SolverFamily::Minion,
+        &vec!["TestMisspeltDependency".to_string()],
+    );
+    match result {
+        Err(ResolveRulesError::DependencyNotFound {
+            rule_set,
+            dependency,
+        }) => {
+            assert_eq!(rule_set, "TestMisspeltDependency");
+            assert_eq!(dependency, "Bsae");
+        }
+        other => panic!("expected a missing dependency error, got {:?}", other),
+    }
+}
+
+#[test]
+fn dependency_cycle_is_an_error() {
+    let result = resolve_rule_sets(SolverFamily::Minion, &vec!["TestCycleA".to_string()]);
+    match result {
+        Err(ResolveRulesError::DependencyCycle(cycle)) => {
+            assert_eq!(cycle, vec!["TestCycleA", "TestCycleB", "TestCycleA"]);
+        }
+        other => panic!("expected a dependency cycle error, got {:?}", other),
+    }
+
+    // The cycle is reported even when it does not include the rule set being resolved
+    let result = resolve_rule_sets(
+        SolverFamily::Minion,
+        &vec!["TestDependsOnCycle".to_string()],
+    );
+    match result {
+        Err(ResolveRulesError::DependencyCycle(cycle)) => {
+            assert_eq!(cycle, vec!["TestCycleB", "TestCycleA", "TestCycleB"]);
+        }
+        other => panic!("expected a dependency cycle error, got {:?}", other),
+    }
+}
+
+#[test]
+fn valid_rule_sets_still_resolve() {
+    let rule_sets = resolve_rule_sets(SolverFamily::Minion, &vec![]).unwrap();
+    assert!(rule_sets.iter().any(|rs| rs.name == "Minion"));
+    assert!(rule_sets.iter().any(|rs| rs.name == "Base"));
+}
+
+#[test]
+fn graph_reports_broken_rule_sets() {
+    assert!(rule_set_graph().is_err());
+}
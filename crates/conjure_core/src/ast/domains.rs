@@ -10,6 +10,16 @@ where
     Bounded(A, A),
 }
 
+impl<A: Ord + Copy> Range<A> {
+    /// The smallest and largest values of the range.
+    pub fn bounds(&self) -> (A, A) {
+        match self {
+            Range::Single(i) => (*i, *i),
+            Range::Bounded(i, j) => (*i, *j),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Domain {
     BoolDomain,
@@ -33,6 +43,79 @@ impl Domain {
         }
     }
 
+    /// The smallest and largest values of the domain, if it is a non-empty IntDomain.
+    pub fn bounds_i32(&self) -> Option<(i32, i32)> {
+        let Domain::IntDomain(ranges) = self else {
+            return None;
+        };
+        ranges
+            .iter()
+            .map(Range::bounds)
+            .filter(|(lo, hi)| lo <= hi)
+            .reduce(|(lo1, hi1), (lo2, hi2)| (lo1.min(lo2), hi1.max(hi2)))
+    }
+
+    /// The values of an IntDomain that lie between `lo` and `hi` inclusive. Other domains are returned unchanged.
+    pub fn restrict_i32(&self, lo: i32, hi: i32) -> Domain {
+        let Domain::IntDomain(ranges) = self else {
+            return self.clone();
+        };
+        Domain::IntDomain(restrict_ranges(ranges, lo, hi))
+    }
+
+    /// The same domain with its ranges sorted and merged, so that no two of them overlap or are adjacent.
+    /// Other domains are returned unchanged.
+    pub fn simplified(&self) -> Domain {
+        let Domain::IntDomain(ranges) = self else {
+            return self.clone();
+        };
+        let mut bounds: Vec<(i32, i32)> = ranges
+            .iter()
+            .map(Range::bounds)
+            .filter(|(lo, hi)| lo <= hi)
+            .collect();
+        bounds.sort();
+
+        let mut merged: Vec<(i32, i32)> = vec![];
+        for (lo, hi) in bounds {
+            match merged.last_mut() {
+                Some((_, last)) if i64::from(lo) <= i64::from(*last) + 1 => *last = hi.max(*last),
+                _ => merged.push((lo, hi)),
+            }
+        }
+        Domain::IntDomain(
+            merged
+                .into_iter()
+                .map(|(lo, hi)| {
+                    if lo == hi {
+                        Range::Single(lo)
+                    } else {
+                        Range::Bounded(lo, hi)
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// The values in both domains, or `None` if one is a BoolDomain and the other an IntDomain.
+    pub fn intersect(&self, other: &Domain) -> Option<Domain> {
+        match (self, other) {
+            (Domain::BoolDomain, Domain::BoolDomain) => Some(Domain::BoolDomain),
+            (Domain::IntDomain(ranges), Domain::IntDomain(other_ranges)) => {
+                Some(Domain::IntDomain(
+                    other_ranges
+                        .iter()
+                        .flat_map(|r| {
+                            let (lo, hi) = r.bounds();
+                            restrict_ranges(ranges, lo, hi)
+                        })
+                        .collect(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
     /// Return an unoptimised domain that is the result of applying a binary i32 operation to two domains.
     ///
     /// The given operator may return None if the operation is not defined for its arguments.
@@ -52,6 +135,22 @@ impl Domain {
     }
 }
 
+/// The parts of `ranges` that lie between `lo` and `hi` inclusive.
+fn restrict_ranges(ranges: &[Range<i32>], lo: i32, hi: i32) -> Vec<Range<i32>> {
+    ranges
+        .iter()
+        .filter_map(|r| {
+            let (i, j) = r.bounds();
+            let (i, j) = (i.max(lo), j.min(hi));
+            match i.cmp(&j) {
+                std::cmp::Ordering::Less => Some(Range::Bounded(i, j)),
+                std::cmp::Ordering::Equal => Some(Range::Single(i)),
+                std::cmp::Ordering::Greater => None,
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +198,39 @@ mod tests {
             panic!();
         }
     }
+
+    #[test]
+    fn test_restrict_keeps_holes() {
+        let d = Domain::IntDomain(vec![
+            Range::Bounded(1, 3),
+            Range::Single(5),
+            Range::Bounded(7, 9),
+        ]);
+        let res = d.restrict_i32(3, 7);
+        assert_eq!(
+            res,
+            Domain::IntDomain(vec![Range::Single(3), Range::Single(5), Range::Single(7)])
+        );
+        assert_eq!(res.bounds_i32(), Some((3, 7)));
+        assert_eq!(d.restrict_i32(4, 4).bounds_i32(), None);
+    }
+
+    #[test]
+    fn test_simplified_merges_ranges_and_keeps_holes() {
+        let d = Domain::IntDomain(vec![
+            Range::Bounded(5, 7),
+            Range::Single(1),
+            Range::Bounded(2, 3),
+            Range::Single(6),
+            Range::Single(10),
+        ]);
+        assert_eq!(
+            d.simplified(),
+            Domain::IntDomain(vec![
+                Range::Bounded(1, 3),
+                Range::Bounded(5, 7),
+                Range::Single(10)
+            ])
+        );
+    }
 }
@@ -1,7 +1,10 @@
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 
 use derive_is_enum_variant::is_enum_variant;
 use serde::{Deserialize, Serialize};
+use strum_macros::{EnumDiscriminants, IntoStaticStr, VariantNames};
 
 use enum_compatability_macro::document_compatibility;
 use uniplate::uniplate::Uniplate;
@@ -14,8 +17,27 @@ use crate::metadata::Metadata;
 
 use super::{Domain, Range};
 
+/// An expression in the Essence AST.
+///
+/// Sub-expressions are shared behind `Arc`s, so cloning an expression is shallow.
+/// Rewriting a node only rebuilds the path from that node up to the root; every other subtree is reused.
+/// Use `Arc::new` for single children and `.into()` (or `Arc::new`) for lists of children.
 #[document_compatibility]
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, is_enum_variant, Uniplate)]
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    is_enum_variant,
+    Uniplate,
+    IntoStaticStr,
+    VariantNames,
+    EnumDiscriminants,
+)]
+#[strum_discriminants(name(ExpressionKind), derive(PartialOrd, Ord))]
 #[non_exhaustive]
 pub enum Expression {
     /**
@@ -26,7 +48,7 @@ pub enum Expression {
 
     /// An expression representing "A is valid as long as B is true"
     /// Turns into a conjunction when it reaches a boolean context
-    Bubble(Metadata, Box<Expression>, Box<Expression>),
+    Bubble(Metadata, Arc<Expression>, Arc<Expression>),
 
     #[compatible(Minion, JsonInput)]
     Constant(Metadata, Constant),
@@ -35,50 +57,64 @@ pub enum Expression {
     Reference(Metadata, Name),
 
     #[compatible(Minion, JsonInput)]
-    Sum(Metadata, Vec<Expression>),
+    Sum(Metadata, Arc<Vec<Expression>>),
 
     // /// Division after preventing division by zero, usually with a top-level constraint
     // #[compatible(Minion)]
-    // SafeDiv(Metadata, Box<Expression>, Box<Expression>),
+    // SafeDiv(Metadata, Arc<Expression>, Arc<Expression>),
     // /// Division with a possibly undefined value (division by 0)
     // #[compatible(Minion, JsonInput)]
-    // Div(Metadata, Box<Expression>, Box<Expression>),
+    // Div(Metadata, Arc<Expression>, Arc<Expression>),
     #[compatible(JsonInput)]
-    Min(Metadata, Vec<Expression>),
+    Min(Metadata, Arc<Vec<Expression>>),
 
-    #[compatible(JsonInput, SAT)]
-    Not(Metadata, Box<Expression>),
+    #[compatible(Minion, JsonInput, SAT)]
+    Not(Metadata, Arc<Expression>),
 
-    #[compatible(JsonInput, SAT)]
-    Or(Metadata, Vec<Expression>),
+    #[compatible(Minion, JsonInput, SAT)]
+    Or(Metadata, Arc<Vec<Expression>>),
 
-    #[compatible(JsonInput, SAT)]
-    And(Metadata, Vec<Expression>),
+    #[compatible(Minion, JsonInput, SAT)]
+    And(Metadata, Arc<Vec<Expression>>),
 
-    #[compatible(JsonInput)]
-    Eq(Metadata, Box<Expression>, Box<Expression>),
+    #[compatible(Minion, JsonInput)]
+    Eq(Metadata, Arc<Expression>, Arc<Expression>),
 
-    #[compatible(JsonInput)]
-    Neq(Metadata, Box<Expression>, Box<Expression>),
+    #[compatible(Minion, JsonInput)]
+    Neq(Metadata, Arc<Expression>, Arc<Expression>),
 
     #[compatible(JsonInput)]
-    Geq(Metadata, Box<Expression>, Box<Expression>),
+    Geq(Metadata, Arc<Expression>, Arc<Expression>),
 
     #[compatible(JsonInput)]
-    Leq(Metadata, Box<Expression>, Box<Expression>),
+    Leq(Metadata, Arc<Expression>, Arc<Expression>),
 
     #[compatible(JsonInput)]
-    Gt(Metadata, Box<Expression>, Box<Expression>),
+    Gt(Metadata, Arc<Expression>, Arc<Expression>),
 
     #[compatible(JsonInput)]
-    Lt(Metadata, Box<Expression>, Box<Expression>),
+    Lt(Metadata, Arc<Expression>, Arc<Expression>),
 
     /// Division after preventing division by zero, usually with a bubble
-    SafeDiv(Metadata, Box<Expression>, Box<Expression>),
+    SafeDiv(Metadata, Arc<Expression>, Arc<Expression>),
 
     /// Division with a possibly undefined value (division by 0)
     #[compatible(JsonInput)]
-    UnsafeDiv(Metadata, Box<Expression>, Box<Expression>),
+    UnsafeDiv(Metadata, Arc<Expression>, Arc<Expression>),
+
+    /// Modulo after preventing modulo by zero, usually with a bubble
+    SafeMod(Metadata, Arc<Expression>, Arc<Expression>),
+
+    /// Modulo with a possibly undefined value (modulo by 0)
+    #[compatible(JsonInput)]
+    UnsafeMod(Metadata, Arc<Expression>, Arc<Expression>),
+
+    /// Exponentiation after preventing negative exponents, usually with a bubble
+    SafePow(Metadata, Arc<Expression>, Arc<Expression>),
+
+    /// Exponentiation with a possibly undefined value (a negative exponent)
+    #[compatible(JsonInput)]
+    UnsafePow(Metadata, Arc<Expression>, Arc<Expression>),
 
     /* Flattened SumEq.
      *
@@ -87,23 +123,35 @@ pub enum Expression {
      *
      * ToDo: This is a stop gap solution. Eventually it may be better to have multiple constraints instead? (gs248)
      */
-    SumEq(Metadata, Vec<Expression>, Box<Expression>),
+    SumEq(Metadata, Arc<Vec<Expression>>, Arc<Expression>),
 
     // Flattened Constraints
     #[compatible(Minion)]
-    SumGeq(Metadata, Vec<Expression>, Box<Expression>),
+    SumGeq(Metadata, Arc<Vec<Expression>>, Arc<Expression>),
+
+    #[compatible(Minion)]
+    SumLeq(Metadata, Arc<Vec<Expression>>, Arc<Expression>),
+
+    #[compatible(Minion)]
+    DivEq(Metadata, Arc<Expression>, Arc<Expression>, Arc<Expression>),
+
+    /// The first argument modulo the second equals the third, as Minion's `modulo_undefzero` constraint
+    #[compatible(Minion)]
+    ModEq(Metadata, Arc<Expression>, Arc<Expression>, Arc<Expression>),
 
+    /// The first argument to the power of the second equals the third, as Minion's `pow` constraint
     #[compatible(Minion)]
-    SumLeq(Metadata, Vec<Expression>, Box<Expression>),
+    PowEq(Metadata, Arc<Expression>, Arc<Expression>, Arc<Expression>),
 
+    /// The minimum of the expressions equals the last argument, as Minion's `min` constraint
     #[compatible(Minion)]
-    DivEq(Metadata, Box<Expression>, Box<Expression>, Box<Expression>),
+    MinEq(Metadata, Arc<Vec<Expression>>, Arc<Expression>),
 
     #[compatible(Minion)]
-    Ineq(Metadata, Box<Expression>, Box<Expression>, Box<Expression>),
+    Ineq(Metadata, Arc<Expression>, Arc<Expression>, Arc<Expression>),
 
     #[compatible(Minion)]
-    AllDiff(Metadata, Vec<Expression>),
+    AllDiff(Metadata, Arc<Vec<Expression>>),
 }
 
 fn expr_vec_to_domain_i32(
@@ -118,30 +166,29 @@ fn expr_vec_to_domain_i32(
         .flatten()
 }
 
-fn range_vec_bounds_i32(ranges: &Vec<Range<i32>>) -> (i32, i32) {
-    let mut min = i32::MAX;
-    let mut max = i32::MIN;
-    for r in ranges {
-        match r {
-            Range::Single(i) => {
-                if *i < min {
-                    min = *i;
-                }
-                if *i > max {
-                    max = *i;
-                }
-            }
-            Range::Bounded(i, j) => {
-                if *i < min {
-                    min = *i;
-                }
-                if *j > max {
-                    max = *j;
-                }
-            }
-        }
+/// Essence's division, which rounds down, or `None` for division by 0 or on overflow.
+pub(crate) fn floor_div(x: i32, y: i32) -> Option<i32> {
+    let q = x.checked_div(y)?;
+    if x.checked_rem(y)? != 0 && (x < 0) != (y < 0) {
+        Some(q - 1)
+    } else {
+        Some(q)
+    }
+}
+
+/// Essence's modulo, which takes the sign of the divisor, or `None` for modulo by 0 or on overflow.
+pub(crate) fn floor_mod(x: i32, y: i32) -> Option<i32> {
+    let r = x.checked_rem(y)?;
+    if r != 0 && (r < 0) != (y < 0) {
+        r.checked_add(y)
+    } else {
+        Some(r)
     }
-    (min, max)
+}
+
+/// `x` to the power of `y`, or `None` for a negative exponent or on overflow.
+pub(crate) fn checked_pow(x: i32, y: i32) -> Option<i32> {
+    x.checked_pow(u32::try_from(y).ok()?)
 }
 
 impl Expression {
@@ -158,25 +205,179 @@ impl Expression {
                 expr_vec_to_domain_i32(exprs, |x, y| Some(if x < y { x } else { y }), vars)
             }
             Expression::UnsafeDiv(_, a, b) | Expression::SafeDiv(_, a, b) => {
-                a.domain_of(vars)?.apply_i32(
-                    |x, y| if y != 0 { Some(x / y) } else { None },
-                    &b.domain_of(vars)?,
-                )
+                a.domain_of(vars)?.apply_i32(floor_div, &b.domain_of(vars)?)
             }
+            Expression::UnsafeMod(_, a, b) | Expression::SafeMod(_, a, b) => {
+                a.domain_of(vars)?.apply_i32(floor_mod, &b.domain_of(vars)?)
+            }
+            Expression::UnsafePow(_, a, b) | Expression::SafePow(_, a, b) => a
+                .domain_of(vars)?
+                .apply_i32(checked_pow, &b.domain_of(vars)?),
             _ => todo!("Calculate domain of {:?}", self),
             // TODO: (flm8) Add support for calculating the domains of more expression types
         };
         match ret {
-            // TODO: (flm8) the Minion bindings currently only support single ranges for domains, so we use the min/max bounds
-            // Once they support a full domain as we define it, we can remove this conversion
-            Some(Domain::IntDomain(ranges)) if ranges.len() > 1 => {
-                let (min, max) = range_vec_bounds_i32(&ranges);
-                Some(Domain::IntDomain(vec![Range::Bounded(min, max)]))
-            }
+            // Merge the single values given by apply_i32, keeping any holes
+            Some(domain @ Domain::IntDomain(_)) => Some(domain.simplified()),
             _ => ret,
         }
     }
 
+    /// Compares expressions by their structure alone, ignoring metadata: first by the kind of expression (in
+    /// the order the variants are declared), then by the constant or name it holds, if any, then by its
+    /// children in order.
+    ///
+    /// Unlike a hash, this order does not depend on the standard library's hasher, so it can be used to put
+    /// expressions in a canonical order.
+    pub fn structural_cmp(&self, other: &Expression) -> Ordering {
+        let ordering = ExpressionKind::from(self)
+            .cmp(&ExpressionKind::from(other))
+            .then_with(|| match (self, other) {
+                (Expression::Constant(_, a), Expression::Constant(_, b)) => a.cmp(b),
+                (Expression::Reference(_, a), Expression::Reference(_, b)) => a.cmp(b),
+                _ => Ordering::Equal,
+            });
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+
+        let (children, other_children) = (self.children(), other.children());
+        for (child, other_child) in children.iter().zip(other_children.iter()) {
+            let ordering = child.structural_cmp(other_child);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        children.len().cmp(&other_children.len())
+    }
+
+    /// Whether two expressions are equal, ignoring their metadata.
+    pub fn structural_eq(&self, other: &Expression) -> bool {
+        self.structural_cmp(other) == Ordering::Equal
+    }
+
+    /// The canonical order of operands: compound expressions first (by kind, then by
+    /// [`Expression::structural_cmp`]), then references by name, then constants by value.
+    pub fn canonical_cmp(&self, other: &Expression) -> Ordering {
+        fn rank(expr: &Expression) -> u8 {
+            match expr {
+                Expression::Reference(_, _) => 1,
+                Expression::Constant(_, _) => 2,
+                _ => 0,
+            }
+        }
+
+        match (self, other) {
+            (Expression::Reference(_, a), Expression::Reference(_, b)) => a.cmp(b),
+            (Expression::Constant(_, a), Expression::Constant(_, b)) => match (a, b) {
+                (Constant::Bool(a), Constant::Bool(b)) => a.cmp(b),
+                (Constant::Int(a), Constant::Int(b)) => a.cmp(b),
+                (Constant::Bool(_), Constant::Int(_)) => Ordering::Less,
+                (Constant::Int(_), Constant::Bool(_)) => Ordering::Greater,
+            },
+            _ => rank(self)
+                .cmp(&rank(other))
+                .then_with(|| self.structural_cmp(other)),
+        }
+    }
+
+    /// A copy of the expression with the operands of the commutative operators (`Sum`, `Min`, `And` and `Or`)
+    /// in the order given by [`Expression::canonical_cmp`], and default metadata, at every level. Two expressions
+    /// that are equal up to their metadata and the order of those operands have equal normal forms.
+    pub fn commutative_normal_form(&self) -> Expression {
+        let children = self
+            .children()
+            .iter()
+            .map(Expression::commutative_normal_form)
+            .collect();
+        let mut expr = self
+            .with_children(children)
+            .unwrap_or_else(|_| self.clone());
+        if let Some(metadata) = expr.metadata_mut() {
+            *metadata = Metadata::new();
+        }
+        match expr {
+            Expression::Sum(m, exprs) => Expression::Sum(m, sort_operands(exprs)),
+            Expression::Min(m, exprs) => Expression::Min(m, sort_operands(exprs)),
+            Expression::And(m, exprs) => Expression::And(m, sort_operands(exprs)),
+            Expression::Or(m, exprs) => Expression::Or(m, sort_operands(exprs)),
+            expr => expr,
+        }
+    }
+
+    /// A copy of the expression with every reference to `name` replaced by `replacement`.
+    pub fn substitute(&self, name: &Name, replacement: &Expression) -> Expression {
+        match self {
+            Expression::Reference(_, n) if n == name => replacement.clone(),
+            _ => {
+                let children = self.children();
+                if children.is_empty() {
+                    return self.clone();
+                }
+                let children = children
+                    .iter()
+                    .map(|child| child.substitute(name, replacement))
+                    .collect();
+                self.with_children(children)
+                    .unwrap_or_else(|_| self.clone())
+            }
+        }
+    }
+
+    /// Whether the expression refers to the variable `name` anywhere.
+    pub fn mentions(&self, name: &Name) -> bool {
+        match self {
+            Expression::Reference(_, n) => n == name,
+            _ => self.children().iter().any(|child| child.mentions(name)),
+        }
+    }
+
+    /// The condition under which this operator is defined, assuming its operands are, or `None` if it is
+    /// always defined.
+    ///
+    /// Each partial operator has a total counterpart, given by [`Expression::safe_version`], which the
+    /// Bubble rules use in its place while carrying this condition up to the nearest boolean context.
+    /// ```text
+    /// a / b     defined if b != 0
+    /// a % b     defined if b != 0
+    /// a ** b    defined if b >= 0
+    /// min([])   never defined
+    /// ```
+    // TODO: matrix indexing is undefined out of range, but matrices are not in the AST yet
+    pub fn definedness_condition(&self) -> Option<Expression> {
+        match self {
+            Expression::UnsafeDiv(_, _, b) | Expression::UnsafeMod(_, _, b) => Some(
+                Expression::Neq(Metadata::new(), b.clone(), Arc::new(Expression::from(0))),
+            ),
+            Expression::UnsafePow(_, _, b) => Some(Expression::Geq(
+                Metadata::new(),
+                b.clone(),
+                Arc::new(Expression::from(0)),
+            )),
+            Expression::Min(_, exprs) if exprs.is_empty() => Some(Expression::from(false)),
+            _ => None,
+        }
+    }
+
+    /// The total counterpart of a partial operator, which agrees with it wherever its
+    /// [`definedness_condition`](Expression::definedness_condition) holds, or `None` if it is always defined.
+    pub fn safe_version(&self) -> Option<Expression> {
+        match self {
+            Expression::UnsafeDiv(_, a, b) => {
+                Some(Expression::SafeDiv(Metadata::new(), a.clone(), b.clone()))
+            }
+            Expression::UnsafeMod(_, a, b) => {
+                Some(Expression::SafeMod(Metadata::new(), a.clone(), b.clone()))
+            }
+            Expression::UnsafePow(_, a, b) => {
+                Some(Expression::SafePow(Metadata::new(), a.clone(), b.clone()))
+            }
+            // Any value will do, as the condition never holds
+            Expression::Min(_, exprs) if exprs.is_empty() => Some(Expression::from(0)),
+            _ => None,
+        }
+    }
+
     pub fn can_be_undefined(&self) -> bool {
         // TODO: there will be more false cases but we are being conservative
         match self {
@@ -205,10 +406,17 @@ impl Expression {
             Expression::Lt(_, _, _) => Some(ReturnType::Bool),
             Expression::SafeDiv(_, _, _) => Some(ReturnType::Int),
             Expression::UnsafeDiv(_, _, _) => Some(ReturnType::Int),
+            Expression::SafeMod(_, _, _) => Some(ReturnType::Int),
+            Expression::UnsafeMod(_, _, _) => Some(ReturnType::Int),
+            Expression::SafePow(_, _, _) => Some(ReturnType::Int),
+            Expression::UnsafePow(_, _, _) => Some(ReturnType::Int),
             Expression::SumEq(_, _, _) => Some(ReturnType::Bool),
             Expression::SumGeq(_, _, _) => Some(ReturnType::Bool),
             Expression::SumLeq(_, _, _) => Some(ReturnType::Bool),
             Expression::DivEq(_, _, _, _) => Some(ReturnType::Bool),
+            Expression::ModEq(_, _, _, _) => Some(ReturnType::Bool),
+            Expression::PowEq(_, _, _, _) => Some(ReturnType::Bool),
+            Expression::MinEq(_, _, _) => Some(ReturnType::Bool),
             Expression::Ineq(_, _, _, _) => Some(ReturnType::Bool),
             Expression::AllDiff(_, _) => Some(ReturnType::Bool),
             Expression::Bubble(_, _, _) => None, // TODO: (flm8) should this be a bool?
@@ -216,6 +424,85 @@ impl Expression {
         }
     }
 
+    /// The name of this expression's variant, e.g. `"SumLeq"`.
+    pub fn variant_name(&self) -> &'static str {
+        self.into()
+    }
+
+    /// The metadata of the expression, or `None` for [`Expression::Nothing`].
+    pub fn metadata(&self) -> Option<&Metadata> {
+        match self {
+            Expression::Nothing => None,
+            Expression::Constant(metadata, _) | Expression::Reference(metadata, _) => {
+                Some(metadata)
+            }
+            Expression::Sum(metadata, _)
+            | Expression::Min(metadata, _)
+            | Expression::Or(metadata, _)
+            | Expression::And(metadata, _)
+            | Expression::AllDiff(metadata, _) => Some(metadata),
+            Expression::Not(metadata, _) => Some(metadata),
+            Expression::Bubble(metadata, _, _)
+            | Expression::Eq(metadata, _, _)
+            | Expression::Neq(metadata, _, _)
+            | Expression::Geq(metadata, _, _)
+            | Expression::Leq(metadata, _, _)
+            | Expression::Gt(metadata, _, _)
+            | Expression::Lt(metadata, _, _)
+            | Expression::SafeDiv(metadata, _, _)
+            | Expression::UnsafeDiv(metadata, _, _)
+            | Expression::SafeMod(metadata, _, _)
+            | Expression::UnsafeMod(metadata, _, _)
+            | Expression::SafePow(metadata, _, _)
+            | Expression::UnsafePow(metadata, _, _) => Some(metadata),
+            Expression::SumEq(metadata, _, _)
+            | Expression::SumGeq(metadata, _, _)
+            | Expression::SumLeq(metadata, _, _)
+            | Expression::MinEq(metadata, _, _) => Some(metadata),
+            Expression::DivEq(metadata, _, _, _)
+            | Expression::ModEq(metadata, _, _, _)
+            | Expression::PowEq(metadata, _, _, _)
+            | Expression::Ineq(metadata, _, _, _) => Some(metadata),
+        }
+    }
+
+    /// The metadata of the expression, or `None` for [`Expression::Nothing`].
+    pub(crate) fn metadata_mut(&mut self) -> Option<&mut Metadata> {
+        match self {
+            Expression::Nothing => None,
+            Expression::Constant(metadata, _) | Expression::Reference(metadata, _) => {
+                Some(metadata)
+            }
+            Expression::Sum(metadata, _)
+            | Expression::Min(metadata, _)
+            | Expression::Or(metadata, _)
+            | Expression::And(metadata, _)
+            | Expression::AllDiff(metadata, _) => Some(metadata),
+            Expression::Not(metadata, _) => Some(metadata),
+            Expression::Bubble(metadata, _, _)
+            | Expression::Eq(metadata, _, _)
+            | Expression::Neq(metadata, _, _)
+            | Expression::Geq(metadata, _, _)
+            | Expression::Leq(metadata, _, _)
+            | Expression::Gt(metadata, _, _)
+            | Expression::Lt(metadata, _, _)
+            | Expression::SafeDiv(metadata, _, _)
+            | Expression::UnsafeDiv(metadata, _, _)
+            | Expression::SafeMod(metadata, _, _)
+            | Expression::UnsafeMod(metadata, _, _)
+            | Expression::SafePow(metadata, _, _)
+            | Expression::UnsafePow(metadata, _, _) => Some(metadata),
+            Expression::SumEq(metadata, _, _)
+            | Expression::SumGeq(metadata, _, _)
+            | Expression::SumLeq(metadata, _, _)
+            | Expression::MinEq(metadata, _, _) => Some(metadata),
+            Expression::DivEq(metadata, _, _, _)
+            | Expression::ModEq(metadata, _, _, _)
+            | Expression::PowEq(metadata, _, _, _)
+            | Expression::Ineq(metadata, _, _, _) => Some(metadata),
+        }
+    }
+
     pub fn is_clean(&self) -> bool {
         match self {
             Expression::Nothing => true,
@@ -237,6 +524,7 @@ impl Expression {
             Expression::Ineq(metadata, box1, box2, box3) => metadata.clean,
             Expression::AllDiff(metadata, exprs) => metadata.clean,
             Expression::SumEq(metadata, exprs, expr) => metadata.clean,
+            Expression::MinEq(metadata, _, _) => metadata.clean,
             _ => false,
         }
     }
@@ -294,6 +582,9 @@ impl Expression {
             Expression::SumEq(metadata, _exprs, _expr) => {
                 metadata.clean = bool_value;
             }
+            Expression::MinEq(metadata, _exprs, _expr) => {
+                metadata.clean = bool_value;
+            }
             Expression::Bubble(metadata, box1, box2) => {
                 metadata.clean = bool_value;
             }
@@ -303,9 +594,24 @@ impl Expression {
             Expression::UnsafeDiv(metadata, box1, box2) => {
                 metadata.clean = bool_value;
             }
+            Expression::SafeMod(metadata, _box1, _box2) => {
+                metadata.clean = bool_value;
+            }
+            Expression::UnsafeMod(metadata, _box1, _box2) => {
+                metadata.clean = bool_value;
+            }
+            Expression::SafePow(metadata, _box1, _box2) => {
+                metadata.clean = bool_value;
+            }
+            Expression::UnsafePow(metadata, _box1, _box2) => {
+                metadata.clean = bool_value;
+            }
             Expression::DivEq(metadata, box1, box2, box3) => {
                 metadata.clean = bool_value;
             }
+            Expression::ModEq(metadata, _, _, _) | Expression::PowEq(metadata, _, _, _) => {
+                metadata.clean = bool_value;
+            }
         }
     }
 }
@@ -335,6 +641,14 @@ impl From<i32> for Expression {
     }
 }
 
+/// Sort operands with [`Expression::structural_cmp`], which ignores metadata, so any order of the same
+/// operands sorts the same way.
+fn sort_operands(exprs: Arc<Vec<Expression>>) -> Arc<Vec<Expression>> {
+    let mut exprs = exprs.as_ref().clone();
+    exprs.sort_by(Expression::canonical_cmp);
+    Arc::new(exprs)
+}
+
 impl From<bool> for Expression {
     fn from(b: bool) -> Self {
         Expression::Constant(Metadata::new(), Constant::Bool(b))
@@ -411,6 +725,14 @@ impl Display for Expression {
             Expression::AllDiff(_, expressions) => {
                 write!(f, "AllDiff({})", display_expressions(expressions))
             }
+            Expression::MinEq(_, expressions, expr_box) => {
+                write!(
+                    f,
+                    "MinEq({}, {})",
+                    display_expressions(expressions),
+                    expr_box.clone()
+                )
+            }
             Expression::Bubble(_, box1, box2) => {
                 write!(f, "{{{} @ {}}}", box1.clone(), box2.clone())
             }
@@ -420,6 +742,18 @@ impl Display for Expression {
             Expression::UnsafeDiv(_, box1, box2) => {
                 write!(f, "UnsafeDiv({}, {})", box1.clone(), box2.clone())
             }
+            Expression::SafeMod(_, box1, box2) => {
+                write!(f, "SafeMod({}, {})", box1.clone(), box2.clone())
+            }
+            Expression::UnsafeMod(_, box1, box2) => {
+                write!(f, "UnsafeMod({}, {})", box1.clone(), box2.clone())
+            }
+            Expression::SafePow(_, box1, box2) => {
+                write!(f, "SafePow({}, {})", box1.clone(), box2.clone())
+            }
+            Expression::UnsafePow(_, box1, box2) => {
+                write!(f, "UnsafePow({}, {})", box1.clone(), box2.clone())
+            }
             Expression::DivEq(_, box1, box2, box3) => {
                 write!(
                     f,
@@ -429,6 +763,12 @@ impl Display for Expression {
                     box3.clone()
                 )
             }
+            Expression::ModEq(_, box1, box2, box3) => {
+                write!(f, "ModEq({}, {}, {})", box1, box2, box3)
+            }
+            Expression::PowEq(_, box1, box2, box3) => {
+                write!(f, "PowEq({}, {}, {})", box1, box2, box3)
+            }
             #[allow(unreachable_patterns)]
             other => todo!("Implement display for {:?}", other),
         }
@@ -445,7 +785,7 @@ mod tests {
     fn test_domain_of_constant_sum() {
         let c1 = Expression::Constant(Metadata::new(), Constant::Int(1));
         let c2 = Expression::Constant(Metadata::new(), Constant::Int(2));
-        let sum = Expression::Sum(Metadata::new(), vec![c1.clone(), c2.clone()]);
+        let sum = Expression::Sum(Metadata::new(), vec![c1.clone(), c2.clone()].into());
         assert_eq!(
             sum.domain_of(&SymbolTable::new()),
             Some(Domain::IntDomain(vec![Range::Single(3)]))
@@ -456,13 +796,13 @@ mod tests {
     fn test_domain_of_constant_invalid_type() {
         let c1 = Expression::Constant(Metadata::new(), Constant::Int(1));
         let c2 = Expression::Constant(Metadata::new(), Constant::Bool(true));
-        let sum = Expression::Sum(Metadata::new(), vec![c1.clone(), c2.clone()]);
+        let sum = Expression::Sum(Metadata::new(), vec![c1.clone(), c2.clone()].into());
         assert_eq!(sum.domain_of(&SymbolTable::new()), None);
     }
 
     #[test]
     fn test_domain_of_empty_sum() {
-        let sum = Expression::Sum(Metadata::new(), vec![]);
+        let sum = Expression::Sum(Metadata::new(), vec![].into());
         assert_eq!(sum.domain_of(&SymbolTable::new()), None);
     }
 
@@ -494,7 +834,10 @@ mod tests {
             Name::MachineName(0),
             DecisionVariable::new(Domain::IntDomain(vec![Range::Single(1)])),
         );
-        let sum = Expression::Sum(Metadata::new(), vec![reference.clone(), reference.clone()]);
+        let sum = Expression::Sum(
+            Metadata::new(),
+            vec![reference.clone(), reference.clone()].into(),
+        );
         assert_eq!(
             sum.domain_of(&vars),
             Some(Domain::IntDomain(vec![Range::Single(2)]))
@@ -509,10 +852,114 @@ mod tests {
             Name::MachineName(0),
             DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(1, 2)])),
         );
-        let sum = Expression::Sum(Metadata::new(), vec![reference.clone(), reference.clone()]);
+        let sum = Expression::Sum(
+            Metadata::new(),
+            vec![reference.clone(), reference.clone()].into(),
+        );
         assert_eq!(
             sum.domain_of(&vars),
             Some(Domain::IntDomain(vec![Range::Bounded(2, 4)]))
         );
     }
+
+    #[test]
+    fn test_domain_of_sum_keeps_holes() {
+        let reference = |n| Expression::Reference(Metadata::new(), Name::MachineName(n));
+        let mut vars = SymbolTable::new();
+        vars.insert(
+            Name::MachineName(0),
+            DecisionVariable::new(Domain::IntDomain(vec![Range::Single(0), Range::Single(10)])),
+        );
+        vars.insert(
+            Name::MachineName(1),
+            DecisionVariable::new(Domain::IntDomain(vec![Range::Bounded(0, 1)])),
+        );
+        let sum = Expression::Sum(Metadata::new(), vec![reference(0), reference(1)].into());
+        assert_eq!(
+            sum.domain_of(&vars),
+            Some(Domain::IntDomain(vec![
+                Range::Bounded(0, 1),
+                Range::Bounded(10, 11)
+            ]))
+        );
+    }
+
+    fn hash_of(expr: &Expression) -> u64 {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+        let mut hasher = DefaultHasher::new();
+        expr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_hash_ignores_metadata() {
+        let reference = Expression::Reference(Metadata::new(), Name::MachineName(0));
+        let dirty = Expression::Sum(Metadata::new(), vec![reference.clone(), 1.into()].into());
+        let mut clean = dirty.clone();
+        clean.set_clean(true);
+        assert_eq!(hash_of(&dirty), hash_of(&clean));
+    }
+
+    #[test]
+    fn test_hash_structural() {
+        let reference = Expression::Reference(Metadata::new(), Name::MachineName(0));
+        let sum_1 = Expression::Sum(Metadata::new(), vec![reference.clone(), 1.into()].into());
+        let sum_2 = Expression::Sum(Metadata::new(), vec![reference.clone(), 2.into()].into());
+        assert_eq!(hash_of(&sum_1), hash_of(&sum_1.clone()));
+        assert_ne!(hash_of(&sum_1), hash_of(&sum_2));
+    }
+
+    #[test]
+    fn test_structural_eq_ignores_metadata() {
+        let reference = Expression::Reference(Metadata::new(), Name::MachineName(0));
+        let dirty = Expression::Sum(Metadata::new(), vec![reference.clone(), 1.into()].into());
+        let mut clean = dirty.clone();
+        clean.set_clean(true);
+        assert_ne!(dirty, clean);
+        assert!(dirty.structural_eq(&clean));
+        assert!(!dirty.structural_eq(&Expression::Sum(
+            Metadata::new(),
+            vec![reference, 2.into()].into()
+        )));
+    }
+
+    #[test]
+    fn test_structural_cmp_orders_by_kind_then_contents() {
+        let a = Expression::Reference(Metadata::new(), Name::UserName("a".into()));
+        let b = Expression::Reference(Metadata::new(), Name::UserName("b".into()));
+        let sum_a = Expression::Sum(Metadata::new(), vec![a.clone()].into());
+        let sum_ab = Expression::Sum(Metadata::new(), vec![a.clone(), b.clone()].into());
+        let sum_b = Expression::Sum(Metadata::new(), vec![b.clone()].into());
+
+        // Constants are declared before references, and references before sums
+        assert_eq!(Expression::from(5).structural_cmp(&a), Ordering::Less);
+        assert_eq!(a.structural_cmp(&sum_a), Ordering::Less);
+        assert_eq!(a.structural_cmp(&b), Ordering::Less);
+        assert_eq!(sum_a.structural_cmp(&sum_ab), Ordering::Less);
+        assert_eq!(sum_ab.structural_cmp(&sum_b), Ordering::Less);
+        assert_eq!(sum_b.structural_cmp(&sum_a), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_commutative_normal_form_ignores_order_and_metadata() {
+        let a = Expression::Reference(Metadata::new(), Name::UserName("a".into()));
+        let b = Expression::Reference(Metadata::new(), Name::UserName("b".into()));
+        let ab = Expression::Sum(Metadata::new(), vec![a.clone(), b.clone(), 1.into()].into());
+        let mut ba = Expression::Sum(Metadata::new(), vec![1.into(), b, a.clone()].into());
+        ba.set_clean(true);
+
+        assert_eq!(ab.commutative_normal_form(), ba.commutative_normal_form());
+        assert_eq!(
+            ab.commutative_normal_form(),
+            Expression::Sum(
+                Metadata::new(),
+                vec![
+                    a,
+                    Expression::Reference(Metadata::new(), Name::UserName("b".into())),
+                    1.into()
+                ]
+                .into()
+            )
+        );
+    }
 }
@@ -9,6 +9,7 @@ pub use constants::Constant;
 pub use domains::Domain;
 pub use domains::Range;
 pub use expressions::Expression;
+pub(crate) use expressions::{checked_pow, floor_div, floor_mod};
 pub use symbol_table::Name;
 pub use symbol_table::SymbolTable;
 pub use types::ReturnType;
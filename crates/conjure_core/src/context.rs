@@ -6,7 +6,7 @@ use schemars::JsonSchema;
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 
-use crate::rule_engine::{Rule, RuleSet};
+use crate::rule_engine::{RewritePolicy, Rule, RuleOverrides, RuleSet};
 use crate::solver::SolverFamily;
 use crate::stats::Stats;
 
@@ -22,6 +22,10 @@ pub struct Context<'a> {
 
     pub extra_rule_set_names: Vec<String>,
 
+    pub rule_overrides: RuleOverrides,
+
+    pub rewrite_policy: RewritePolicy,
+
     #[serde(skip)]
     pub rules: Vec<&'a Rule<'a>>,
 
@@ -70,6 +74,8 @@ impl<'a> Debug for Context<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let target_solver_family: Option<SolverFamily> = self.target_solver_family;
         let extra_rule_set_names: Vec<String> = self.extra_rule_set_names.clone();
+        let rule_overrides: &RuleOverrides = &self.rule_overrides;
+        let rewrite_policy: RewritePolicy = self.rewrite_policy;
         let rules: Vec<&str> = self.rules.iter().map(|r| r.name).collect();
         let rule_sets: Vec<&str> = self.rule_sets.iter().map(|r| r.name).collect();
 
@@ -78,10 +84,17 @@ impl<'a> Debug for Context<'a> {
             "Context {{\n\
             \ttarget_solver_family: {:?}\n\
             \textra_rule_set_names: {:?}\n\
+            \trule_overrides: {:?}\n\
+            \trewrite_policy: {:?}\n\
             \trules: {:?}\n\
             \trule_sets: {:?}\n\
         }}",
-            target_solver_family, extra_rule_set_names, rules, rule_sets
+            target_solver_family,
+            extra_rule_set_names,
+            rule_overrides,
+            rewrite_policy,
+            rules,
+            rule_sets
         )
     }
 }
@@ -0,0 +1,261 @@
+//! Elimination of variables whose value the top-level constraints fix, either to a constant (`x = 3`, a
+//! single-valued domain, or a top-level boolean `p` or `!p`) or to another variable (`x = y`).
+//!
+//! Each eliminated variable is replaced by its constant or remaining variable throughout the constraints and
+//! removed from the symbol table. The replacement is recorded in [`Model::eliminated`], from which
+//! [`restore_eliminated`] adds the variable back to the solutions.
+//!
+//! Auxiliary variables that nothing refers to any more are removed by [`remove_unused_variables`].
+
+use std::collections::{HashMap, HashSet};
+
+use uniplate::uniplate::Uniplate;
+
+use crate::ast::{Constant, Domain, Expression, Name};
+use crate::metadata::Metadata;
+use crate::rules::eval_constant;
+use crate::Model;
+
+/// Eliminate fixed and aliased variables from the model until there are none left.
+///
+/// Of two equal variables, an auxiliary variable is eliminated in favour of a user variable, and otherwise
+/// the second is eliminated in favour of the first. The remaining variable's domain becomes the
+/// intersection of the two. Constraints that become true are
+/// removed; constraints that become false are left for the solver (or an earlier check) to report.
+pub fn eliminate_variables(model: &mut Model) {
+    while let Some((name, replacement)) = find_elimination(model) {
+        eliminate(model, &name, replacement);
+    }
+
+    let constraints = model
+        .get_constraints_vec()
+        .into_iter()
+        .filter(|c| match c {
+            // Left by eliminating one of two equal variables
+            Expression::Eq(_, a, b) => match (a.as_ref(), b.as_ref()) {
+                (Expression::Reference(_, x), Expression::Reference(_, y)) => x != y,
+                _ => eval_constant(c) != Some(Constant::Bool(true)),
+            },
+            _ => eval_constant(c) != Some(Constant::Bool(true)),
+        })
+        .collect();
+    model.set_constraints(constraints);
+}
+
+/// Remove the auxiliary variables that neither the constraints nor an eliminated variable refer to.
+///
+/// Unused `find` variables are kept, as each of their values gives a different solution.
+pub fn remove_unused_variables(model: &mut Model) {
+    let used: HashSet<Name> = std::iter::once(&model.constraints)
+        .chain(model.eliminated.values())
+        .flat_map(|expr| expr.universe())
+        .filter_map(|expr| match expr {
+            Expression::Reference(_, name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    model
+        .variables
+        .retain(|name, _| matches!(name, Name::UserName(_)) || used.contains(name));
+}
+
+/// Add the values of eliminated variables, as recorded in [`Model::eliminated`], to a solution of the model.
+pub fn restore_eliminated(
+    eliminated: &HashMap<Name, Expression>,
+    solution: &mut HashMap<Name, Constant>,
+) {
+    for (name, replacement) in eliminated {
+        let value = match replacement {
+            Expression::Constant(_, c) => Some(c.clone()),
+            Expression::Reference(_, other) => solution.get(other).cloned(),
+            _ => None,
+        };
+        if let Some(value) = value {
+            solution.insert(name.clone(), value);
+        }
+    }
+}
+
+/// A variable that can be eliminated, and what to replace it with.
+fn find_elimination(model: &Model) -> Option<(Name, Expression)> {
+    for (name, var) in &model.variables {
+        if let Some((lo, hi)) = var.domain.bounds_i32() {
+            if lo == hi {
+                return Some((name.clone(), Expression::from(lo)));
+            }
+        }
+    }
+
+    for constraint in model.get_constraints_vec() {
+        let found = match &constraint {
+            Expression::Reference(_, name) => fixed(model, name, &Constant::Bool(true)),
+            Expression::Not(_, expr) => match expr.as_ref() {
+                Expression::Reference(_, name) => fixed(model, name, &Constant::Bool(false)),
+                _ => None,
+            },
+            Expression::Eq(_, a, b) => match (a.as_ref(), b.as_ref()) {
+                (Expression::Reference(_, name), Expression::Constant(_, c))
+                | (Expression::Constant(_, c), Expression::Reference(_, name)) => {
+                    fixed(model, name, c)
+                }
+                (Expression::Reference(_, x), Expression::Reference(_, y)) => aliased(model, x, y),
+                _ => None,
+            },
+            _ => None,
+        };
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+/// Replace `name` with `value`, if it is a variable whose domain contains the value.
+fn fixed(model: &Model, name: &Name, value: &Constant) -> Option<(Name, Expression)> {
+    let contains = match (model.get_domain(name)?, value) {
+        (Domain::BoolDomain, Constant::Bool(_)) => true,
+        (domain @ Domain::IntDomain(_), Constant::Int(i)) => {
+            domain.restrict_i32(*i, *i).bounds_i32().is_some()
+        }
+        _ => false,
+    };
+    contains.then(|| {
+        (
+            name.clone(),
+            Expression::Constant(Metadata::new(), value.clone()),
+        )
+    })
+}
+
+/// Replace one of two different variables with the other, if their domains have values in common.
+fn aliased(model: &Model, x: &Name, y: &Name) -> Option<(Name, Expression)> {
+    if x == y {
+        return None;
+    }
+    let domain = model.get_domain(x)?.intersect(model.get_domain(y)?)?;
+    if domain.bounds_i32().is_none() && domain != Domain::BoolDomain {
+        return None;
+    }
+    // Keep user variables where possible
+    let (removed, kept) = match (x, y) {
+        (Name::MachineName(_), Name::UserName(_)) => (x, y),
+        _ => (y, x),
+    };
+    Some((
+        removed.clone(),
+        Expression::Reference(Metadata::new(), kept.clone()),
+    ))
+}
+
+/// Replace the variable `name` with `replacement` in the constraints and in earlier eliminations.
+fn eliminate(model: &mut Model, name: &Name, replacement: Expression) {
+    // The remaining variable must also take a value in the eliminated variable's domain
+    if let Expression::Reference(_, kept) = &replacement {
+        if let (Some(a), Some(b)) = (model.get_domain(name), model.get_domain(kept)) {
+            if let Some(domain) = a.intersect(b) {
+                model.update_domain(kept, domain);
+            }
+        }
+    }
+
+    model.variables.remove(name);
+    model.constraints = model.constraints.substitute(name, &replacement);
+    for value in model.eliminated.values_mut() {
+        *value = value.substitute(name, &replacement);
+    }
+    model.eliminated.insert(name.clone(), replacement);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::ast::DecisionVariable;
+    use crate::test_utils::{int, name, var};
+
+    use super::*;
+
+    fn eq(a: Expression, b: Expression) -> Expression {
+        Expression::Eq(Metadata::new(), Arc::new(a), Arc::new(b))
+    }
+
+    #[test]
+    fn fixed_and_aliased_variables_are_eliminated() {
+        let mut model = Model::new_empty(Default::default());
+        model.add_variable(name("x"), DecisionVariable::new(int(0, 5)));
+        model.add_variable(name("y"), DecisionVariable::new(int(2, 9)));
+        model.add_variable(name("z"), DecisionVariable::new(int(0, 9)));
+        model.add_variable(name("p"), DecisionVariable::new(Domain::BoolDomain));
+        let aux = Name::MachineName(0);
+        model.add_variable(aux.clone(), DecisionVariable::new(int(0, 9)));
+        let leq = Expression::Leq(Metadata::new(), Arc::new(var("y")), Arc::new(var("z")));
+        model.set_constraints(vec![
+            eq(var("x"), var("y")),
+            eq(4.into(), var("z")),
+            var("p"),
+            eq(
+                Expression::Reference(Metadata::new(), aux.clone()),
+                var("y"),
+            ),
+            leq,
+        ]);
+
+        eliminate_variables(&mut model);
+
+        // x = y keeps x, as it comes first, with the values both can take
+        assert_eq!(model.variables.keys().collect::<Vec<_>>(), vec![&name("x")]);
+        assert_eq!(model.get_domain(&name("x")), Some(&int(2, 5)));
+        assert_eq!(
+            model.constraints,
+            Expression::Leq(Metadata::new(), Arc::new(var("x")), Arc::new(4.into()))
+        );
+
+        let mut solution = HashMap::from([(name("x"), Constant::Int(3))]);
+        restore_eliminated(&model.eliminated, &mut solution);
+        assert_eq!(solution[&name("y")], Constant::Int(3));
+        assert_eq!(solution[&aux], Constant::Int(3));
+        assert_eq!(solution[&name("z")], Constant::Int(4));
+        assert_eq!(solution[&name("p")], Constant::Bool(true));
+    }
+
+    #[test]
+    fn values_outside_the_domain_are_left_to_the_solver() {
+        let mut model = Model::new_empty(Default::default());
+        model.add_variable(name("x"), DecisionVariable::new(int(0, 5)));
+        model.add_variable(name("y"), DecisionVariable::new(int(7, 9)));
+        model.set_constraints(vec![eq(var("x"), 6.into()), eq(var("x"), var("y"))]);
+        let before = model.clone();
+
+        eliminate_variables(&mut model);
+
+        assert_eq!(model, before);
+        assert!(model.eliminated.is_empty());
+    }
+
+    #[test]
+    fn only_unused_auxiliary_variables_are_removed() {
+        let mut model = Model::new_empty(Default::default());
+        for n in 0..3 {
+            model.add_variable(Name::MachineName(n), DecisionVariable::new(int(0, 9)));
+        }
+        model.add_variable(name("x"), DecisionVariable::new(int(0, 9)));
+        model.add_variable(name("y"), DecisionVariable::new(int(0, 9)));
+        let aux = |n| Expression::Reference(Metadata::new(), Name::MachineName(n));
+        model.set_constraints(vec![eq(aux(0), var("x"))]);
+        model.eliminated.insert(name("z"), aux(1));
+
+        remove_unused_variables(&mut model);
+
+        let mut names: Vec<&Name> = model.variables.keys().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                &name("x"),
+                &name("y"),
+                &Name::MachineName(0),
+                &Name::MachineName(1)
+            ]
+        );
+    }
+}
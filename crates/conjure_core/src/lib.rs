@@ -4,11 +4,16 @@ pub use model::Model;
 
 pub mod ast;
 pub mod context;
+pub mod eliminate;
 pub mod error;
 pub mod metadata;
 pub mod model;
 pub mod parse;
+pub mod propagate;
 pub mod rule_engine;
 pub mod rules;
 pub mod solver;
 pub mod stats;
+
+#[cfg(test)]
+mod test_utils;
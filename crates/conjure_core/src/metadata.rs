@@ -1,5 +1,6 @@
 use crate::ast::types::ReturnType;
 use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
 
 use serde::{Deserialize, Serialize};
 
@@ -25,6 +26,12 @@ impl Metadata {
     }
 }
 
+// Metadata is not part of an expression's structure, so it is left out of its hash.
+// This is still consistent with `Eq`, as equal metadata trivially hashes the same.
+impl Hash for Metadata {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
 impl Display for Metadata {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Metadata")
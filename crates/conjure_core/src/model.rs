@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::{Arc, RwLock};
 
@@ -21,6 +22,16 @@ pub struct Model {
     #[derivative(PartialEq = "ignore")]
     pub context: Arc<RwLock<Context<'static>>>,
     next_var: RefCell<i32>,
+    /// The auxiliary variables introduced to stand for expressions, by the commutative normal form of the
+    /// expression. Rules look here to reuse a variable rather than introduce another for an equal expression.
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub(crate) aux_vars: HashMap<Expression, Name>,
+    /// Variables removed from the model because the constraints fix their value, with the constant or the
+    /// remaining variable they were replaced by (see [`eliminate_variables`](crate::eliminate::eliminate_variables)).
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub eliminated: HashMap<Name, Expression>,
 }
 
 impl Model {
@@ -34,6 +45,8 @@ impl Model {
             constraints,
             context,
             next_var: RefCell::new(0),
+            aux_vars: HashMap::new(),
+            eliminated: HashMap::new(),
         }
     }
 
@@ -58,7 +71,7 @@ impl Model {
 
     pub fn get_constraints_vec(&self) -> Vec<Expression> {
         match &self.constraints {
-            Expression::And(_, constraints) => constraints.clone(),
+            Expression::And(_, constraints) => constraints.as_ref().clone(),
             Expression::Nothing => vec![],
             _ => vec![self.constraints.clone()],
         }
@@ -70,7 +83,7 @@ impl Model {
         } else if constraints.len() == 1 {
             self.constraints = constraints[0].clone();
         } else {
-            self.constraints = Expression::And(Metadata::new(), constraints);
+            self.constraints = Expression::And(Metadata::new(), constraints.into());
         }
     }
 
@@ -98,4 +111,24 @@ impl Model {
         *(self.next_var.borrow_mut()) += 1;
         Name::MachineName(num) // incremented when inserted
     }
+
+    /// The auxiliary variable that stands for an expression equal to `expr`, up to the order of the operands of
+    /// commutative operators, if there is one (see [`Reduction::define_aux_var`](crate::rule_engine::Reduction::define_aux_var)).
+    pub fn find_aux_var(&self, expr: &Expression) -> Option<Name> {
+        let name = self.aux_vars.get(&expr.commutative_normal_form())?;
+        // The variable may since have been removed
+        self.variables.contains_key(name).then(|| name.clone())
+    }
+
+    /// Record that the variable `name` stands for `expr`, so that [`Model::find_aux_var`] finds it.
+    pub fn add_aux_var(&mut self, name: Name, expr: &Expression) {
+        self.aux_vars.insert(expr.commutative_normal_form(), name);
+    }
+
+    /// Make sure [`Model::gensym`] does not return a name that `other` may already have generated, e.g. when
+    /// `other` is a modified copy of this model.
+    pub fn reserve_names_from(&self, other: &Model) {
+        let next = (*self.next_var.borrow()).max(*other.next_var.borrow());
+        *self.next_var.borrow_mut() = next;
+    }
 }
@@ -140,9 +140,9 @@ fn parse_int_domain(v: &JsonValue) -> Result<Domain> {
 }
 
 // this needs an explicit type signature to force the closures to have the same type
-type BinOp = Box<dyn Fn(Metadata, Box<Expression>, Box<Expression>) -> Expression>;
-type UnaryOp = Box<dyn Fn(Metadata, Box<Expression>) -> Expression>;
-type VecOp = Box<dyn Fn(Metadata, Vec<Expression>) -> Expression>;
+type BinOp = Box<dyn Fn(Metadata, Arc<Expression>, Arc<Expression>) -> Expression>;
+type UnaryOp = Box<dyn Fn(Metadata, Arc<Expression>) -> Expression>;
+type VecOp = Box<dyn Fn(Metadata, Arc<Vec<Expression>>) -> Expression>;
 
 fn parse_expression(obj: &JsonValue) -> Option<Expression> {
     let binary_operators: HashMap<&str, BinOp> = [
@@ -182,6 +182,14 @@ fn parse_expression(obj: &JsonValue) -> Option<Expression> {
             "MkOpDiv",
             Box::new(Expression::UnsafeDiv) as Box<dyn Fn(_, _, _) -> _>,
         ),
+        (
+            "MkOpMod",
+            Box::new(Expression::UnsafeMod) as Box<dyn Fn(_, _, _) -> _>,
+        ),
+        (
+            "MkOpPow",
+            Box::new(Expression::UnsafePow) as Box<dyn Fn(_, _, _) -> _>,
+        ),
     ]
     .into_iter()
     .collect();
@@ -254,7 +262,7 @@ fn parse_bin_op(
         Value::Array(bin_op_args) if bin_op_args.len() == 2 => {
             let arg1 = parse_expression(&bin_op_args[0])?;
             let arg2 = parse_expression(&bin_op_args[1])?;
-            Some(constructor(Metadata::new(), Box::new(arg1), Box::new(arg2)))
+            Some(constructor(Metadata::new(), Arc::new(arg1), Arc::new(arg2)))
         }
         otherwise => panic!("Unhandled parse_bin_op {:#?}", otherwise),
     }
@@ -268,7 +276,7 @@ fn parse_unary_op(
     let constructor = unary_operators.get(key.as_str())?;
 
     let arg = parse_expression(value)?;
-    Some(constructor(Metadata::new(), Box::new(arg)))
+    Some(constructor(Metadata::new(), Arc::new(arg)))
 }
 
 fn parse_vec_op(
@@ -289,7 +297,7 @@ fn parse_vec_op(
     if number_of_args != valid_args.len() {
         None
     } else {
-        Some(constructor(Metadata::new(), valid_args))
+        Some(constructor(Metadata::new(), valid_args.into()))
     }
 }
 
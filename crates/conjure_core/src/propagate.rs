@@ -0,0 +1,401 @@
+//! Bounds propagation over the top-level constraints of a model, to narrow domains before solving.
+//!
+//! Linear constraints (`SumLeq`, `SumGeq`, `SumEq`, `Ineq` and comparisons between variables and
+//! constants) are read as `c1*x1 + c2*x2 + ... <= k` with each coefficient 1 or -1, and each variable's
+//! bounds are tightened by what the other terms allow. Boolean variables, and boolean references and their
+//! negations at the top level, take part as 0 and 1. This repeats until no bound changes.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::ast::{Constant, Domain, Expression, Name};
+use crate::rules::eval_constant;
+use crate::Model;
+
+/// Propagation stops after this many passes over the constraints even if bounds are still changing, e.g.
+/// for `x < y & y < x` over large domains, which only moves each bound by one per pass.
+const MAX_PASSES: usize = 1000;
+
+/// Why bounds propagation found a model to be unsatisfiable.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum Unsatisfiable {
+    #[error("no value of {0} satisfies the constraints")]
+    EmptyDomain(Name),
+
+    #[error("the constraint {0} can never hold")]
+    FalseConstraint(Expression),
+}
+
+/// Narrow the domains of the model's integer variables to the bounds implied by its top-level constraints.
+///
+/// The constraints themselves are left unchanged. Constraints the propagator does not understand are ignored,
+/// so narrowing is sound but not necessarily complete.
+///
+/// # Returns
+/// - `Ok(())` with the narrowed domains written back to the model with [`Model::update_domain`]
+/// - `Unsatisfiable` if a constraint can never hold or a domain becomes empty; the model is then unchanged
+pub fn propagate_bounds(model: &mut Model) -> Result<(), Unsatisfiable> {
+    let mut propagator = Propagator::new(model)?;
+    let constraints = model.get_constraints_vec();
+    for _ in 0..MAX_PASSES {
+        propagator.changed = false;
+        for constraint in &constraints {
+            propagator.propagate(constraint)?;
+        }
+        if !propagator.changed {
+            break;
+        }
+    }
+
+    for (name, domain) in propagator.domains {
+        if model.get_domain(&name) != Some(&domain) {
+            model.update_domain(&name, domain);
+        }
+    }
+    Ok(())
+}
+
+/// Find a reason the rewritten model has no solutions without searching: a top-level constraint that
+/// constant folding has reduced to `false`, or a variable with no values left in its domain.
+pub fn detect_unsatisfiable(model: &Model) -> Option<Unsatisfiable> {
+    let collapsed = model
+        .get_constraints_vec()
+        .into_iter()
+        .find(|c| eval_constant(c) == Some(Constant::Bool(false)));
+    if let Some(constraint) = collapsed {
+        return Some(Unsatisfiable::FalseConstraint(constraint));
+    }
+
+    let mut names: Vec<&Name> = model.variables.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .find(|name| {
+            matches!(
+                model.get_domain(name),
+                Some(domain @ Domain::IntDomain(_)) if domain.bounds_i32().is_none()
+            )
+        })
+        .map(|name| Unsatisfiable::EmptyDomain(name.clone()))
+}
+
+/// A term of a linear constraint.
+enum Operand {
+    Var(Name),
+    Const(i64),
+}
+
+struct Propagator {
+    /// The current bounds of every variable, with false as 0 and true as 1.
+    bounds: HashMap<Name, (i64, i64)>,
+    /// The current domains of the integer variables.
+    domains: HashMap<Name, Domain>,
+    changed: bool,
+}
+
+impl Propagator {
+    fn new(model: &Model) -> Result<Propagator, Unsatisfiable> {
+        let mut bounds = HashMap::new();
+        let mut domains = HashMap::new();
+        for (name, var) in &model.variables {
+            match &var.domain {
+                Domain::BoolDomain => {
+                    bounds.insert(name.clone(), (0, 1));
+                }
+                domain @ Domain::IntDomain(_) => {
+                    let (lo, hi) = domain
+                        .bounds_i32()
+                        .ok_or_else(|| Unsatisfiable::EmptyDomain(name.clone()))?;
+                    bounds.insert(name.clone(), (lo.into(), hi.into()));
+                    domains.insert(name.clone(), domain.clone());
+                }
+            }
+        }
+        Ok(Propagator {
+            bounds,
+            domains,
+            changed: false,
+        })
+    }
+
+    fn propagate(&mut self, constraint: &Expression) -> Result<(), Unsatisfiable> {
+        use Expression::{
+            And, Eq, Geq, Gt, Ineq, Leq, Lt, Neq, Not, Reference, SumEq, SumGeq, SumLeq,
+        };
+        let le = |a: &Expression, b: &Expression| vec![(1, a.clone()), (-1, b.clone())];
+        match constraint {
+            And(_, exprs) => {
+                for expr in exprs.iter() {
+                    self.propagate(expr)?;
+                }
+            }
+            Expression::Constant(_, Constant::Bool(false)) => {
+                return Err(Unsatisfiable::FalseConstraint(constraint.clone()));
+            }
+            Reference(_, name) => self.tighten(name, 1, 1)?,
+            Not(_, expr) => {
+                if let Reference(_, name) = expr.as_ref() {
+                    self.tighten(name, 0, 0)?;
+                }
+            }
+            Eq(_, a, b) => {
+                self.linear(constraint, &le(a, b), 0)?;
+                self.linear(constraint, &le(b, a), 0)?;
+            }
+            Leq(_, a, b) => self.linear(constraint, &le(a, b), 0)?,
+            Geq(_, a, b) => self.linear(constraint, &le(b, a), 0)?,
+            Lt(_, a, b) => self.linear(constraint, &le(a, b), -1)?,
+            Gt(_, a, b) => self.linear(constraint, &le(b, a), -1)?,
+            Neq(_, a, b) => self.not_equal(constraint, a, b)?,
+            // a <= b + c
+            Ineq(_, a, b, c) => {
+                let mut terms = le(a, b);
+                terms.push((-1, c.as_ref().clone()));
+                self.linear(constraint, &terms, 0)?;
+            }
+            SumLeq(_, exprs, rhs) | SumGeq(_, exprs, rhs) | SumEq(_, exprs, rhs) => {
+                // sum(exprs) - rhs <= 0, and the reverse
+                let mut leq: Vec<(i64, Expression)> =
+                    exprs.iter().map(|e| (1, e.clone())).collect();
+                leq.push((-1, rhs.as_ref().clone()));
+                let geq: Vec<(i64, Expression)> =
+                    leq.iter().map(|(c, e)| (-c, e.clone())).collect();
+                if !constraint.is_sum_geq() {
+                    self.linear(constraint, &leq, 0)?;
+                }
+                if !constraint.is_sum_leq() {
+                    self.linear(constraint, &geq, 0)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Propagate `sum(c * x for (c, x) in terms) <= k`, where each `c` is 1 or -1. Does nothing if a term is
+    /// not a variable or constant.
+    fn linear(
+        &mut self,
+        constraint: &Expression,
+        terms: &[(i64, Expression)],
+        k: i64,
+    ) -> Result<(), Unsatisfiable> {
+        let Some(operands) = terms
+            .iter()
+            .map(|(c, e)| self.operand(e).map(|o| (*c, o)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(());
+        };
+
+        // The smallest value each term can take
+        let mins: Vec<i64> = operands
+            .iter()
+            .map(|(c, operand)| {
+                let (lo, hi) = self.operand_bounds(operand);
+                if *c > 0 {
+                    c * lo
+                } else {
+                    c * hi
+                }
+            })
+            .collect();
+        let total: i64 = mins.iter().sum();
+        if total > k {
+            return Err(Unsatisfiable::FalseConstraint(constraint.clone()));
+        }
+
+        for ((c, operand), min) in operands.iter().zip(&mins) {
+            let Operand::Var(name) = operand else {
+                continue;
+            };
+            // The most this term can be while the others are at their smallest
+            let slack = k - (total - min);
+            if *c > 0 {
+                self.tighten(name, i64::MIN, slack)?;
+            } else {
+                self.tighten(name, -slack, i64::MAX)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Propagate `a != b`, which removes a value from a bound when the other side is fixed.
+    fn not_equal(
+        &mut self,
+        constraint: &Expression,
+        a: &Expression,
+        b: &Expression,
+    ) -> Result<(), Unsatisfiable> {
+        let (Some(a), Some(b)) = (self.operand(a), self.operand(b)) else {
+            return Ok(());
+        };
+        let (a_bounds, b_bounds) = (self.operand_bounds(&a), self.operand_bounds(&b));
+        for (x, (lo, hi), (other_lo, other_hi)) in
+            [(&a, a_bounds, b_bounds), (&b, b_bounds, a_bounds)]
+        {
+            if other_lo != other_hi {
+                continue;
+            }
+            let value = other_lo;
+            match x {
+                Operand::Const(c) if *c == value => {
+                    return Err(Unsatisfiable::FalseConstraint(constraint.clone()));
+                }
+                Operand::Var(name) if lo == value => self.tighten(name, value + 1, hi)?,
+                Operand::Var(name) if hi == value => self.tighten(name, lo, value - 1)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn operand(&self, expr: &Expression) -> Option<Operand> {
+        match expr {
+            Expression::Reference(_, name) if self.bounds.contains_key(name) => {
+                Some(Operand::Var(name.clone()))
+            }
+            Expression::Constant(_, Constant::Int(i)) => Some(Operand::Const((*i).into())),
+            Expression::Constant(_, Constant::Bool(b)) => Some(Operand::Const((*b).into())),
+            _ => None,
+        }
+    }
+
+    fn operand_bounds(&self, operand: &Operand) -> (i64, i64) {
+        match operand {
+            Operand::Var(name) => self.bounds[name],
+            Operand::Const(c) => (*c, *c),
+        }
+    }
+
+    /// Intersect the bounds of `name` with `lo..=hi`, skipping over any holes in its domain.
+    fn tighten(&mut self, name: &Name, lo: i64, hi: i64) -> Result<(), Unsatisfiable> {
+        let (old_lo, old_hi) = self.bounds[name];
+        let (mut new_lo, mut new_hi) = (old_lo.max(lo), old_hi.min(hi));
+        if (new_lo, new_hi) == (old_lo, old_hi) {
+            return Ok(());
+        }
+        if let Some(domain) = self.domains.get_mut(name) {
+            // Within the old bounds, so within i32
+            let clamp = |x: i64| x.clamp(old_lo, old_hi) as i32;
+            *domain = domain.restrict_i32(clamp(new_lo), clamp(new_hi));
+            (new_lo, new_hi) = match domain.bounds_i32() {
+                Some((lo, hi)) if new_lo <= new_hi => (lo.into(), hi.into()),
+                _ => return Err(Unsatisfiable::EmptyDomain(name.clone())),
+            };
+        } else if new_lo > new_hi {
+            return Err(Unsatisfiable::EmptyDomain(name.clone()));
+        }
+        self.bounds.insert(name.clone(), (new_lo, new_hi));
+        self.changed = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::ast::{DecisionVariable, Range};
+    use crate::metadata::Metadata;
+    use crate::test_utils::{int, name, var};
+
+    use super::*;
+
+    fn model(vars: &[(&str, Domain)], constraints: Vec<Expression>) -> Model {
+        let mut model = Model::new_empty(Default::default());
+        for (var, domain) in vars {
+            model.add_variable(name(var), DecisionVariable::new(domain.clone()));
+        }
+        model.set_constraints(constraints);
+        model
+    }
+
+    fn domain(model: &Model, var: &str) -> Option<Domain> {
+        model.get_domain(&name(var)).cloned()
+    }
+
+    #[test]
+    fn sums_and_inequalities_narrow_bounds() {
+        // x + y <= 4, x >= y + 2 (as y <= x + -2), z = x
+        let mut model = model(
+            &[("x", int(0, 10)), ("y", int(1, 10)), ("z", int(-5, 5))],
+            vec![
+                Expression::SumLeq(
+                    Metadata::new(),
+                    Arc::new(vec![var("x"), var("y")]),
+                    Arc::new(4.into()),
+                ),
+                Expression::Ineq(
+                    Metadata::new(),
+                    Arc::new(var("y")),
+                    Arc::new(var("x")),
+                    Arc::new((-2).into()),
+                ),
+                Expression::Eq(Metadata::new(), Arc::new(var("z")), Arc::new(var("x"))),
+            ],
+        );
+        assert_eq!(propagate_bounds(&mut model), Ok(()));
+        let single = |i| Some(Domain::IntDomain(vec![Range::Single(i)]));
+        assert_eq!(domain(&model, "x"), single(3));
+        assert_eq!(domain(&model, "y"), single(1));
+        assert_eq!(domain(&model, "z"), single(3));
+    }
+
+    #[test]
+    fn booleans_and_disequalities_take_part() {
+        // p, sum([p, x]) >= 3, x != 4
+        let mut model = model(
+            &[("p", Domain::BoolDomain), ("x", int(0, 4))],
+            vec![
+                var("p"),
+                Expression::SumGeq(
+                    Metadata::new(),
+                    Arc::new(vec![var("p"), var("x")]),
+                    Arc::new(3.into()),
+                ),
+                Expression::Neq(Metadata::new(), Arc::new(var("x")), Arc::new(4.into())),
+            ],
+        );
+        assert_eq!(propagate_bounds(&mut model), Ok(()));
+        assert_eq!(domain(&model, "p"), Some(Domain::BoolDomain));
+        assert_eq!(domain(&model, "x"), Some(int(2, 3)));
+    }
+
+    #[test]
+    fn unsatisfiable_models_are_reported() {
+        let lt = Expression::Lt(Metadata::new(), Arc::new(var("x")), Arc::new(var("y")));
+        let gt = Expression::Gt(Metadata::new(), Arc::new(var("x")), Arc::new(var("y")));
+        let mut unsat = model(&[("x", int(0, 3)), ("y", int(0, 3))], vec![lt, gt]);
+        assert!(propagate_bounds(&mut unsat).is_err());
+        // The model is unchanged
+        assert_eq!(domain(&unsat, "x"), Some(int(0, 3)));
+
+        let not_p = Expression::Not(Metadata::new(), Arc::new(var("p")));
+        let mut unsat = model(&[("p", Domain::BoolDomain)], vec![var("p"), not_p]);
+        assert_eq!(
+            propagate_bounds(&mut unsat),
+            Err(Unsatisfiable::EmptyDomain(name("p")))
+        );
+    }
+
+    #[test]
+    fn collapsed_constraints_and_empty_domains_are_detected() {
+        let leq = Expression::Leq(Metadata::new(), Arc::new(3.into()), Arc::new(1.into()));
+        let collapsed = model(&[("x", Domain::BoolDomain)], vec![var("x"), leq.clone()]);
+        assert_eq!(
+            detect_unsatisfiable(&collapsed),
+            Some(Unsatisfiable::FalseConstraint(leq))
+        );
+
+        let empty = model(&[("x", Domain::IntDomain(vec![]))], vec![var("x")]);
+        assert_eq!(
+            detect_unsatisfiable(&empty),
+            Some(Unsatisfiable::EmptyDomain(name("x")))
+        );
+
+        let satisfiable = model(&[("x", Domain::BoolDomain)], vec![var("x")]);
+        assert_eq!(detect_unsatisfiable(&satisfiable), None);
+    }
+}
@@ -20,6 +20,10 @@ pub use linkme::distributed_slice;
 /// Functions must have the signature `fn(&Expr) -> ApplicationResult`.
 /// The created rule will have the same name as the function.
 ///
+/// A function with the signature `fn(&Model) -> Result<Model, ApplicationError>` is registered as a model
+/// rule instead (see [`Application::Model`]). Model rules see every constraint and variable at once, so can
+/// e.g. remove variables or tighten their domains.
+///
 /// Intermediary static variables are created to allow for the decentralized registry, with the prefix `CONJURE_GEN_`.
 /// Please ensure that other variable names in the same scope do not conflict with these.
 ///
@@ -27,6 +31,9 @@ pub use linkme::distributed_slice;
 /// As arguments, it excepts a tuple of 2-tuples in the format:
 /// `((<RuleSet name>, <Priority in RuleSet>), ...)`
 ///
+/// Rules that only match certain kinds of expression should also declare them with
+/// `applies_to(<Expression variant>, ...)`. The rewriter then only tries the rule on those variants.
+///
 /// <hr>
 ///
 /// For example:
@@ -40,6 +47,24 @@ pub use linkme::distributed_slice;
 /// fn identity(expr: &Expression, mdl: &Model) -> ApplicationResult {
 ///   Ok(Reduction::pure(expr.clone()))
 /// }
+///
+/// #[register_rule(("RuleSetName", 10), applies_to(Not))]
+/// fn remove_not(expr: &Expression, mdl: &Model) -> ApplicationResult {
+///   match expr {
+///     Expression::Not(_, e) => Ok(Reduction::pure(e.as_ref().clone())),
+///     _ => Err(ApplicationError::RuleNotApplicable),
+///   }
+/// }
+///
+/// #[register_rule(("RuleSetName", 10))]
+/// fn remove_all_variables(mdl: &Model) -> Result<Model, ApplicationError> {
+///   if mdl.variables.is_empty() {
+///     return Err(ApplicationError::RuleNotApplicable);
+///   }
+///   let mut new_model = mdl.clone();
+///   new_model.variables.clear();
+///   Ok(new_model)
+/// }
 /// ```
 pub use conjure_macros::register_rule;
 
@@ -63,17 +88,96 @@ pub use conjure_macros::register_rule;
 /// ```
 #[doc(inline)]
 pub use conjure_macros::register_rule_set;
-pub use resolve_rules::{get_rule_priorities, get_rules_vec, resolve_rule_sets};
-pub use rewrite::{rewrite_model, RewriteError};
-pub use rule::{ApplicationError, ApplicationResult, Reduction, Rule};
+
+/// This procedural macro declares rules as patterns over expressions, and registers them like
+/// [`register_rule`].
+///
+/// Each rule is a function-like item with a `#[register_rule(...)]` attribute and a list of arms:
+///
+/// ```text
+/// #[register_rule(<rule sets and priorities>)]
+/// fn <name> {
+///     <pattern> [if <guard>] => <template>,
+///     ...
+/// }
+/// ```
+///
+/// Arms are tried in order, and the first one whose pattern matches and whose guard holds gives the
+/// result. If none do, the rule is not applicable.
+///
+/// Patterns and templates use the same syntax:
+/// - `sum_leq(xs, y)` is an `Expression::SumLeq`; constructors are named in snake_case and
+///   take the variant's fields, without its metadata;
+/// - `a == b`, `a != b`, `a < b`, `a <= b`, `a > b`, `a >= b` and `!a` are shorthand for `eq`,
+///   `neq`, `lt`, `leq`, `gt`, `geq` and `not`;
+/// - integer and boolean literals are constants;
+/// - `[a, b, ..rest]` is a list; in a pattern, `..rest` binds the tail of the list and `..`
+///   ignores it;
+/// - a lowercase name binds (in a pattern) or reuses (in a template) a variable, and `_` matches
+///   anything.
+///
+/// A guard is any Rust expression over the bound variables and `model`, the [`Model`](crate::Model)
+/// being rewritten. In a template, a `{ ... }` block runs Rust code, e.g. to call a helper function,
+/// and its value is used as an expression or list.
+///
+/// Bound variables are references into the matched expression, so cloning them is cheap.
+///
+/// If `applies_to` is not given, the rule applies to the variants at the top of its patterns.
+///
+/// # Example
+/// ```rust
+/// use conjure_core::ast::Expression;
+/// use conjure_core::rule_engine::pattern_rule;
+///
+/// fn is_flat(exprs: &[Expression]) -> bool {
+///     !exprs.iter().any(|e| matches!(e, Expression::Sum(_, _)))
+/// }
+///
+/// pattern_rule! {
+///     /// `not(not(a)) = a`
+///     #[register_rule(("RuleSetName", 10))]
+///     fn remove_not_not {
+///         !!x => x,
+///     }
+///
+///     #[register_rule(("RuleSetName", 10))]
+///     fn sum_leq_to_sumleq {
+///         sum(xs) <= y if is_flat(xs) => sum_leq(xs, y),
+///     }
+///
+///     #[register_rule(("RuleSetName", 10))]
+///     fn drop_first_true {
+///         and([true, ..rest]) => and([..rest]),
+///     }
+/// }
+/// ```
+pub use conjure_macros::pattern_rule;
+pub use resolve_rules::{get_rule_priorities, get_rules_vec, resolve_rule_sets, ResolveRulesError};
+pub use rewrite::{rewrite_model, rewrite_model_all, RewriteError, RewritePolicy};
+pub use rewrite_steps::{
+    apply_all_rules, pretty_path, CandidateFinder, RewriteCandidate, RewriteScript, RewriteStep,
+};
+pub use rule::{
+    Application, ApplicationError, ApplicationResult, ModelRuleApplication, Reduction, Rule,
+    RuleApplication,
+};
+pub use rule_file::{load_rule_file, load_rules, RuleFileError};
+pub use rule_overrides::{matches_pattern, RuleOverrides};
 pub use rule_set::RuleSet;
+pub use rule_set_graph::{rule_set_graph, RuleSetGraph, RuleSetNode};
 
 use crate::solver::SolverFamily;
 
+#[doc(hidden)]
+pub mod pattern_rule;
 mod resolve_rules;
 mod rewrite;
+mod rewrite_steps;
 mod rule;
+mod rule_file;
+mod rule_overrides;
 mod rule_set;
+mod rule_set_graph;
 
 #[doc(hidden)]
 #[distributed_slice]
@@ -88,7 +192,7 @@ pub mod _dependencies {
     pub use linkme::distributed_slice;
 }
 
-/// Returns a copied `Vec` of all rules registered with the `register_rule` macro.
+/// Returns a copied `Vec` of all rules registered with the `register_rule` macro or loaded from a rule file.
 ///
 /// Rules are not guaranteed to be in any particular order.
 ///
@@ -115,7 +219,10 @@ pub mod _dependencies {
 /// ```
 /// Where `MEM` is the memory address of the `identity` function.
 pub fn get_rules() -> Vec<&'static Rule<'static>> {
-    RULES_DISTRIBUTED_SLICE.iter().collect()
+    RULES_DISTRIBUTED_SLICE
+        .iter()
+        .chain(rule_file::loaded_rules())
+        .collect()
 }
 
 /// Get a rule by name.
@@ -147,7 +254,7 @@ pub fn get_rule_by_name(name: &str) -> Option<&'static Rule<'static>> {
 }
 
 /// Get all rule sets
-/// Returns a `Vec` of static references to all rule sets registered with the `register_rule_set` macro.
+/// Returns a `Vec` of static references to all rule sets registered with the `register_rule_set` macro or loaded from a rule file.
 /// Rule sets are not guaranteed to be in any particular order.
 ///
 /// # Example
@@ -170,7 +277,10 @@ pub fn get_rule_by_name(name: &str) -> Option<&'static Rule<'static>> {
 /// ```
 ///
 pub fn get_rule_sets() -> Vec<&'static RuleSet<'static>> {
-    RULE_SETS_DISTRIBUTED_SLICE.iter().collect()
+    RULE_SETS_DISTRIBUTED_SLICE
+        .iter()
+        .chain(rule_file::loaded_rule_sets())
+        .collect()
 }
 
 /// Get a rule set by name.
@@ -0,0 +1,138 @@
+//! Conversions used by the code that `pattern_rule!` generates.
+//!
+//! Variables bound by a rule pattern are references to whatever the matched expression holds: a
+//! child (`&Arc<Expression>`), a list (`&Arc<Vec<Expression>>`), a list element (`&Expression`) or
+//! a list tail (`&[Expression]`). A template may put any of these wherever it fits, so each
+//! argument of a template constructor goes through [`into_arg`], which picks the conversion from
+//! the type of the field being filled.
+
+use std::sync::Arc;
+
+use crate::ast::Expression;
+use crate::metadata::Metadata;
+
+/// A value that can fill a field of type `T` in a rule template.
+pub trait TemplateArg<T> {
+    fn into_arg(self) -> T;
+}
+
+/// Converts a bound variable or sub-template into the field type expected by its constructor.
+pub fn into_arg<T, A: TemplateArg<T>>(arg: A) -> T {
+    arg.into_arg()
+}
+
+/// The metadata of the expression built by a template: that of the matched expression, marked dirty so
+/// the new expression is rewritten further.
+pub fn root_metadata(matched: &Expression) -> Metadata {
+    matched
+        .metadata()
+        .map(Metadata::clone_dirty)
+        .unwrap_or_default()
+}
+
+/// A value that can be spliced into a list template with `..tail`.
+pub trait TemplateList {
+    fn into_list(self) -> Vec<Expression>;
+}
+
+impl TemplateArg<Expression> for Expression {
+    fn into_arg(self) -> Expression {
+        self
+    }
+}
+
+impl TemplateArg<Expression> for &Expression {
+    fn into_arg(self) -> Expression {
+        self.clone()
+    }
+}
+
+impl TemplateArg<Expression> for Arc<Expression> {
+    fn into_arg(self) -> Expression {
+        Arc::unwrap_or_clone(self)
+    }
+}
+
+impl TemplateArg<Expression> for &Arc<Expression> {
+    fn into_arg(self) -> Expression {
+        self.as_ref().clone()
+    }
+}
+
+impl TemplateArg<Arc<Expression>> for Expression {
+    fn into_arg(self) -> Arc<Expression> {
+        Arc::new(self)
+    }
+}
+
+impl TemplateArg<Arc<Expression>> for &Expression {
+    fn into_arg(self) -> Arc<Expression> {
+        Arc::new(self.clone())
+    }
+}
+
+impl TemplateArg<Arc<Expression>> for Arc<Expression> {
+    fn into_arg(self) -> Arc<Expression> {
+        self
+    }
+}
+
+impl TemplateArg<Arc<Expression>> for &Arc<Expression> {
+    fn into_arg(self) -> Arc<Expression> {
+        Arc::clone(self)
+    }
+}
+
+impl TemplateArg<Arc<Vec<Expression>>> for Vec<Expression> {
+    fn into_arg(self) -> Arc<Vec<Expression>> {
+        Arc::new(self)
+    }
+}
+
+impl TemplateArg<Arc<Vec<Expression>>> for &Vec<Expression> {
+    fn into_arg(self) -> Arc<Vec<Expression>> {
+        Arc::new(self.clone())
+    }
+}
+
+impl TemplateArg<Arc<Vec<Expression>>> for &[Expression] {
+    fn into_arg(self) -> Arc<Vec<Expression>> {
+        Arc::new(self.to_vec())
+    }
+}
+
+impl TemplateArg<Arc<Vec<Expression>>> for Arc<Vec<Expression>> {
+    fn into_arg(self) -> Arc<Vec<Expression>> {
+        self
+    }
+}
+
+impl TemplateArg<Arc<Vec<Expression>>> for &Arc<Vec<Expression>> {
+    fn into_arg(self) -> Arc<Vec<Expression>> {
+        Arc::clone(self)
+    }
+}
+
+impl TemplateList for Vec<Expression> {
+    fn into_list(self) -> Vec<Expression> {
+        self
+    }
+}
+
+impl TemplateList for &Vec<Expression> {
+    fn into_list(self) -> Vec<Expression> {
+        self.clone()
+    }
+}
+
+impl TemplateList for &[Expression] {
+    fn into_list(self) -> Vec<Expression> {
+        self.to_vec()
+    }
+}
+
+impl TemplateList for &Arc<Vec<Expression>> {
+    fn into_list(self) -> Vec<Expression> {
+        self.as_ref().clone()
+    }
+}
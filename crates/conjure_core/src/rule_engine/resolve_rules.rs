@@ -6,15 +6,41 @@ use thiserror::Error;
 use crate::rule_engine::{get_rule_set_by_name, get_rule_sets_for_solver_family, Rule, RuleSet};
 use crate::solver::SolverFamily;
 
-#[derive(Debug, Error)]
+#[derive(Clone, Debug, Error)]
 pub enum ResolveRulesError {
     RuleSetNotFound,
+    /// A rule set depends on a rule set that is not registered.
+    DependencyNotFound {
+        rule_set: String,
+        dependency: String,
+    },
+    /// Rule sets depend on each other in a cycle. The first and last names are the same.
+    DependencyCycle(Vec<String>),
+    /// A rule named in the rule overrides is not registered.
+    RuleNotFound(String),
+    /// A pattern in the rule overrides does not match any rule.
+    NoRulesMatch(String),
 }
 
 impl Display for ResolveRulesError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ResolveRulesError::RuleSetNotFound => write!(f, "Rule set not found."),
+            ResolveRulesError::DependencyNotFound {
+                rule_set,
+                dependency,
+            } => write!(
+                f,
+                "Rule set {} depends on non-existent rule set {}.",
+                rule_set, dependency
+            ),
+            ResolveRulesError::DependencyCycle(cycle) => {
+                write!(f, "Rule set dependency cycle: {}.", cycle.join(" -> "))
+            }
+            ResolveRulesError::RuleNotFound(name) => write!(f, "Rule {} not found.", name),
+            ResolveRulesError::NoRulesMatch(pattern) => {
+                write!(f, "No rules match the pattern {}.", pattern)
+            }
         }
     }
 }
@@ -49,7 +75,7 @@ pub fn rule_sets_by_names<'a>(
 
     for rule_set_name in rule_set_names {
         let rule_set = get_rule_set(rule_set_name)?;
-        let new_dependencies = rule_set.get_dependencies();
+        let new_dependencies = rule_set.get_dependencies()?;
         rs_set.insert(rule_set);
         rs_set.extend(new_dependencies);
     }
@@ -74,7 +100,7 @@ pub fn resolve_rule_sets<'a>(
     let mut ans = HashSet::new();
 
     for rs in get_rule_sets_for_solver_family(target_solver) {
-        ans.extend(rs.with_dependencies());
+        ans.extend(rs.with_dependencies()?);
     }
 
     ans.extend(rule_sets_by_names(extra_rs_names)?);
@@ -1,12 +1,22 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::env;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
 
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::Serialize;
+use strum::VariantNames;
 use thiserror::Error;
 
-use crate::stats::RewriterStats;
+use crate::stats::{RewriterStats, RuleStats};
 use uniplate::uniplate::Uniplate;
 
-use crate::rule_engine::{Reduction, Rule, RuleSet};
+use crate::rule_engine::{
+    pretty_path, ApplicationResult, Reduction, RewriteCandidate, RewriteStep, Rule, RuleSet,
+};
 use crate::{
     ast::Expression,
     rule_engine::resolve_rules::{
@@ -15,21 +25,25 @@ use crate::{
     Model,
 };
 
-#[derive(Debug)]
-struct RuleResult<'a> {
-    rule: &'a Rule<'a>,
-    reduction: Reduction,
-}
-
 #[derive(Debug, Error)]
 pub enum RewriteError {
     ResolveRulesError(ResolveError),
+    /// The numbered step of a [`RewriteScript`](crate::rule_engine::RewriteScript) could not be applied.
+    StepNotApplicable(usize, RewriteStep),
+    /// A rule application was found at a path that does not lead to a sub-expression of the model.
+    PathNotFound(Vec<usize>),
 }
 
 impl Display for RewriteError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RewriteError::ResolveRulesError(e) => write!(f, "Error resolving rules: {}", e),
+            RewriteError::StepNotApplicable(i, step) => {
+                write!(f, "Step {} of the script cannot be applied: {}", i, step)
+            }
+            RewriteError::PathNotFound(path) => {
+                write!(f, "No sub-expression at {} in the model", pretty_path(path))
+            }
         }
     }
 }
@@ -40,21 +54,190 @@ impl From<ResolveError> for RewriteError {
     }
 }
 
-/// Checks if the OPTIMIZATIONS environment variable is set to "1".
+/// Checks if the OPTIMIZATIONS environment variable is set to "0".
 ///
 /// # Returns
-/// - true if the environment variable is set to "1".
-/// - false if the environment variable is not set or set to any other value.
-fn optimizations_enabled() -> bool {
-    match env::var("OPTIMIZATIONS") {
-        Ok(val) => val == "1",
-        Err(_) => false, // Assume optimizations are disabled if the environment variable is not set
+/// - false if the environment variable is set to "0".
+/// - true if the environment variable is not set or set to any other value.
+pub(super) fn optimizations_enabled() -> bool {
+    !env::var("OPTIMIZATIONS").is_ok_and(|val| val == "0")
+}
+
+/// Sub-expressions that no rule applies to, either to the expression itself or to anything below it.
+///
+/// The rewriter restarts from the root after every rule application, so most of the tree is unchanged between iterations.
+/// Expressions are looked up by their structural hash and compared with [`Expression::structural_eq`], both of which
+/// ignore metadata, so a known subtree is skipped wherever it ends up.
+///
+/// The cache also keeps the hashes of the model's constraints, which are updated as rules are applied.
+#[derive(Default)]
+pub(super) struct IrreducibleCache {
+    by_hash: HashMap<u64, Vec<Expression>>,
+    hashes: Option<HashedExpr>,
+}
+
+impl IrreducibleCache {
+    pub(super) fn contains(&self, hashed: &HashedExpr, expr: &Expression) -> bool {
+        self.by_hash
+            .get(&hashed.hash)
+            .is_some_and(|exprs| exprs.iter().any(|e| e.structural_eq(expr)))
+    }
+
+    pub(super) fn insert(&mut self, hashed: &HashedExpr, expr: &Expression) {
+        self.by_hash
+            .entry(hashed.hash)
+            .or_default()
+            .push(expr.clone());
+    }
+
+    /// Update the cache after a rule application at `path` of the constraints, which are now `constraints`.
+    pub(super) fn applied(
+        &mut self,
+        changed_symbols: bool,
+        constraints: &Expression,
+        path: &[usize],
+    ) {
+        if changed_symbols {
+            // Rules may depend on the domains of the variables in an expression
+            self.by_hash.clear();
+        }
+        if let Some(hashes) = self.hashes.as_mut() {
+            hashes.update(constraints, path);
+        }
+    }
+
+    /// Forget everything, e.g. when moving on to another model.
+    pub(super) fn clear(&mut self) {
+        self.by_hash.clear();
+        self.hashes = None;
+    }
+}
+
+/// The structural hashes of an expression and of each of its sub-expressions, in the same shape as the expression.
+///
+/// These are worked out bottom-up once, and then kept between passes with [`HashedExpr::update`], so looking an
+/// expression up in the [`IrreducibleCache`] does not rehash the subtree below it.
+pub(super) struct HashedExpr {
+    hash: u64,
+    variant: &'static str,
+    pub(super) children: Vec<HashedExpr>,
+}
+
+impl HashedExpr {
+    pub(super) fn new(expr: &Expression) -> Self {
+        let children: Vec<HashedExpr> = expr.children().iter().map(HashedExpr::new).collect();
+        Self {
+            hash: Self::combine(expr, &children),
+            variant: expr.variant_name(),
+            children,
+        }
+    }
+
+    /// Update the hashes of an expression after the sub-expression at `path` was rewritten.
+    ///
+    /// Only the hashes along the path are worked out again, along with those of any children added after the
+    /// existing ones, such as new top-level constraints.
+    pub(super) fn update(&mut self, expr: &Expression, path: &[usize]) {
+        let children = expr.children();
+        match path.split_first() {
+            Some((&i, rest))
+                if self.variant == expr.variant_name()
+                    && i < self.children.len()
+                    && self.children.len() <= children.len() =>
+            {
+                self.children[i].update(&children[i], rest);
+                let known = self.children.len();
+                self.children
+                    .extend(children[known..].iter().map(HashedExpr::new));
+                self.hash = Self::combine(expr, &self.children);
+            }
+            _ => *self = HashedExpr::new(expr),
+        }
+    }
+
+    fn combine(expr: &Expression, children: &[HashedExpr]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        if children.is_empty() {
+            // Metadata is not hashed, so this only hashes the kind of expression and any constant or name
+            expr.hash(&mut hasher);
+        } else {
+            // Only constants and references hold anything other than metadata and children
+            expr.variant_name().hash(&mut hasher);
+            for child in children {
+                child.hash.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// The candidate rules for each kind of expression, in the order they should be tried.
+///
+/// Rules which declare the `Expression` variants they apply to are only listed under those variants;
+/// all other expression rules are listed under every variant. Model rules are kept apart.
+pub(super) struct RuleIndex<'a> {
+    by_variant: HashMap<&'static str, Vec<&'a Rule<'a>>>,
+    pub(super) model_rules: Vec<&'a Rule<'a>>,
+}
+
+impl<'a> RuleIndex<'a> {
+    pub(super) fn new(rules: &[&'a Rule<'a>]) -> Self {
+        let by_variant = Expression::VARIANTS
+            .iter()
+            .map(|variant| {
+                let candidates = rules
+                    .iter()
+                    .filter(|rule| match rule.applicable_variants {
+                        _ if rule.is_model_rule() => false,
+                        None => true,
+                        Some(variants) => variants.contains(variant),
+                    })
+                    .copied()
+                    .collect();
+                (*variant, candidates)
+            })
+            .collect();
+        let model_rules = rules
+            .iter()
+            .filter(|rule| rule.is_model_rule())
+            .copied()
+            .collect();
+        Self {
+            by_variant,
+            model_rules,
+        }
+    }
+
+    pub(super) fn candidates(&self, expr: &Expression) -> &[&'a Rule<'a>] {
+        self.by_variant
+            .get(expr.variant_name())
+            .map_or(&[], |rules| rules.as_slice())
     }
 }
 
+/// How the rewriter picks between the reductions offered by a rule with several alternatives
+/// (see [`Reduction::choice`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, JsonSchema, ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum RewritePolicy {
+    /// Take the first alternative.
+    #[default]
+    First,
+    /// Take the alternative with the lowest [`Reduction::cost`], or the first of the cheapest.
+    Cheapest,
+    /// Rewrite the model once for each combination of alternatives, with [`rewrite_model_all`].
+    /// [`rewrite_model`] takes the first alternative.
+    All,
+}
+
 /// Rewrites the model by applying the rules to all constraints.
 ///
 /// Any side-effects such as symbol table updates and top-level constraints are applied to the returned model.
+/// Model rules are only tried once no expression rule applies anywhere in the model.
+///
+/// The rules come from the given rule sets, adjusted by the [`RuleOverrides`](crate::rule_engine::RuleOverrides)
+/// in the model's context. When a rule offers several alternatives, one is picked according to the
+/// [`RewritePolicy`] in the model's context.
 ///
 /// # Returns
 /// A copy of the model after all, if any, possible rules are applied to its constraints.
@@ -62,121 +245,396 @@ pub fn rewrite_model<'a>(
     model: &Model,
     rule_sets: &Vec<&'a RuleSet<'a>>,
 ) -> Result<Model, RewriteError> {
-    let rule_priorities = get_rule_priorities(rule_sets)?;
-    let rules = get_rules_vec(&rule_priorities);
+    let mut rewriter = Rewriter::new(model, rule_sets)?;
     let mut new_model = model.clone();
-    let mut stats = RewriterStats {
-        is_optimization_enabled: Some(optimizations_enabled()),
-        rewriter_run_time: None,
-        rewriter_rule_application_attempts: Some(0),
-        rewriter_rule_applications: Some(0),
-    };
 
-    // Check if optimizations are enabled
-    let apply_optimizations = optimizations_enabled();
-
-    let start = std::time::Instant::now();
-
-    while let Some(step) = rewrite_iteration(
-        &new_model.constraints,
-        &new_model,
-        &rules,
-        apply_optimizations,
-        &mut stats,
-    ) {
-        step.apply(&mut new_model); // Apply side-effects (e.g. symbol table updates)
+    while let Some(step) = rewriter.step(&new_model) {
+        let reduction = rewriter.choose(step);
+        rewriter.apply(reduction, &mut new_model); // Apply side-effects (e.g. symbol table updates)
     }
-    stats.rewriter_run_time = Some(start.elapsed());
-    model.context.write().unwrap().stats.add_rewriter_run(stats);
+
+    rewriter.finish(model, rule_sets);
     Ok(new_model)
 }
 
+/// Rewrites the model once for each combination of the alternatives offered by the rules, like
+/// a portfolio of models in Conjure.
+///
+/// Unless the [`RewritePolicy`] in the model's context is `All`, this returns the single model
+/// that [`rewrite_model`] would.
+///
+/// The number of models grows exponentially with the number of choices made, so at most
+/// `max_models` are made if it is given.
+///
 /// # Returns
-/// - Some(<new_expression>) after applying the first applicable rule to `expr` or a sub-expression.
-/// - None if no rule is applicable to the expression or any sub-expression.
-fn rewrite_iteration<'a>(
-    expression: &'a Expression,
-    model: &'a Model,
-    rules: &'a Vec<&'a Rule<'a>>,
-    apply_optimizations: bool,
-    stats: &mut RewriterStats,
-) -> Option<Reduction> {
-    if apply_optimizations && expression.is_clean() {
-        // Skip processing this expression if it's clean
-        return None;
+/// The rewritten models, starting with the one made by always taking the first alternative.
+pub fn rewrite_model_all<'a>(
+    model: &Model,
+    rule_sets: &Vec<&'a RuleSet<'a>>,
+    max_models: Option<usize>,
+) -> Result<Vec<Model>, RewriteError> {
+    let mut rewriter = Rewriter::new(model, rule_sets)?;
+    if rewriter.policy != RewritePolicy::All {
+        return Ok(vec![rewrite_model(model, rule_sets)?]);
     }
 
-    // Mark the expression as clean - will be marked dirty if any rule is applied
-    let mut expression = expression.clone();
+    let mut models = Vec::new();
+    // Models part way through rewriting, after taking an alternative other than the first
+    let mut pending = vec![model.clone()];
+    while let Some(mut current) = pending.pop() {
+        if !models.is_empty() {
+            // What is irreducible depends on the variables, which may differ between branches
+            rewriter.clear_cache();
+        }
+        while let Some(step) = rewriter.step(&current) {
+            let mut alternatives = step.into_alternatives().into_iter();
+            let Some(first) = alternatives.next() else {
+                break;
+            };
+            // Pushed in reverse, so the alternatives are explored in order
+            for alternative in alternatives.rev() {
+                let mut branch = current.clone();
+                alternative.apply(&mut branch);
+                pending.push(branch);
+            }
+            rewriter.apply(first, &mut current);
+        }
+        models.push(current);
+        if max_models.is_some_and(|max| models.len() >= max) {
+            break;
+        }
+    }
 
-    let rule_results = apply_all_rules(&expression, model, rules, stats);
-    if let Some(new) = choose_rewrite(&rule_results) {
-        // If a rule is applied, mark the expression as dirty
-        return Some(new);
+    rewriter.finish(model, rule_sets);
+    Ok(models)
+}
+
+/// The state of a rewriter run, shared between passes (and, for `RewritePolicy::All`, between models).
+struct Rewriter<'a> {
+    rule_index: RuleIndex<'a>,
+    policy: RewritePolicy,
+    cache: Option<IrreducibleCache>,
+    /// The path to the sub-expression rewritten by the reduction last returned by [`Rewriter::step`].
+    path: Vec<usize>,
+    stats: RewriterStats,
+    start: Instant,
+}
+
+impl<'a> Rewriter<'a> {
+    fn new(model: &Model, rule_sets: &Vec<&'a RuleSet<'a>>) -> Result<Self, RewriteError> {
+        #[allow(clippy::unwrap_used)]
+        let (rule_overrides, policy) = {
+            let context = model.context.read().unwrap();
+            (context.rule_overrides.clone(), context.rewrite_policy)
+        };
+        let rule_priorities = rule_overrides.apply(get_rule_priorities(rule_sets)?)?;
+        let rules = get_rules_vec(&rule_priorities);
+
+        Ok(Self {
+            rule_index: RuleIndex::new(&rules),
+            policy,
+            // Only memoise rule applications if optimizations are enabled
+            cache: optimizations_enabled().then(IrreducibleCache::default),
+            path: Vec::new(),
+            stats: RewriterStats {
+                is_optimization_enabled: Some(optimizations_enabled()),
+                rewriter_run_time: None,
+                rewriter_rule_application_attempts: Some(0),
+                rewriter_rule_applications: Some(0),
+                rewriter_passes: Some(0),
+                ..Default::default()
+            },
+            start: Instant::now(),
+        })
     }
 
-    let mut sub = expression.children();
-    for i in 0..sub.len() {
-        if let Some(red) = rewrite_iteration(&sub[i], model, rules, apply_optimizations, stats) {
-            sub[i] = red.new_expression;
-            if let Ok(res) = expression.with_children(sub.clone()) {
-                return Some(Reduction::new(res, red.new_top, red.symbols));
-            }
+    /// One pass over the model, returning the first reduction found (with any alternatives).
+    ///
+    /// Model rules are only tried if no expression rule applies.
+    fn step(&mut self, model: &Model) -> Option<Reduction> {
+        self.stats.rewriter_passes = self.stats.rewriter_passes.map(|passes| passes + 1);
+        let candidates = find_candidates(
+            model,
+            &self.rule_index,
+            self.cache.as_mut(),
+            true,
+            &mut self.stats,
+        );
+        self.path = candidates.first()?.path.clone();
+        // The alternatives of a rule, each applied to the whole of the constraints
+        let reductions = candidates
+            .into_iter()
+            .filter_map(|candidate| candidate.into_reduction(&model.constraints).ok());
+        Reduction::choice(reductions).ok()
+    }
+
+    /// Apply a reduction chosen by [`Rewriter::choose`] to the model.
+    fn apply(&mut self, reduction: Reduction, model: &mut Model) {
+        let changed_symbols = reduction.changes_existing_symbols();
+        reduction.apply(model);
+        if let Some(cache) = self.cache.as_mut() {
+            cache.applied(changed_symbols, &model.constraints, &self.path);
+        }
+    }
+
+    fn clear_cache(&mut self) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
         }
     }
-    // If all children are clean, mark this expression as clean
-    if apply_optimizations {
-        assert!(expression.children().iter().all(|c| c.is_clean()));
-        expression.set_clean(true);
-        return Some(Reduction::pure(expression));
+
+    /// Pick one of the alternatives of a reduction according to the policy.
+    fn choose(&self, reduction: Reduction) -> Reduction {
+        let mut alternatives = reduction.into_alternatives().into_iter();
+        let chosen = match self.policy {
+            RewritePolicy::First | RewritePolicy::All => alternatives.next(),
+            RewritePolicy::Cheapest => alternatives.min_by_key(Reduction::cost),
+        };
+        // `into_alternatives` always returns at least the reduction itself
+        #[allow(clippy::unwrap_used)]
+        chosen.unwrap()
+    }
+
+    /// Record the statistics of the run in the model's context.
+    fn finish(mut self, model: &Model, rule_sets: &Vec<&'a RuleSet<'a>>) {
+        self.stats.rewriter_run_time = Some(self.start.elapsed());
+
+        for rule_set in rule_sets {
+            let mut total = RuleStats::default();
+            for rule in rule_set.get_rules().keys() {
+                if let Some(rule_stats) = self.stats.rule_stats.get(rule.name) {
+                    total.add(*rule_stats);
+                }
+            }
+            self.stats
+                .rule_set_stats
+                .insert(rule_set.name.to_string(), total);
+        }
+
+        #[allow(clippy::unwrap_used)]
+        model
+            .context
+            .write()
+            .unwrap()
+            .stats
+            .add_rewriter_run(self.stats);
     }
-    None
 }
 
+/// Finds rule applications in the model's constraints, for both the rewriter and
+/// [`CandidateFinder`](crate::rule_engine::CandidateFinder).
+///
+/// Sub-expressions are visited in pre-order, trying the candidate rules for each in order, and then model rules are
+/// tried at the root. Subtrees in the cache are skipped, and subtrees where no rule applies are added to it.
+///
 /// # Returns
-/// - A list of RuleResults after applying all rules to `expression`.
-/// - An empty list if no rules are applicable.
-fn apply_all_rules<'a>(
-    expression: &'a Expression,
-    model: &'a Model,
-    rules: &'a Vec<&'a Rule<'a>>,
+/// - Every rule application, with each alternative offered by a rule as a separate candidate.
+/// - If `first_only` is set, only the alternatives of the first rule that applies. Model rules are then only tried
+///   if no expression rule applies.
+pub(super) fn find_candidates<'a>(
+    model: &Model,
+    rules: &RuleIndex<'a>,
+    mut cache: Option<&mut IrreducibleCache>,
+    first_only: bool,
     stats: &mut RewriterStats,
-) -> Vec<RuleResult<'a>> {
-    let mut results = Vec::new();
-    for rule in rules {
-        match rule.apply(expression, model) {
-            Ok(red) => {
-                log::trace!(target: "file", "Rule applicable: {:?}, to Expression: {:?}, resulting in: {:?}", rule, expression, red.new_expression);
-                stats.rewriter_rule_application_attempts =
-                    Some(stats.rewriter_rule_application_attempts.unwrap() + 1);
-                stats.rewriter_rule_applications =
-                    Some(stats.rewriter_rule_applications.unwrap() + 1);
-                // Assert no clean children
-                // assert!(!red.new_expression.children().iter().any(|c| c.is_clean()), "Rule that caused assertion to fail: {:?}", rule.name);
-                // assert!(!red.new_expression.children().iter().any(|c| c.children().iter().any(|c| c.is_clean())));
-                results.push(RuleResult {
-                    rule,
-                    reduction: red,
-                });
+) -> Vec<RewriteCandidate<'a>> {
+    // Taken out of the cache while it is updated, and put back afterwards
+    let hashes = cache.as_deref_mut().map(|cache| {
+        cache
+            .hashes
+            .take()
+            .unwrap_or_else(|| HashedExpr::new(&model.constraints))
+    });
+    let mut search = Search {
+        model,
+        rules,
+        cache: cache.as_deref_mut(),
+        first_only,
+        stats,
+        candidates: Vec::new(),
+    };
+    search.visit(&model.constraints, hashes.as_ref(), &mut Vec::new());
+
+    if !first_only || search.candidates.is_empty() {
+        for rule in &rules.model_rules {
+            let Some(reduction) = search.attempt(rule, |rule| rule.apply_to_model(model)) else {
+                continue;
+            };
+            log::trace!(target: "file", "Model rule applicable: {:?}, resulting in: {:?}", rule, reduction.new_expression);
+            search.push(rule, &[], &model.constraints, reduction);
+            if first_only {
+                break;
             }
-            Err(_) => {
+        }
+    }
+
+    let candidates = search.candidates;
+    if let Some(cache) = cache {
+        cache.hashes = hashes;
+    }
+    candidates
+}
+
+/// The state of [`find_candidates`] as it walks the constraints.
+struct Search<'s, 'a> {
+    model: &'s Model,
+    rules: &'s RuleIndex<'a>,
+    cache: Option<&'s mut IrreducibleCache>,
+    first_only: bool,
+    stats: &'s mut RewriterStats,
+    candidates: Vec<RewriteCandidate<'a>>,
+}
+
+impl<'a> Search<'_, 'a> {
+    fn visit(
+        &mut self,
+        expression: &Expression,
+        hashed: Option<&HashedExpr>,
+        path: &mut Vec<usize>,
+    ) {
+        if let (Some(cache), Some(hashed)) = (self.cache.as_deref(), hashed) {
+            if cache.contains(hashed, expression) {
+                // No rule applied to this subtree last time we saw it, so none will now
+                return;
+            }
+        }
+        let found = self.candidates.len();
+
+        for rule in self.rules.candidates(expression) {
+            let Some(reduction) = self.attempt(rule, |rule| rule.apply(expression, self.model))
+            else {
                 log::trace!(target: "file", "Rule attempted but not applied: {:?}, to Expression: {:?}", rule, expression);
-                stats.rewriter_rule_application_attempts =
-                    Some(stats.rewriter_rule_application_attempts.unwrap() + 1);
                 continue;
+            };
+            log::trace!(target: "file", "Rule applicable: {:?}, to Expression: {:?}, resulting in: {:?}", rule, expression, reduction.new_expression);
+            self.push(rule, path, expression, reduction);
+            if self.first_only {
+                return;
+            }
+        }
+
+        for (i, child) in expression.children().iter().enumerate() {
+            path.push(i);
+            let hashed_child = hashed.map(|hashed| &hashed.children[i]);
+            self.visit(child, hashed_child, path);
+            path.pop();
+            if self.first_only && self.candidates.len() > found {
+                return;
+            }
+        }
+
+        if self.candidates.len() == found {
+            if let (Some(cache), Some(hashed)) = (self.cache.as_deref_mut(), hashed) {
+                cache.insert(hashed, expression);
             }
         }
     }
-    results
+
+    /// Apply a rule, recording the attempt in the statistics.
+    fn attempt(
+        &mut self,
+        rule: &'a Rule<'a>,
+        apply: impl FnOnce(&'a Rule<'a>) -> ApplicationResult,
+    ) -> Option<Reduction> {
+        let start = Instant::now();
+        let result = apply(rule);
+        self.stats
+            .record_rule_attempt(rule.name, result.is_ok(), start.elapsed());
+        self.stats.rewriter_rule_application_attempts =
+            self.stats.rewriter_rule_application_attempts.map(|n| n + 1);
+        if result.is_ok() {
+            self.stats.rewriter_rule_applications =
+                self.stats.rewriter_rule_applications.map(|n| n + 1);
+        }
+        result.ok()
+    }
+
+    /// Add a candidate for each alternative of a reduction of the sub-expression at `path`.
+    fn push(
+        &mut self,
+        rule: &'a Rule<'a>,
+        path: &[usize],
+        expression: &Expression,
+        reduction: Reduction,
+    ) {
+        for (alternative, reduction) in reduction.into_alternatives().into_iter().enumerate() {
+            self.candidates.push(RewriteCandidate {
+                rule,
+                path: path.to_vec(),
+                alternative,
+                expression: expression.clone(),
+                reduction,
+            });
+        }
+    }
 }
 
-/// # Returns
-/// - Some(<reduction>) after applying the first rule in `results`.
-/// - None if `results` is empty.
-fn choose_rewrite(results: &[RuleResult]) -> Option<Reduction> {
-    if results.is_empty() {
-        return None;
-    }
-    // Return the first result for now
-    Some(results[0].reduction.clone())
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::ast::Name;
+    use crate::metadata::Metadata;
+
+    use super::*;
+
+    fn sum_of_refs() -> Expression {
+        let reference = Expression::Reference(Metadata::new(), Name::MachineName(0));
+        Expression::Sum(Metadata::new(), vec![reference.clone(), reference].into())
+    }
+
+    #[test]
+    fn cache_finds_expressions_with_different_metadata() {
+        let expr = sum_of_refs();
+        let mut cache = IrreducibleCache::default();
+        cache.insert(&HashedExpr::new(&expr), &expr);
+
+        let mut clean = expr.clone();
+        clean.set_clean(true);
+        assert!(cache.contains(&HashedExpr::new(&clean), &clean));
+
+        let other = Expression::Not(Metadata::new(), Arc::new(expr));
+        assert!(!cache.contains(&HashedExpr::new(&other), &other));
+    }
+
+    #[test]
+    fn hashes_are_kept_for_every_sub_expression() {
+        let expr = Expression::Not(Metadata::new(), Arc::new(sum_of_refs()));
+        let hashed = HashedExpr::new(&expr);
+        assert_eq!(hashed.children.len(), 1);
+        assert_eq!(
+            hashed.children[0].hash,
+            HashedExpr::new(&sum_of_refs()).hash
+        );
+        assert_eq!(hashed.children[0].children.len(), 2);
+        assert_eq!(
+            hashed.children[0].children[0].hash,
+            hashed.children[0].children[1].hash
+        );
+    }
+
+    #[test]
+    fn updated_hashes_match_hashing_again() {
+        let expr = Expression::Not(Metadata::new(), Arc::new(sum_of_refs()));
+        let mut hashed = HashedExpr::new(&expr);
+
+        // Rewrite the second operand of the sum
+        let sum = Expression::Sum(
+            Metadata::new(),
+            vec![sum_of_refs().children()[0].clone(), Expression::from(1)].into(),
+        );
+        let rewritten = Expression::Not(Metadata::new(), Arc::new(sum));
+        hashed.update(&rewritten, &[0, 1]);
+        assert_eq!(hashed.hash, HashedExpr::new(&rewritten).hash);
+        assert_ne!(hashed.hash, HashedExpr::new(&expr).hash);
+
+        // Add a new top-level constraint, turning the root into a conjunction
+        let with_top = Expression::And(
+            Metadata::new(),
+            vec![rewritten.clone(), Expression::from(true)].into(),
+        );
+        hashed.update(&with_top, &[0]);
+        assert_eq!(hashed.hash, HashedExpr::new(&with_top).hash);
+        assert_eq!(hashed.children.len(), 2);
+    }
 }
@@ -0,0 +1,210 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use uniplate::uniplate::Uniplate;
+
+use crate::ast::Expression;
+use crate::rule_engine::rewrite::{
+    find_candidates, optimizations_enabled, IrreducibleCache, RuleIndex,
+};
+use crate::rule_engine::{Reduction, RewriteError, Rule};
+use crate::stats::RewriterStats;
+use crate::Model;
+
+/// A single rule application that could be made to a model, found by [`apply_all_rules`].
+#[derive(Clone, Debug)]
+pub struct RewriteCandidate<'a> {
+    pub rule: &'a Rule<'a>,
+    /// The indices of the children to follow from the model's constraints to the rewritten sub-expression.
+    pub path: Vec<usize>,
+    /// Which of the alternatives offered by the rule this is (0 unless the rule offers several).
+    pub alternative: usize,
+    /// The sub-expression the rule applies to.
+    pub expression: Expression,
+    /// The reduction of the sub-expression.
+    pub reduction: Reduction,
+}
+
+impl<'a> RewriteCandidate<'a> {
+    /// The step to record in a [`RewriteScript`] to make this rule application again.
+    pub fn step(&self) -> RewriteStep {
+        RewriteStep {
+            rule: self.rule.name.to_string(),
+            path: self.path.clone(),
+            alternative: self.alternative,
+        }
+    }
+
+    /// Apply the rule application to the model, including any side-effects.
+    ///
+    /// # Returns
+    /// - `RewriteError::PathNotFound` if the path does not lead to a sub-expression of the model, e.g. because
+    ///   the candidate was found for a different model. The model is left unchanged.
+    pub fn apply(self, model: &mut Model) -> Result<(), RewriteError> {
+        self.into_reduction(&model.constraints)?.apply(model);
+        Ok(())
+    }
+
+    /// The reduction of the whole of `constraints` made by this rule application.
+    pub(super) fn into_reduction(
+        self,
+        constraints: &Expression,
+    ) -> Result<Reduction, RewriteError> {
+        let mut reduction = self.reduction;
+        let new_expression = std::mem::replace(&mut reduction.new_expression, Expression::Nothing);
+        reduction.new_expression = replace_at(constraints, &self.path, new_expression)
+            .ok_or(RewriteError::PathNotFound(self.path))?;
+        Ok(reduction)
+    }
+}
+
+/// Finds every way of applying one of `rules` to the model's constraints or one of their sub-expressions.
+///
+/// To find rule applications for a model again and again as it is rewritten, keep a [`CandidateFinder`].
+///
+/// # Returns
+/// The candidates, in the order the rewriter would consider them: sub-expressions in pre-order, then rules in
+/// the order given, then alternatives in the order offered, and finally model rules, at the root. The first
+/// candidate, if any, is the rule application [`rewrite_model`](crate::rule_engine::rewrite_model) makes next
+/// with the `First` policy.
+pub fn apply_all_rules<'a>(model: &Model, rules: &[&'a Rule<'a>]) -> Vec<RewriteCandidate<'a>> {
+    CandidateFinder::new(rules).all(model)
+}
+
+/// Finds rule applications in a model as it is rewritten one step at a time.
+///
+/// Like the rewriter, this only tries the rules for each kind of expression and, unless the `OPTIMIZATIONS`
+/// environment variable is set to "0", skips sub-expressions that no rule applied to last time.
+/// Make rule applications with [`CandidateFinder::apply`], and call [`CandidateFinder::clear_cache`] if the
+/// model changes in any other way.
+pub struct CandidateFinder<'a> {
+    rules: RuleIndex<'a>,
+    cache: Option<IrreducibleCache>,
+}
+
+impl<'a> CandidateFinder<'a> {
+    pub fn new(rules: &[&'a Rule<'a>]) -> Self {
+        Self {
+            rules: RuleIndex::new(rules),
+            cache: optimizations_enabled().then(IrreducibleCache::default),
+        }
+    }
+
+    /// Every rule application that could be made to the model, in the order given by [`apply_all_rules`].
+    pub fn all(&mut self, model: &Model) -> Vec<RewriteCandidate<'a>> {
+        self.find(model, false)
+    }
+
+    /// The rule application that [`rewrite_model`](crate::rule_engine::rewrite_model) makes next with the
+    /// `First` policy, found without looking for the others.
+    pub fn first(&mut self, model: &Model) -> Option<RewriteCandidate<'a>> {
+        self.find(model, true).into_iter().next()
+    }
+
+    /// Apply a rule application found for the model. See [`RewriteCandidate::apply`].
+    pub fn apply(
+        &mut self,
+        candidate: RewriteCandidate<'a>,
+        model: &mut Model,
+    ) -> Result<(), RewriteError> {
+        let changed_symbols = candidate.reduction.changes_existing_symbols();
+        let path = candidate.path.clone();
+        candidate.apply(model)?;
+        if let Some(cache) = self.cache.as_mut() {
+            cache.applied(changed_symbols, &model.constraints, &path);
+        }
+        Ok(())
+    }
+
+    /// Forget which sub-expressions no rule applies to, e.g. when going back to an earlier model.
+    pub fn clear_cache(&mut self) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
+        }
+    }
+
+    fn find(&mut self, model: &Model, first_only: bool) -> Vec<RewriteCandidate<'a>> {
+        // Statistics are only kept for rewriter runs
+        find_candidates(
+            model,
+            &self.rules,
+            self.cache.as_mut(),
+            first_only,
+            &mut RewriterStats::default(),
+        )
+    }
+}
+
+/// Replace the sub-expression of `expression` at `path` with `new`.
+fn replace_at(expression: &Expression, path: &[usize], new: Expression) -> Option<Expression> {
+    let Some((first, rest)) = path.split_first() else {
+        return Some(new);
+    };
+    let mut children = expression.children();
+    let child = children.get(*first)?;
+    children[*first] = replace_at(child, rest, new)?;
+    expression.with_children(children).ok()
+}
+
+/// One rule application in a [`RewriteScript`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewriteStep {
+    pub rule: String,
+    /// See [`RewriteCandidate::path`].
+    pub path: Vec<usize>,
+    /// See [`RewriteCandidate::alternative`].
+    #[serde(default)]
+    pub alternative: usize,
+}
+
+impl Display for RewriteStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.rule, pretty_path(&self.path))?;
+        if self.alternative != 0 {
+            write!(f, " (alternative {})", self.alternative)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format a path as its indices joined by dots, or `root` for the empty path.
+pub fn pretty_path(path: &[usize]) -> String {
+    if path.is_empty() {
+        return "root".to_string();
+    }
+    path.iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// A sequence of rule applications, e.g. recorded while rewriting interactively, which can be replayed
+/// on the same model.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewriteScript {
+    pub steps: Vec<RewriteStep>,
+}
+
+impl RewriteScript {
+    /// Apply the steps of the script to a copy of the model, in order.
+    ///
+    /// # Returns
+    /// - The rewritten model
+    /// - `RewriteError::StepNotApplicable` if a step does not match any rule application
+    ///   found by [`apply_all_rules`] at that point
+    pub fn replay<'a>(&self, model: &Model, rules: &[&'a Rule<'a>]) -> Result<Model, RewriteError> {
+        let mut model = model.clone();
+        let mut finder = CandidateFinder::new(rules);
+        for (i, step) in self.steps.iter().enumerate() {
+            let candidate = finder
+                .all(&model)
+                .into_iter()
+                .find(|candidate| candidate.step() == *step)
+                .ok_or_else(|| RewriteError::StepNotApplicable(i + 1, step.clone()))?;
+            finder.apply(candidate, &mut model)?;
+        }
+        Ok(model)
+    }
+}
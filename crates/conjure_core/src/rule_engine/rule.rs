@@ -1,9 +1,11 @@
 use std::fmt::{self, Display, Formatter};
 use std::hash::Hash;
+use std::sync::Arc;
 
 use thiserror::Error;
+use uniplate::uniplate::Uniplate;
 
-use crate::ast::{Expression, SymbolTable};
+use crate::ast::{Domain, Expression, Name, SymbolTable};
 use crate::metadata::Metadata;
 use crate::model::Model;
 
@@ -19,12 +21,27 @@ pub enum ApplicationError {
 /// The result of applying a rule to an expression.
 ///
 /// Contains an expression to replace the original, a top-level constraint to add to the top of the constraint AST, and an expansion to the model symbol table.
+/// A reduction may also remove variables from the symbol table, or change their domains; see [`Reduction::remove_symbols`]
+/// and [`Reduction::update_domain`].
+///
+/// A rule with several valid encodings can return them all with [`Reduction::choice`]; the
+/// rewriter then picks between them according to its [`RewritePolicy`](crate::rule_engine::RewritePolicy).
 #[non_exhaustive]
 #[derive(Clone, Debug)]
 pub struct Reduction {
     pub new_expression: Expression,
     pub new_top: Expression,
     pub symbols: SymbolTable,
+    /// Variables to remove from the symbol table. The rule must make sure they are no longer used.
+    pub removed_symbols: Vec<Name>,
+    /// New domains for existing variables.
+    pub domain_updates: Vec<(Name, Domain)>,
+    /// New variables that stand for an expression, to be reused for equal expressions.
+    pub aux_vars: Vec<(Name, Expression)>,
+    /// Variables eliminated from the model, and what they were replaced by (see [`Model::eliminated`]).
+    pub eliminated: Vec<(Name, Expression)>,
+    /// Other reductions the rule could have made instead of this one.
+    alternatives: Vec<Reduction>,
 }
 
 /// The result of applying a rule to an expression.
@@ -37,6 +54,11 @@ impl Reduction {
             new_expression,
             new_top,
             symbols,
+            removed_symbols: Vec::new(),
+            domain_updates: Vec::new(),
+            aux_vars: Vec::new(),
+            eliminated: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 
@@ -46,6 +68,11 @@ impl Reduction {
             new_expression,
             new_top: Expression::Nothing,
             symbols: SymbolTable::new(),
+            removed_symbols: Vec::new(),
+            domain_updates: Vec::new(),
+            aux_vars: Vec::new(),
+            eliminated: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 
@@ -55,6 +82,11 @@ impl Reduction {
             new_expression,
             new_top: Expression::Nothing,
             symbols,
+            removed_symbols: Vec::new(),
+            domain_updates: Vec::new(),
+            aux_vars: Vec::new(),
+            eliminated: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 
@@ -64,60 +96,298 @@ impl Reduction {
             new_expression,
             new_top,
             symbols: SymbolTable::new(),
+            removed_symbols: Vec::new(),
+            domain_updates: Vec::new(),
+            aux_vars: Vec::new(),
+            eliminated: Vec::new(),
+            alternatives: Vec::new(),
+        }
+    }
+
+    /// Also remove the given variables from the symbol table.
+    pub fn remove_symbols(mut self, names: impl IntoIterator<Item = Name>) -> Self {
+        self.removed_symbols.extend(names);
+        self
+    }
+
+    /// Also change the domain of an existing variable.
+    pub fn update_domain(mut self, name: Name, domain: Domain) -> Self {
+        self.domain_updates.push((name, domain));
+        self
+    }
+
+    /// Also record that the new variable `name` stands for `expr` (common subexpression elimination).
+    ///
+    /// The reduction must also add the variable, and the constraints that define it to `new_top`. If the model
+    /// already has a variable for an expression equal to `expr`, up to the order of the operands of
+    /// commutative operators, [`Reduction::apply`] uses that variable instead: it drops `name` and the
+    /// constraints in `new_top` that mention it.
+    pub fn define_aux_var(mut self, name: Name, expr: Expression) -> Self {
+        self.aux_vars.push((name, expr));
+        self
+    }
+
+    /// The reduction that turns the model `old` into `new`, as returned by a model rule.
+    pub fn from_models(old: &Model, new: Model) -> Self {
+        // Keep any names the rule generated from being reused
+        old.reserve_names_from(&new);
+
+        let removed_symbols = old
+            .variables
+            .keys()
+            .filter(|name| !new.variables.contains_key(name))
+            .cloned()
+            .collect();
+        let aux_vars = new
+            .aux_vars
+            .iter()
+            .filter(|(expr, _)| !old.aux_vars.contains_key(expr))
+            .map(|(expr, name)| (name.clone(), expr.clone()))
+            .collect();
+        let eliminated = new
+            .eliminated
+            .iter()
+            .filter(|(name, replacement)| old.eliminated.get(name) != Some(replacement))
+            .map(|(name, replacement)| (name.clone(), replacement.clone()))
+            .collect();
+        let mut symbols = SymbolTable::new();
+        let mut domain_updates = Vec::new();
+        for (name, var) in new.variables {
+            match old.variables.get(&name) {
+                None => {
+                    symbols.insert(name, var);
+                }
+                Some(old_var) if old_var.domain != var.domain => {
+                    domain_updates.push((name, var.domain))
+                }
+                Some(_) => {}
+            }
+        }
+
+        Self {
+            new_expression: new.constraints,
+            new_top: Expression::Nothing,
+            symbols,
+            removed_symbols,
+            domain_updates,
+            aux_vars,
+            eliminated,
+            alternatives: Vec::new(),
         }
     }
 
+    /// Whether applying this reduction removes variables or changes their domains, which may make other rules
+    /// applicable to parts of the model it does not rewrite.
+    pub fn changes_existing_symbols(&self) -> bool {
+        !self.removed_symbols.is_empty() || !self.domain_updates.is_empty()
+    }
+
+    /// Represents a choice between several reductions, e.g. different encodings of a constraint.
+    ///
+    /// The first reduction is the default, used by [`RewritePolicy::First`](crate::rule_engine::RewritePolicy::First).
+    /// Returns `RuleNotApplicable` if there are no reductions to choose from.
+    pub fn choice(reductions: impl IntoIterator<Item = Reduction>) -> ApplicationResult {
+        let mut reductions = reductions
+            .into_iter()
+            .flat_map(Reduction::into_alternatives);
+        let mut first = reductions
+            .next()
+            .ok_or(ApplicationError::RuleNotApplicable)?;
+        first.alternatives = reductions.collect();
+        Ok(first)
+    }
+
+    /// Whether this reduction offers a choice between several reductions.
+    pub fn has_alternatives(&self) -> bool {
+        !self.alternatives.is_empty()
+    }
+
+    /// Split this reduction into the reductions it offers a choice between, the default first.
+    pub fn into_alternatives(mut self) -> Vec<Reduction> {
+        let alternatives = std::mem::take(&mut self.alternatives);
+        let mut all = vec![self];
+        all.extend(alternatives);
+        all
+    }
+
+    /// A rough measure of how expensive this reduction makes the model: the number of expression
+    /// nodes it adds, plus one for each new variable.
+    pub fn cost(&self) -> usize {
+        let nodes = |expr: &Expression| match expr {
+            Expression::Nothing => 0,
+            _ => expr.universe().len(),
+        };
+        nodes(&self.new_expression) + nodes(&self.new_top) + self.symbols.len()
+    }
+
     // Apply side-effects (e.g. symbol table updates
     pub fn apply(self, model: &mut Model) {
+        for name in &self.removed_symbols {
+            model.variables.remove(name);
+        }
+        for (name, domain) in self.domain_updates {
+            model.update_domain(&name, domain);
+        }
         model.variables.extend(self.symbols); // Add new assignments to the symbol table
-        if self.new_top.is_nothing() {
-            model.constraints = self.new_expression.clone();
+        model.eliminated.extend(self.eliminated);
+        let mut new_expression = self.new_expression;
+        let mut new_top = self.new_top;
+        for (name, expr) in self.aux_vars {
+            match model.find_aux_var(&expr) {
+                Some(existing) if existing != name => {
+                    model.variables.remove(&name);
+                    let existing = Expression::Reference(Metadata::new(), existing);
+                    new_expression = new_expression.substitute(&name, &existing);
+                    new_top = without_mentions(new_top, &name);
+                }
+                _ => model.add_aux_var(name, &expr),
+            }
+        }
+        if new_top.is_nothing() {
+            model.constraints = new_expression;
         } else {
-            model.constraints = match self.new_expression {
+            model.constraints = match new_expression {
                 Expression::And(metadata, mut exprs) => {
                     // Avoid creating a nested conjunction
-                    exprs.push(self.new_top.clone());
+                    Arc::make_mut(&mut exprs).push(new_top);
                     Expression::And(metadata.clone_dirty(), exprs)
                 }
-                _ => Expression::And(
-                    Metadata::new(),
-                    vec![self.new_expression.clone(), self.new_top],
-                ),
+                _ => Expression::And(Metadata::new(), vec![new_expression, new_top].into()),
             };
         }
     }
 }
 
+/// `top` without the conjuncts that mention `name`, or `Nothing` if none are left.
+fn without_mentions(top: Expression, name: &Name) -> Expression {
+    match top {
+        Expression::And(metadata, exprs) => {
+            let exprs: Vec<_> = exprs
+                .iter()
+                .filter(|expr| !expr.mentions(name))
+                .cloned()
+                .collect();
+            if exprs.is_empty() {
+                Expression::Nothing
+            } else {
+                Expression::And(metadata, exprs.into())
+            }
+        }
+        top if top.mentions(name) => Expression::Nothing,
+        top => top,
+    }
+}
+
+/// The function that applies a rule to an expression.
+pub type RuleApplication = dyn Fn(&Expression, &Model) -> ApplicationResult + Send + Sync;
+
+/// The function that applies a model rule, which sees all the constraints and variables at once and
+/// returns the whole new model.
+///
+/// It returns `RuleNotApplicable` if it would not change the model.
+pub type ModelRuleApplication = dyn Fn(&Model) -> Result<Model, ApplicationError> + Send + Sync;
+
+/// What a rule applies to, and the function that applies it.
+#[derive(Clone, Copy)]
+pub enum Application<'a> {
+    /// The rule rewrites one expression, anywhere in the constraints.
+    Expression(&'a RuleApplication),
+    /// The rule rewrites the whole model.
+    ///
+    /// The rewriter only tries model rules once no expression rule applies to the model.
+    Model(&'a ModelRuleApplication),
+}
+
 /**
  * A rule with a name, application function, and rule sets.
  *
  * # Fields
  * - `name` The name of the rule.
- * - `application` The function to apply the rule.
+ * - `application` The function to apply the rule, to an expression or to the whole model. Compiled rules point at a plain function; rules loaded from a rule file at runtime carry their patterns in a closure.
  * - `rule_sets` A list of rule set names and priorities that this rule is a part of. This is used to populate rulesets at runtime.
+ * - `applicable_variants` The names of the `Expression` variants this rule can apply to, or `None` if it may apply to any expression.
  */
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Rule<'a> {
     pub name: &'a str,
-    pub application: fn(&Expression, &Model) -> ApplicationResult,
+    pub application: Application<'a>,
     pub rule_sets: &'a [(&'a str, u8)], // (name, priority). At runtime, we add the rule to rulesets
+    pub applicable_variants: Option<&'a [&'a str]>,
+    /// The rule's documentation, taken from the doc comment on its definition.
+    pub doc: &'a str,
 }
 
 impl<'a> Rule<'a> {
     pub const fn new(
         name: &'a str,
-        application: fn(&Expression, &Model) -> ApplicationResult,
+        application: &'a RuleApplication,
+        rule_sets: &'a [(&'static str, u8)],
+    ) -> Self {
+        Self {
+            name,
+            application: Application::Expression(application),
+            rule_sets,
+            applicable_variants: None,
+            doc: "",
+        }
+    }
+
+    pub const fn new_model_rule(
+        name: &'a str,
+        application: &'a ModelRuleApplication,
         rule_sets: &'a [(&'static str, u8)],
     ) -> Self {
         Self {
             name,
-            application,
+            application: Application::Model(application),
             rule_sets,
+            applicable_variants: None,
+            doc: "",
+        }
+    }
+
+    /// Whether this rule rewrites the whole model rather than a single expression.
+    pub fn is_model_rule(&self) -> bool {
+        matches!(self.application, Application::Model(_))
+    }
+
+    /// Whether this rule could apply to the given expression, based on its variant alone.
+    ///
+    /// Rules that do not declare their applicable variants are candidates for every expression.
+    /// Model rules never apply to a single expression.
+    pub fn applies_to(&self, expr: &Expression) -> bool {
+        match self.applicable_variants {
+            _ if self.is_model_rule() => false,
+            None => true,
+            Some(variants) => variants.contains(&expr.variant_name()),
         }
     }
 
+    /// Apply the rule to an expression. Model rules are never applicable.
     pub fn apply(&self, expr: &Expression, mdl: &Model) -> ApplicationResult {
-        (self.application)(expr, mdl)
+        match self.application {
+            Application::Expression(application) => application(expr, mdl),
+            Application::Model(_) => Err(ApplicationError::RuleNotApplicable),
+        }
+    }
+
+    /// Apply a model rule to the whole model, returning the reduction from the old model to the new one.
+    /// Expression rules are never applicable.
+    pub fn apply_to_model(&self, mdl: &Model) -> ApplicationResult {
+        match self.application {
+            Application::Model(application) => Ok(Reduction::from_models(mdl, application(mdl)?)),
+            Application::Expression(_) => Err(ApplicationError::RuleNotApplicable),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Rule<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rule")
+            .field("name", &self.name)
+            .field("rule_sets", &self.rule_sets)
+            .field("applicable_variants", &self.applicable_variants)
+            .finish_non_exhaustive()
     }
 }
 
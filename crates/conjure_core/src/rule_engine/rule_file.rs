@@ -0,0 +1,1226 @@
+//! Rules loaded at runtime from text rule files.
+//!
+//! A rule file declares rule sets and rules in the same pattern/template notation as
+//! [`pattern_rule!`](crate::rule_engine::pattern_rule):
+//!
+//! ```text
+//! # Comments start with '#'.
+//! # Rule sets use the same arguments as `register_rule_set!`: name, order, dependencies and
+//! # (optionally) the solver families they are enabled for.
+//! rule_set "Experimental", 120, ("Base"), (Minion)
+//!
+//! # A rule is a header with its name and (rule set, priority) pairs, followed by one arm per line.
+//! rule negated_lt ("Experimental", 10):
+//!     !(a < b) => a >= b
+//!     !(a <= b) => a > b
+//!
+//! rule drop_true ("Experimental", 10), ("Base", 5):
+//!     and([true, ..rest]) => and([..rest])
+//! ```
+//!
+//! Guards cannot run Rust code, so they are built from a few predicates combined with `!`, `&&`
+//! and `||`: `is_<variant>(x)` (e.g. `is_constant(x)`, `is_sum(x)`), `can_be_undefined(x)` and
+//! `empty(xs)` for lists.
+//!
+//! Loaded rules and rule sets are registered alongside the compiled ones, so they are picked up by
+//! [`get_rules`](crate::rule_engine::get_rules), [`get_rule_sets`](crate::rule_engine::get_rule_sets)
+//! and [`resolve_rule_sets`](crate::rule_engine::resolve_rule_sets). Files must be loaded before
+//! the rule sets they add rules to are first used; loaded rules live for the rest of the program.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use strum::VariantNames;
+use thiserror::Error;
+
+use crate::ast::{Constant, Expression};
+use crate::metadata::Metadata;
+use crate::model::Model;
+use crate::rule_engine::pattern_rule::root_metadata;
+use crate::rule_engine::{
+    Application, ApplicationError, ApplicationResult, Reduction, Rule, RuleSet,
+    RULES_DISTRIBUTED_SLICE, RULE_SETS_DISTRIBUTED_SLICE,
+};
+use crate::solver::SolverFamily;
+
+#[derive(Debug, Error)]
+pub enum RuleFileError {
+    #[error("could not read rule file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("rule `{0}` is already defined")]
+    DuplicateRule(String),
+
+    #[error("rule set `{0}` is already defined")]
+    DuplicateRuleSet(String),
+
+    #[error("rule set `{0}` is not defined")]
+    UnknownRuleSet(String),
+
+    #[error("rule set `{0}` is already in use, so rules can no longer be added to it")]
+    RuleSetInUse(String),
+}
+
+struct Loaded {
+    rules: Vec<&'static Rule<'static>>,
+    rule_sets: Vec<&'static RuleSet<'static>>,
+}
+
+static LOADED: RwLock<Loaded> = RwLock::new(Loaded {
+    rules: Vec::new(),
+    rule_sets: Vec::new(),
+});
+
+pub(super) fn loaded_rules() -> Vec<&'static Rule<'static>> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .rules
+        .clone()
+}
+
+pub(super) fn loaded_rule_sets() -> Vec<&'static RuleSet<'static>> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .rule_sets
+        .clone()
+}
+
+/// Load the rules and rule sets declared in the rule file at `path`.
+///
+/// Nothing is registered unless the whole file is valid.
+pub fn load_rule_file(path: &Path) -> Result<(), RuleFileError> {
+    let source = std::fs::read_to_string(path).map_err(|source| RuleFileError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    load_rules(&source)
+}
+
+/// Load the rules and rule sets declared in the text of a rule file.
+///
+/// Nothing is registered unless the whole text is valid.
+pub fn load_rules(source: &str) -> Result<(), RuleFileError> {
+    let (rule_sets, rules) = parse_rule_file(source)?;
+
+    // Registered rules are looked up through the lock we hold, rather than `get_rules`.
+    let mut loaded = LOADED.write().unwrap_or_else(PoisonError::into_inner);
+    let existing_rule_set = |name: &str| {
+        RULE_SETS_DISTRIBUTED_SLICE
+            .iter()
+            .chain(loaded.rule_sets.iter().copied())
+            .find(|rule_set| rule_set.name == name)
+    };
+    let existing_rules: HashSet<&str> = RULES_DISTRIBUTED_SLICE
+        .iter()
+        .chain(loaded.rules.iter().copied())
+        .map(|rule| rule.name)
+        .collect();
+
+    let mut declared = HashSet::new();
+    for rule_set in &rule_sets {
+        if existing_rule_set(&rule_set.name).is_some() || !declared.insert(&rule_set.name) {
+            return Err(RuleFileError::DuplicateRuleSet(rule_set.name.clone()));
+        }
+    }
+    for rule_set in &rule_sets {
+        for dependency in &rule_set.dependencies {
+            if !declared.contains(dependency) && existing_rule_set(dependency).is_none() {
+                return Err(RuleFileError::UnknownRuleSet(dependency.clone()));
+            }
+        }
+    }
+
+    let mut names = HashSet::new();
+    for rule in &rules {
+        if existing_rules.contains(rule.name.as_str()) || !names.insert(&rule.name) {
+            return Err(RuleFileError::DuplicateRule(rule.name.clone()));
+        }
+        for (rule_set, _) in &rule.rule_sets {
+            if declared.contains(rule_set) {
+                continue;
+            }
+            match existing_rule_set(rule_set) {
+                None => return Err(RuleFileError::UnknownRuleSet(rule_set.clone())),
+                Some(existing) if existing.rules_resolved() => {
+                    return Err(RuleFileError::RuleSetInUse(rule_set.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    // Rules and rule sets are referenced for the rest of the program, so they are leaked.
+    for rule_set in rule_sets {
+        let dependencies: Vec<&'static str> = rule_set
+            .dependencies
+            .into_iter()
+            .map(|name| &*name.leak())
+            .collect();
+        loaded.rule_sets.push(Box::leak(Box::new(RuleSet::new(
+            rule_set.name.leak(),
+            rule_set.order,
+            dependencies.leak(),
+            rule_set.solver_families.leak(),
+        ))));
+    }
+    for rule in rules {
+        loaded.rules.push(Box::leak(Box::new(rule.into_rule())));
+    }
+
+    Ok(())
+}
+
+/*****************************************************************************/
+/*                                 Rules                                     */
+/*****************************************************************************/
+
+struct RuleSetDecl {
+    name: String,
+    order: u8,
+    dependencies: Vec<String>,
+    solver_families: Vec<SolverFamily>,
+}
+
+struct RuleDecl {
+    name: String,
+    rule_sets: Vec<(String, u8)>,
+    arms: Vec<Arm>,
+    /// The source of the arms, which serves as the rule's documentation.
+    doc: Vec<String>,
+}
+
+struct Arm {
+    pattern: Term,
+    guard: Option<Guard>,
+    template: Term,
+}
+
+impl RuleDecl {
+    fn into_rule(self) -> Rule<'static> {
+        let rule_sets: Vec<(&'static str, u8)> = self
+            .rule_sets
+            .into_iter()
+            .map(|(name, priority)| (&*name.leak(), priority))
+            .collect();
+
+        // As with `pattern_rule!`, the rule applies to the variants at the top of its patterns.
+        let mut variants = Vec::new();
+        for arm in &self.arms {
+            match &arm.pattern {
+                Term::Node(variant, _) if variants.contains(variant) => {}
+                Term::Node(variant, _) => variants.push(*variant),
+                Term::Constant(_) if variants.contains(&"Constant") => {}
+                Term::Constant(_) => variants.push("Constant"),
+                _ => {
+                    variants.clear();
+                    break;
+                }
+            }
+        }
+
+        let arms = self.arms;
+        let application = move |expr: &Expression, _: &Model| -> ApplicationResult {
+            arms.iter()
+                .find_map(|arm| arm.apply(expr))
+                .map(Reduction::pure)
+                .ok_or(ApplicationError::RuleNotApplicable)
+        };
+
+        Rule {
+            name: self.name.leak(),
+            application: Application::Expression(Box::leak(Box::new(application))),
+            rule_sets: rule_sets.leak(),
+            applicable_variants: (!variants.is_empty()).then(|| &*variants.leak()),
+            doc: self.doc.join("\n").leak(),
+        }
+    }
+}
+
+impl Arm {
+    fn apply(&self, expr: &Expression) -> Option<Expression> {
+        let mut bindings = HashMap::new();
+        if !match_term(&self.pattern, expr, &mut bindings) {
+            return None;
+        }
+        if let Some(guard) = &self.guard {
+            if !guard.holds(&bindings) {
+                return None;
+            }
+        }
+        match build(&self.template, &bindings) {
+            Value::One(mut new) => {
+                // As with `pattern_rule!`, an expression built at the root of the template takes the
+                // metadata of the matched expression
+                if matches!(self.template, Term::Node(..) | Term::Constant(_)) {
+                    if let Some(metadata) = new.metadata_mut() {
+                        *metadata = root_metadata(expr);
+                    }
+                }
+                Some(new)
+            }
+            // Rejected when the rule file is loaded
+            Value::List(_) => None,
+        }
+    }
+}
+
+/*****************************************************************************/
+/*                        Patterns and templates                             */
+/*****************************************************************************/
+
+/// A pattern or template.
+#[derive(Debug)]
+enum Term {
+    Var(String),
+    Wildcard,
+    Constant(Constant),
+    /// An expression variant and its fields, excluding metadata.
+    Node(&'static str, Vec<Term>),
+    /// List items, then `None` for a closed list, `Some(None)` for `..` or `Some(Some(tail))` for `..tail`.
+    List(Vec<Term>, Option<Option<String>>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    One,
+    List,
+}
+
+/// A built expression or list.
+#[derive(Clone, Debug)]
+enum Value {
+    One(Expression),
+    List(Vec<Expression>),
+}
+
+/// A field of a matched expression, borrowed from it.
+#[derive(Clone, Copy, Debug)]
+enum Field<'a> {
+    One(&'a Expression),
+    List(&'a [Expression]),
+}
+
+impl Field<'_> {
+    fn to_value(self) -> Value {
+        match self {
+            Field::One(expr) => Value::One(expr.clone()),
+            Field::List(exprs) => Value::List(exprs.to_vec()),
+        }
+    }
+}
+
+type Bindings<'a> = HashMap<String, Field<'a>>;
+
+/// The kinds of the fields of the expression variants that rule files can match and build.
+fn shape(variant: &str) -> Option<&'static [Kind]> {
+    use Kind::*;
+    match variant {
+        "Not" => Some(&[One]),
+        "Bubble" | "Eq" | "Neq" | "Geq" | "Leq" | "Gt" | "Lt" | "SafeDiv" | "UnsafeDiv"
+        | "SafeMod" | "UnsafeMod" | "SafePow" | "UnsafePow" => Some(&[One, One]),
+        "DivEq" | "ModEq" | "PowEq" | "Ineq" => Some(&[One, One, One]),
+        "Sum" | "Min" | "Or" | "And" | "AllDiff" => Some(&[List]),
+        "SumEq" | "SumGeq" | "SumLeq" | "MinEq" => Some(&[List, One]),
+        _ => None,
+    }
+}
+
+/// The fields of `expr`, if it is one of the variants described by [`shape`].
+fn fields<'a>(expr: &'a Expression) -> Option<Vec<Field<'a>>> {
+    let one = |e: &'a Arc<Expression>| Field::One(e.as_ref());
+    let list = |es: &'a Arc<Vec<Expression>>| Field::List(es.as_slice());
+    use Expression::*;
+    match expr {
+        Not(_, a) => Some(vec![one(a)]),
+        Bubble(_, a, b)
+        | Eq(_, a, b)
+        | Neq(_, a, b)
+        | Geq(_, a, b)
+        | Leq(_, a, b)
+        | Gt(_, a, b)
+        | Lt(_, a, b)
+        | SafeDiv(_, a, b)
+        | UnsafeDiv(_, a, b)
+        | SafeMod(_, a, b)
+        | UnsafeMod(_, a, b)
+        | SafePow(_, a, b)
+        | UnsafePow(_, a, b) => Some(vec![one(a), one(b)]),
+        DivEq(_, a, b, c) | ModEq(_, a, b, c) | PowEq(_, a, b, c) | Ineq(_, a, b, c) => {
+            Some(vec![one(a), one(b), one(c)])
+        }
+        Sum(_, es) | Min(_, es) | Or(_, es) | And(_, es) | AllDiff(_, es) => Some(vec![list(es)]),
+        SumEq(_, es, a) | SumGeq(_, es, a) | SumLeq(_, es, a) | MinEq(_, es, a) => {
+            Some(vec![list(es), one(a)])
+        }
+        _ => None,
+    }
+}
+
+/// Builds `variant` from fields matching its [`shape`].
+fn construct(variant: &str, fields: Vec<Value>) -> Option<Expression> {
+    let mut ones = Vec::new();
+    let mut lists = Vec::new();
+    for field in fields {
+        match field {
+            Value::One(e) => ones.push(Arc::new(e)),
+            Value::List(es) => lists.push(Arc::new(es)),
+        }
+    }
+    let mut ones = ones.into_iter();
+    let mut one = || ones.next();
+    let mut lists = lists.into_iter();
+    let mut list = || lists.next();
+    let md = Metadata::new;
+    use Expression::*;
+    Some(match variant {
+        "Not" => Not(md(), one()?),
+        "Bubble" => Bubble(md(), one()?, one()?),
+        "Eq" => Eq(md(), one()?, one()?),
+        "Neq" => Neq(md(), one()?, one()?),
+        "Geq" => Geq(md(), one()?, one()?),
+        "Leq" => Leq(md(), one()?, one()?),
+        "Gt" => Gt(md(), one()?, one()?),
+        "Lt" => Lt(md(), one()?, one()?),
+        "SafeDiv" => SafeDiv(md(), one()?, one()?),
+        "UnsafeDiv" => UnsafeDiv(md(), one()?, one()?),
+        "SafeMod" => SafeMod(md(), one()?, one()?),
+        "UnsafeMod" => UnsafeMod(md(), one()?, one()?),
+        "SafePow" => SafePow(md(), one()?, one()?),
+        "UnsafePow" => UnsafePow(md(), one()?, one()?),
+        "DivEq" => DivEq(md(), one()?, one()?, one()?),
+        "ModEq" => ModEq(md(), one()?, one()?, one()?),
+        "PowEq" => PowEq(md(), one()?, one()?, one()?),
+        "Ineq" => Ineq(md(), one()?, one()?, one()?),
+        "Sum" => Sum(md(), list()?),
+        "Min" => Min(md(), list()?),
+        "Or" => Or(md(), list()?),
+        "And" => And(md(), list()?),
+        "AllDiff" => AllDiff(md(), list()?),
+        "SumEq" => SumEq(md(), list()?, one()?),
+        "SumGeq" => SumGeq(md(), list()?, one()?),
+        "SumLeq" => SumLeq(md(), list()?, one()?),
+        "MinEq" => MinEq(md(), list()?, one()?),
+        _ => return None,
+    })
+}
+
+fn match_term<'a>(term: &Term, expr: &'a Expression, bindings: &mut Bindings<'a>) -> bool {
+    match term {
+        Term::Var(name) => {
+            bindings.insert(name.clone(), Field::One(expr));
+            true
+        }
+        Term::Wildcard => true,
+        Term::Constant(c) => matches!(expr, Expression::Constant(_, e) if e == c),
+        Term::Node(variant, args) => {
+            if expr.variant_name() != *variant {
+                return false;
+            }
+            let Some(fields) = fields(expr) else {
+                return false;
+            };
+            args.iter()
+                .zip(fields)
+                .all(|(arg, field)| match_field(arg, field, bindings))
+        }
+        Term::List(..) => false,
+    }
+}
+
+fn match_field<'a>(term: &Term, field: Field<'a>, bindings: &mut Bindings<'a>) -> bool {
+    match (term, field) {
+        (_, Field::One(expr)) => match_term(term, expr, bindings),
+        (Term::Var(name), list @ Field::List(_)) => {
+            bindings.insert(name.clone(), list);
+            true
+        }
+        (Term::Wildcard, Field::List(_)) => true,
+        (Term::List(items, tail), Field::List(exprs)) => {
+            let length_ok = match tail {
+                None => exprs.len() == items.len(),
+                Some(_) => exprs.len() >= items.len(),
+            };
+            if !length_ok
+                || !items
+                    .iter()
+                    .zip(exprs)
+                    .all(|(item, expr)| match_term(item, expr, bindings))
+            {
+                return false;
+            }
+            if let Some(Some(name)) = tail {
+                bindings.insert(name.clone(), Field::List(&exprs[items.len()..]));
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Builds a template. The template has been checked against the pattern's bindings when loaded.
+fn build(term: &Term, bindings: &Bindings) -> Value {
+    match term {
+        Term::Var(name) => bindings
+            .get(name)
+            .map_or(Value::One(Expression::Nothing), |field| field.to_value()),
+        Term::Wildcard => Value::One(Expression::Nothing),
+        Term::Constant(c) => Value::One(Expression::Constant(Metadata::new(), c.clone())),
+        Term::Node(variant, args) => {
+            let fields = args.iter().map(|arg| build(arg, bindings)).collect();
+            Value::One(construct(variant, fields).unwrap_or(Expression::Nothing))
+        }
+        Term::List(items, tail) => {
+            let mut exprs = Vec::new();
+            for item in items {
+                match build(item, bindings) {
+                    Value::One(expr) => exprs.push(expr),
+                    Value::List(list) => exprs.extend(list),
+                }
+            }
+            if let Some(Some(name)) = tail {
+                if let Some(Field::List(list)) = bindings.get(name) {
+                    exprs.extend(list.iter().cloned());
+                }
+            }
+            Value::List(exprs)
+        }
+    }
+}
+
+/// Records the variables bound by a pattern matched against a field of the given kind.
+fn bind_pattern(term: &Term, kind: Kind, vars: &mut HashMap<String, Kind>) -> Result<(), String> {
+    match (term, kind) {
+        (Term::Var(name), _) => match vars.insert(name.clone(), kind) {
+            Some(_) => Err(format!(
+                "`{}` is bound more than once in this pattern",
+                name
+            )),
+            None => Ok(()),
+        },
+        (Term::Wildcard, _) => Ok(()),
+        (Term::Constant(_) | Term::Node(..), Kind::One) => {
+            check_args(term, |arg, kind| bind_pattern(arg, kind, vars))
+        }
+        (Term::List(items, tail), Kind::List) => {
+            for item in items {
+                bind_pattern(item, Kind::One, vars)?;
+            }
+            if let Some(Some(name)) = tail {
+                if vars.insert(name.clone(), Kind::List).is_some() {
+                    return Err(format!(
+                        "`{}` is bound more than once in this pattern",
+                        name
+                    ));
+                }
+            }
+            Ok(())
+        }
+        (Term::List(..), Kind::One) => Err(String::from("expected an expression, found a list")),
+        (_, Kind::List) => Err(String::from("expected a list, found an expression")),
+    }
+}
+
+/// Checks that a template only uses bound variables, each where its kind fits.
+fn check_template(term: &Term, kind: Kind, vars: &HashMap<String, Kind>) -> Result<(), String> {
+    match (term, kind) {
+        (Term::Var(name), _) => match vars.get(name) {
+            None => Err(format!("`{}` is not bound by the pattern", name)),
+            Some(bound) if *bound != kind => Err(format!(
+                "`{}` is bound to {}, but used as {}",
+                name,
+                describe(*bound),
+                describe(kind)
+            )),
+            Some(_) => Ok(()),
+        },
+        (Term::Wildcard, _) => Err(String::from("`_` cannot be used in a template")),
+        (Term::Constant(_) | Term::Node(..), Kind::One) => {
+            check_args(term, |arg, kind| check_template(arg, kind, vars))
+        }
+        (Term::List(items, tail), Kind::List) => {
+            for item in items {
+                check_template(item, Kind::One, vars)?;
+            }
+            match tail {
+                Some(None) => Err(String::from("expected `..name` to splice a list")),
+                Some(Some(name)) => check_template(&Term::Var(name.clone()), Kind::List, vars),
+                None => Ok(()),
+            }
+        }
+        (Term::List(..), Kind::One) => Err(String::from("expected an expression, found a list")),
+        (_, Kind::List) => Err(String::from("expected a list, found an expression")),
+    }
+}
+
+fn describe(kind: Kind) -> &'static str {
+    match kind {
+        Kind::One => "an expression",
+        Kind::List => "a list",
+    }
+}
+
+/// Runs `check` on each argument of a node, with the kind of field it fills.
+fn check_args(
+    term: &Term,
+    mut check: impl FnMut(&Term, Kind) -> Result<(), String>,
+) -> Result<(), String> {
+    let Term::Node(variant, args) = term else {
+        return Ok(());
+    };
+    let Some(kinds) = shape(variant) else {
+        return Err(format!(
+            "`{}` expressions are not supported in rule files",
+            variant
+        ));
+    };
+    if kinds.len() != args.len() {
+        return Err(format!(
+            "`{}` takes {} argument(s), found {}",
+            variant,
+            kinds.len(),
+            args.len()
+        ));
+    }
+    args.iter()
+        .zip(kinds)
+        .try_for_each(|(arg, kind)| check(arg, *kind))
+}
+
+/*****************************************************************************/
+/*                                 Guards                                    */
+/*****************************************************************************/
+
+#[derive(Debug)]
+enum Guard {
+    Not(Box<Guard>),
+    And(Box<Guard>, Box<Guard>),
+    Or(Box<Guard>, Box<Guard>),
+    Test(Predicate, String),
+}
+
+#[derive(Debug)]
+enum Predicate {
+    IsVariant(&'static str),
+    CanBeUndefined,
+    Empty,
+}
+
+impl Predicate {
+    fn kind(&self) -> Kind {
+        match self {
+            Predicate::IsVariant(_) | Predicate::CanBeUndefined => Kind::One,
+            Predicate::Empty => Kind::List,
+        }
+    }
+}
+
+impl Guard {
+    fn holds(&self, bindings: &Bindings) -> bool {
+        match self {
+            Guard::Not(guard) => !guard.holds(bindings),
+            Guard::And(a, b) => a.holds(bindings) && b.holds(bindings),
+            Guard::Or(a, b) => a.holds(bindings) || b.holds(bindings),
+            Guard::Test(predicate, var) => match (predicate, bindings.get(var)) {
+                (Predicate::IsVariant(variant), Some(Field::One(e))) => {
+                    e.variant_name() == *variant
+                }
+                (Predicate::CanBeUndefined, Some(Field::One(e))) => e.can_be_undefined(),
+                (Predicate::Empty, Some(Field::List(es))) => es.is_empty(),
+                _ => false,
+            },
+        }
+    }
+
+    fn check(&self, vars: &HashMap<String, Kind>) -> Result<(), String> {
+        match self {
+            Guard::Not(guard) => guard.check(vars),
+            Guard::And(a, b) | Guard::Or(a, b) => {
+                a.check(vars)?;
+                b.check(vars)
+            }
+            Guard::Test(predicate, var) => {
+                check_template(&Term::Var(var.clone()), predicate.kind(), vars)
+            }
+        }
+    }
+}
+
+/*****************************************************************************/
+/*                                 Parsing                                   */
+/*****************************************************************************/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i32),
+    Str(String),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: [&str; 18] = [
+    "=>", "==", "!=", "<=", ">=", "&&", "||", "..", "(", ")", "[", "]", ",", ":", "<", ">", "!",
+    "-",
+];
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c == '#' {
+            break;
+        } else if c.is_whitespace() {
+            rest = rest.trim_start();
+            continue;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let value = rest[..end]
+                .parse()
+                .map_err(|_| format!("integer `{}` is out of range", &rest[..end]))?;
+            tokens.push(Token::Int(value));
+            rest = &rest[end..];
+        } else if c == '"' {
+            let end = rest[1..]
+                .find('"')
+                .ok_or_else(|| String::from("unterminated string"))?;
+            tokens.push(Token::Str(rest[1..=end].to_string()));
+            rest = &rest[end + 2..];
+        } else if let Some(punct) = PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+            tokens.push(Token::Punct(punct));
+            rest = &rest[punct.len()..];
+        } else {
+            return Err(format!("unexpected character `{}`", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn is_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(i)) if i == ident)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.is_punct(punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", punct)))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> String {
+        match self.peek() {
+            None => format!("expected {}, found the end of the line", expected),
+            Some(Token::Ident(i)) => format!("expected {}, found `{}`", expected, i),
+            Some(Token::Int(i)) => format!("expected {}, found `{}`", expected, i),
+            Some(Token::Str(s)) => format!("expected {}, found \"{}\"", expected, s),
+            Some(Token::Punct(p)) => format!("expected {}, found `{}`", expected, p),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Ident(i)) => {
+                let i = i.clone();
+                self.pos += 1;
+                Ok(i)
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Str(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => Err(self.unexpected("a string")),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        match self.peek() {
+            Some(Token::Int(i)) => {
+                let i = *i;
+                self.pos += 1;
+                u8::try_from(i).map_err(|_| format!("`{}` is not between 0 and 255", i))
+            }
+            _ => Err(self.unexpected("a number")),
+        }
+    }
+
+    fn end(&self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.unexpected("the end of the line")),
+        }
+    }
+
+    /// `(<item>, ...)`
+    fn parenthesized<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        self.expect_punct("(")?;
+        let mut items = Vec::new();
+        while !self.eat_punct(")") {
+            items.push(item(self)?);
+            if !self.is_punct(")") {
+                self.expect_punct(",")?;
+            }
+        }
+        Ok(items)
+    }
+
+    /// `rule_set "<name>", <order>[, (<dependency>, ...)[, (<solver family>, ...)]]`
+    fn rule_set(&mut self) -> Result<RuleSetDecl, String> {
+        let name = self.string()?;
+        self.expect_punct(",")?;
+        let order = self.u8()?;
+        let mut dependencies = Vec::new();
+        let mut solver_families = Vec::new();
+        if self.eat_punct(",") {
+            dependencies = self.parenthesized(Self::string)?;
+            if self.eat_punct(",") {
+                solver_families = self.parenthesized(|p| {
+                    let family = p.ident()?;
+                    SolverFamily::from_str(&family)
+                        .map_err(|_| format!("unknown solver family `{}`", family))
+                })?;
+            }
+        }
+        self.end()?;
+        Ok(RuleSetDecl {
+            name,
+            order,
+            dependencies,
+            solver_families,
+        })
+    }
+
+    /// `rule <name> ("<rule set>", <priority>), ...:`
+    fn rule_header(&mut self) -> Result<RuleDecl, String> {
+        let name = self.ident()?;
+        let mut rule_sets = Vec::new();
+        loop {
+            self.expect_punct("(")?;
+            let rule_set = self.string()?;
+            self.expect_punct(",")?;
+            let priority = self.u8()?;
+            self.expect_punct(")")?;
+            rule_sets.push((rule_set, priority));
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        self.expect_punct(":")?;
+        self.end()?;
+        Ok(RuleDecl {
+            name,
+            rule_sets,
+            arms: Vec::new(),
+            doc: Vec::new(),
+        })
+    }
+
+    /// `<pattern> [if <guard>] => <template>`
+    fn arm(&mut self) -> Result<Arm, String> {
+        let pattern = self.term()?;
+        let mut vars = HashMap::new();
+        bind_pattern(&pattern, Kind::One, &mut vars)?;
+
+        let guard = if self.is_ident("if") {
+            self.pos += 1;
+            let guard = self.guard()?;
+            guard.check(&vars)?;
+            Some(guard)
+        } else {
+            None
+        };
+
+        self.expect_punct("=>")?;
+        let template = self.term()?;
+        check_template(&template, Kind::One, &vars)?;
+        self.end()?;
+        Ok(Arm {
+            pattern,
+            guard,
+            template,
+        })
+    }
+
+    /// A comparison, or a single operand.
+    fn term(&mut self) -> Result<Term, String> {
+        let left = self.unary()?;
+        let variant = match self.peek() {
+            Some(Token::Punct("==")) => "Eq",
+            Some(Token::Punct("!=")) => "Neq",
+            Some(Token::Punct("<")) => "Lt",
+            Some(Token::Punct("<=")) => "Leq",
+            Some(Token::Punct(">")) => "Gt",
+            Some(Token::Punct(">=")) => "Geq",
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.unary()?;
+        Ok(Term::Node(variant, vec![left, right]))
+    }
+
+    fn unary(&mut self) -> Result<Term, String> {
+        if self.eat_punct("!") {
+            return Ok(Term::Node("Not", vec![self.unary()?]));
+        }
+        if self.eat_punct("-") {
+            return match self.next() {
+                Some(Token::Int(i)) => Ok(Term::Constant(Constant::Int(-i))),
+                _ => Err(String::from("expected a number after `-`")),
+            };
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Term, String> {
+        match self.peek().cloned() {
+            Some(Token::Int(i)) => {
+                self.pos += 1;
+                Ok(Term::Constant(Constant::Int(i)))
+            }
+            Some(Token::Ident(ident)) => {
+                self.pos += 1;
+                match ident.as_str() {
+                    "true" => Ok(Term::Constant(Constant::Bool(true))),
+                    "false" => Ok(Term::Constant(Constant::Bool(false))),
+                    "_" => Ok(Term::Wildcard),
+                    _ if self.is_punct("(") => {
+                        let variant = variant_name(&ident)?;
+                        let args = self.parenthesized(Self::term)?;
+                        Ok(Term::Node(variant, args))
+                    }
+                    _ => Ok(Term::Var(ident)),
+                }
+            }
+            Some(Token::Punct("[")) => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                let mut tail = None;
+                while !self.eat_punct("]") {
+                    if tail.is_some() {
+                        return Err(String::from("`..` must come last in a list"));
+                    }
+                    if self.eat_punct("..") {
+                        tail = Some(match self.peek() {
+                            Some(Token::Ident(_)) => Some(self.ident()?),
+                            _ => None,
+                        });
+                    } else {
+                        items.push(self.term()?);
+                    }
+                    if !self.is_punct("]") {
+                        self.expect_punct(",")?;
+                    }
+                }
+                Ok(Term::List(items, tail))
+            }
+            Some(Token::Punct("(")) => {
+                self.pos += 1;
+                let term = self.term()?;
+                self.expect_punct(")")?;
+                Ok(term)
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    fn guard(&mut self) -> Result<Guard, String> {
+        let mut guard = self.guard_conjunction()?;
+        while self.eat_punct("||") {
+            guard = Guard::Or(Box::new(guard), Box::new(self.guard_conjunction()?));
+        }
+        Ok(guard)
+    }
+
+    fn guard_conjunction(&mut self) -> Result<Guard, String> {
+        let mut guard = self.guard_atom()?;
+        while self.eat_punct("&&") {
+            guard = Guard::And(Box::new(guard), Box::new(self.guard_atom()?));
+        }
+        Ok(guard)
+    }
+
+    fn guard_atom(&mut self) -> Result<Guard, String> {
+        if self.eat_punct("!") {
+            return Ok(Guard::Not(Box::new(self.guard_atom()?)));
+        }
+        if self.eat_punct("(") {
+            let guard = self.guard()?;
+            self.expect_punct(")")?;
+            return Ok(guard);
+        }
+        let name = self.ident()?;
+        let predicate = match name.as_str() {
+            "can_be_undefined" => Predicate::CanBeUndefined,
+            "empty" => Predicate::Empty,
+            _ => match name.strip_prefix("is_") {
+                Some(variant) => Predicate::IsVariant(variant_name(variant)?),
+                None => return Err(format!("unknown guard `{}`", name)),
+            },
+        };
+        self.expect_punct("(")?;
+        let var = self.ident()?;
+        self.expect_punct(")")?;
+        Ok(Guard::Test(predicate, var))
+    }
+}
+
+/// `sum_leq` -> `"SumLeq"`, as spelled in `Expression::VARIANTS`.
+fn variant_name(snake_case: &str) -> Result<&'static str, String> {
+    let camel_case: String = snake_case
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    Expression::VARIANTS
+        .iter()
+        .find(|variant| **variant == camel_case)
+        .copied()
+        .ok_or_else(|| format!("unknown expression `{}`", snake_case))
+}
+
+fn parse_rule_file(source: &str) -> Result<(Vec<RuleSetDecl>, Vec<RuleDecl>), RuleFileError> {
+    let mut rule_sets = Vec::new();
+    let mut rules: Vec<RuleDecl> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let syntax_error = |message| RuleFileError::Syntax {
+            line: index + 1,
+            message,
+        };
+
+        let tokens = tokenize(line).map_err(syntax_error)?;
+        if tokens.is_empty() {
+            continue;
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+
+        if parser.is_ident("rule_set") {
+            parser.pos += 1;
+            rule_sets.push(parser.rule_set().map_err(syntax_error)?);
+        } else if parser.is_ident("rule") {
+            if let Some(rule) = rules.last() {
+                if rule.arms.is_empty() {
+                    return Err(syntax_error(format!("rule `{}` has no arms", rule.name)));
+                }
+            }
+            parser.pos += 1;
+            rules.push(parser.rule_header().map_err(syntax_error)?);
+        } else {
+            let Some(rule) = rules.last_mut() else {
+                return Err(syntax_error(String::from(
+                    "expected `rule_set` or `rule` before the first arm",
+                )));
+            };
+            rule.arms.push(parser.arm().map_err(syntax_error)?);
+            rule.doc.push(line.trim().to_string());
+        }
+    }
+
+    if let Some(rule) = rules.last() {
+        if rule.arms.is_empty() {
+            return Err(RuleFileError::Syntax {
+                line: source.lines().count(),
+                message: format!("rule `{}` has no arms", rule.name),
+            });
+        }
+    }
+
+    Ok((rule_sets, rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syntax_error(source: &str) -> String {
+        match parse_rule_file(source) {
+            Err(RuleFileError::Syntax { message, .. }) => message,
+            Err(e) => panic!("expected a syntax error, got {}", e),
+            Ok(_) => panic!("expected a syntax error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_rule_file() {
+        let (rule_sets, rules) = parse_rule_file(
+            r#"
+            # A comment
+            rule_set "Experimental", 120, ("Base"), (Minion)
+
+            rule negated_lt ("Experimental", 10), ("Base", 5):
+                !(a < b) => a >= b
+                !(a <= b) if !can_be_undefined(a) => a > b # trailing comment
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rule_sets.len(), 1);
+        assert_eq!(rule_sets[0].name, "Experimental");
+        assert_eq!(rule_sets[0].order, 120);
+        assert_eq!(rule_sets[0].dependencies, vec!["Base"]);
+        assert_eq!(rule_sets[0].solver_families, vec![SolverFamily::Minion]);
+
+        assert_eq!(rules.len(), 1);
+        assert_eq!(
+            rules[0].rule_sets,
+            vec![
+                (String::from("Experimental"), 10),
+                (String::from("Base"), 5)
+            ]
+        );
+        assert_eq!(rules[0].arms.len(), 2);
+        assert!(rules[0].arms[1].guard.is_some());
+    }
+
+    #[test]
+    fn test_rule_file_errors() {
+        assert_eq!(
+            syntax_error("!!x => x"),
+            "expected `rule_set` or `rule` before the first arm"
+        );
+        assert_eq!(
+            syntax_error("rule r (\"Base\", 1):\n  frobnicate(x) => x"),
+            "unknown expression `frobnicate`"
+        );
+        assert_eq!(
+            syntax_error("rule r (\"Base\", 1):\n  x == x => x"),
+            "`x` is bound more than once in this pattern"
+        );
+        assert_eq!(
+            syntax_error("rule r (\"Base\", 1):\n  !x => y"),
+            "`y` is not bound by the pattern"
+        );
+        assert_eq!(
+            syntax_error("rule r (\"Base\", 1):\n  sum(xs) => xs"),
+            "`xs` is bound to a list, but used as an expression"
+        );
+        assert_eq!(
+            syntax_error("rule r (\"Base\", 1):\n  not(a, b) => a"),
+            "`Not` takes 1 argument(s), found 2"
+        );
+        assert_eq!(
+            syntax_error("rule r (\"Base\", 1):"),
+            "rule `r` has no arms"
+        );
+    }
+
+    #[test]
+    fn test_rule_file_arms() {
+        let (_, rules) = parse_rule_file(
+            "rule r (\"Base\", 1):\n  and([true, x, ..rest]) if !empty(rest) => or([!x, ..rest])",
+        )
+        .unwrap();
+        let rule = rules.into_iter().next().unwrap().into_rule();
+        assert_eq!(rule.applicable_variants, Some(&["And"][..]));
+
+        let var = |name: &str| {
+            Expression::Reference(
+                Metadata::new(),
+                crate::ast::Name::UserName(String::from(name)),
+            )
+        };
+        let t = Expression::Constant(Metadata::new(), Constant::Bool(true));
+        let model = Model::new_empty(Default::default());
+
+        let expr = Expression::And(
+            Metadata::new(),
+            Arc::new(vec![t.clone(), var("a"), var("b")]),
+        );
+        assert_eq!(
+            rule.apply(&expr, &model).unwrap().new_expression,
+            Expression::Or(
+                Metadata::new(),
+                Arc::new(vec![
+                    Expression::Not(Metadata::new(), Arc::new(var("a"))),
+                    var("b")
+                ])
+            )
+        );
+
+        let expr = Expression::And(Metadata::new(), Arc::new(vec![t, var("a")]));
+        assert!(rule.apply(&expr, &model).is_err());
+    }
+
+    #[test]
+    fn test_rule_file_keeps_root_metadata() {
+        let (_, rules) = parse_rule_file("rule r (\"Base\", 1):\n  !(a < b) => a >= b").unwrap();
+        let rule = rules.into_iter().next().unwrap().into_rule();
+
+        let metadata = Metadata {
+            clean: true,
+            etype: Some(crate::ast::ReturnType::Bool),
+        };
+        let int = |i: i32| Arc::new(Expression::Constant(Metadata::new(), Constant::Int(i)));
+        let expr = Expression::Not(
+            metadata,
+            Arc::new(Expression::Lt(Metadata::new(), int(1), int(2))),
+        );
+        let new = rule
+            .apply(&expr, &Model::new_empty(Default::default()))
+            .unwrap()
+            .new_expression;
+        let Expression::Geq(metadata, _, _) = new else {
+            panic!("expected a Geq, got {:?}", new);
+        };
+        assert_eq!(metadata.etype, Some(crate::ast::ReturnType::Bool));
+        assert!(!metadata.clean);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::rule_engine::{get_rule_by_name, get_rules, ResolveRulesError, Rule};
+
+/// Changes to the rules picked by the enabled rule sets, e.g. from the command line.
+///
+/// Overrides are applied in order: priorities first, then `only_rules`, then `disabled_rules`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleOverrides {
+    /// Rules that are never applied.
+    pub disabled_rules: Vec<String>,
+    /// If not empty, only rules whose names match one of these patterns are applied.
+    /// In a pattern, `*` matches any sequence of characters.
+    pub only_rules: Vec<String>,
+    /// Rules to apply with the given priority. A rule given a priority is applied even if none
+    /// of its rule sets are enabled.
+    pub priorities: BTreeMap<String, u8>,
+}
+
+impl RuleOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.disabled_rules.is_empty() && self.only_rules.is_empty() && self.priorities.is_empty()
+    }
+
+    /// Apply the overrides to a map of rules to their priorities, as returned by
+    /// [`get_rule_priorities`](crate::rule_engine::get_rule_priorities).
+    ///
+    /// # Returns
+    /// - The new map of rules to their priorities
+    /// - `ResolveRulesError::RuleNotFound` if a disabled or reprioritised rule does not exist
+    /// - `ResolveRulesError::NoRulesMatch` if a pattern in `only_rules` matches no rule
+    pub fn apply<'a>(
+        &self,
+        mut rule_priorities: HashMap<&'a Rule<'a>, u8>,
+    ) -> Result<HashMap<&'a Rule<'a>, u8>, ResolveRulesError> {
+        for (name, priority) in &self.priorities {
+            let rule = get_rule_by_name(name)
+                .ok_or_else(|| ResolveRulesError::RuleNotFound(name.clone()))?;
+            rule_priorities.insert(rule, *priority);
+        }
+
+        if !self.only_rules.is_empty() {
+            let rules = get_rules();
+            for pattern in &self.only_rules {
+                if !rules.iter().any(|rule| matches_pattern(pattern, rule.name)) {
+                    return Err(ResolveRulesError::NoRulesMatch(pattern.clone()));
+                }
+            }
+            rule_priorities.retain(|rule, _| {
+                self.only_rules
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, rule.name))
+            });
+        }
+
+        for name in &self.disabled_rules {
+            let rule = get_rule_by_name(name)
+                .ok_or_else(|| ResolveRulesError::RuleNotFound(name.clone()))?;
+            rule_priorities.remove(rule);
+        }
+
+        Ok(rule_priorities)
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` in the pattern matches any sequence of characters.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // `split` always yields at least one part
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` in the pattern
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches_pattern;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("lt_to_ineq", "lt_to_ineq"));
+        assert!(!matches_pattern("lt_to_ineq", "lt_to_ineq2"));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("*_to_ineq", "geq_to_ineq"));
+        assert!(matches_pattern("sum_*", "sum_constants"));
+        assert!(!matches_pattern("sum_*", "unwrap_sum"));
+        assert!(matches_pattern("*not*", "distribute_not_over_or"));
+        assert!(matches_pattern("a*b*b", "abb"));
+        assert!(!matches_pattern("a*b*b", "ab"));
+    }
+}
@@ -3,9 +3,7 @@ use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::sync::OnceLock;
 
-use log::warn;
-
-use crate::rule_engine::{get_rule_set_by_name, get_rules, Rule};
+use crate::rule_engine::{get_rule_set_by_name, get_rules, ResolveRulesError, Rule};
 use crate::solver::SolverFamily;
 
 /// A set of rules with a name, priority, and dependencies.
@@ -20,7 +18,7 @@ pub struct RuleSet<'a> {
     rules: OnceLock<HashMap<&'a Rule<'a>, u8>>,
     /// The names of the rule sets that this rule set depends on.
     dependency_rs_names: &'a [&'a str],
-    dependencies: OnceLock<HashSet<&'a RuleSet<'a>>>,
+    dependencies: OnceLock<Result<HashSet<&'a RuleSet<'a>>, ResolveRulesError>>,
     /// The solver families that this rule set applies to.
     pub solver_families: &'a [SolverFamily],
 }
@@ -59,10 +57,17 @@ impl<'a> RuleSet<'a> {
         }
     }
 
+    /// Whether the rules of this rule set have already been collected.
+    /// Rules registered after this point will not be part of the rule set.
+    pub(crate) fn rules_resolved(&self) -> bool {
+        self.rules.get().is_some()
+    }
+
     /// Get the dependencies of this rule set, evaluating them lazily if necessary
-    /// Returns a `&HashSet<&RuleSet>` of the rule sets that this rule set depends on.
+    /// Returns a `&HashSet<&RuleSet>` of the rule sets that this rule set depends on, or an error if
+    /// a dependency does not exist or the dependencies form a cycle.
     #[allow(clippy::mutable_key_type)] // RuleSet is 'static so it's fine
-    pub fn get_dependencies(&self) -> &HashSet<&'static RuleSet> {
+    pub fn get_dependencies(&self) -> Result<&HashSet<&'static RuleSet>, ResolveRulesError> {
         match self.dependencies.get() {
             None => {
                 let dependencies = self.resolve_dependencies();
@@ -71,18 +76,27 @@ impl<'a> RuleSet<'a> {
                 // At this point, the dependencies cell is guaranteed to be set, so we can unwrap safely.
                 // see: https://doc.rust-lang.org/stable/std/sync/struct.OnceLock.html#method.set
                 #[allow(clippy::unwrap_used)]
-                self.dependencies.get().unwrap()
+                self.dependencies
+                    .get()
+                    .unwrap()
+                    .as_ref()
+                    .map_err(Clone::clone)
             }
-            Some(dependencies) => dependencies,
+            Some(dependencies) => dependencies.as_ref().map_err(Clone::clone),
         }
     }
 
     /// Get the dependencies of this rule set, including itself
     #[allow(clippy::mutable_key_type)] // RuleSet is 'static so it's fine
-    pub fn with_dependencies(&self) -> HashSet<&'static RuleSet> {
-        let mut deps = self.get_dependencies().clone();
+    pub fn with_dependencies(&self) -> Result<HashSet<&'static RuleSet>, ResolveRulesError> {
+        let mut deps = self.get_dependencies()?.clone();
         deps.insert(self);
-        deps
+        Ok(deps)
+    }
+
+    /// The names of the rule sets that this rule set directly depends on.
+    pub fn dependency_names(&self) -> &[&'a str] {
+        self.dependency_rs_names
     }
 
     /// Resolve the rules of this rule set ("reverse the arrows")
@@ -111,28 +125,41 @@ impl<'a> RuleSet<'a> {
 
     /// Recursively resolve the dependencies of this rule set.
     #[allow(clippy::mutable_key_type)] // RuleSet is 'static so it's fine
-    fn resolve_dependencies(&self) -> HashSet<&'static RuleSet> {
+    fn resolve_dependencies(&self) -> Result<HashSet<&'static RuleSet>, ResolveRulesError> {
         let mut dependencies = HashSet::new();
+        self.collect_dependencies(&mut vec![self.name], &mut dependencies)?;
+        Ok(dependencies)
+    }
 
+    /// Depth-first search through the dependencies of this rule set.
+    /// `path` holds the rule sets being visited, from the one being resolved down to this one.
+    #[allow(clippy::mutable_key_type)] // RuleSet is 'static so it's fine
+    fn collect_dependencies<'b>(
+        &'b self,
+        path: &mut Vec<&'b str>,
+        dependencies: &mut HashSet<&'static RuleSet>,
+    ) -> Result<(), ResolveRulesError> {
         for dep in self.dependency_rs_names {
-            match get_rule_set_by_name(dep) {
-                None => {
-                    warn!(
-                        "Rule set {} depends on non-existent rule set {}",
-                        &self.name, dep
-                    );
-                }
-                Some(rule_set) => {
-                    if !dependencies.contains(rule_set) {
-                        // Prevent cycles
-                        dependencies.insert(rule_set);
-                        dependencies.extend(rule_set.resolve_dependencies());
-                    }
-                }
+            let rule_set =
+                get_rule_set_by_name(dep).ok_or_else(|| ResolveRulesError::DependencyNotFound {
+                    rule_set: self.name.to_string(),
+                    dependency: dep.to_string(),
+                })?;
+
+            if let Some(start) = path.iter().position(|name| *name == rule_set.name) {
+                let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+                cycle.push(rule_set.name.to_string());
+                return Err(ResolveRulesError::DependencyCycle(cycle));
+            }
+
+            if dependencies.insert(rule_set) {
+                path.push(rule_set.name);
+                rule_set.collect_dependencies(path, dependencies)?;
+                path.pop();
             }
         }
 
-        dependencies
+        Ok(())
     }
 }
 
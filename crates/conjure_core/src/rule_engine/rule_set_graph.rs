@@ -0,0 +1,126 @@
+use std::fmt::{Display, Formatter};
+
+use crate::rule_engine::{get_rule_sets, ResolveRulesError};
+use crate::solver::SolverFamily;
+
+/// The registered rule sets and the dependencies between them.
+#[derive(Clone, Debug)]
+pub struct RuleSetGraph {
+    /// The rule sets, sorted by name.
+    pub nodes: Vec<RuleSetNode>,
+}
+
+/// A rule set in a [`RuleSetGraph`].
+#[derive(Clone, Debug)]
+pub struct RuleSetNode {
+    pub name: String,
+    pub order: u8,
+    /// The solver families the rule set is enabled for by default.
+    pub solver_families: Vec<SolverFamily>,
+    /// The number of rules in the rule set, not counting its dependencies.
+    pub rule_count: usize,
+    /// The names of the rule sets this one directly depends on, sorted.
+    pub dependencies: Vec<String>,
+}
+
+/// Build the graph of all registered rule sets.
+///
+/// # Returns
+/// - The graph, or the first error found while resolving the dependencies of a rule set.
+pub fn rule_set_graph() -> Result<RuleSetGraph, ResolveRulesError> {
+    let mut rule_sets = get_rule_sets();
+    rule_sets.sort_by_key(|rs| rs.name);
+
+    let mut nodes = Vec::new();
+    for rule_set in rule_sets {
+        rule_set.get_dependencies()?;
+
+        let mut dependencies: Vec<String> = rule_set
+            .dependency_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+
+        nodes.push(RuleSetNode {
+            name: rule_set.name.to_string(),
+            order: rule_set.order,
+            solver_families: rule_set.solver_families.to_vec(),
+            rule_count: rule_set.get_rules().len(),
+            dependencies,
+        });
+    }
+
+    Ok(RuleSetGraph { nodes })
+}
+
+impl RuleSetGraph {
+    /// Render the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph rule_sets {\n");
+        for node in &self.nodes {
+            let mut label = format!("{}\\norder {}", escape(&node.name), node.order);
+            if !node.solver_families.is_empty() {
+                label.push_str(&format!("\\n{}", solver_families(node)));
+            }
+            label.push_str(&format!("\\n{} rules", node.rule_count));
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\"];\n",
+                escape(&node.name),
+                label
+            ));
+        }
+        for node in &self.nodes {
+            for dependency in &node.dependencies {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    escape(&node.name),
+                    escape(dependency)
+                ));
+            }
+        }
+        dot.push('}');
+        dot
+    }
+}
+
+/// One line per rule set, e.g. `Minion: order 100, 12 rules, solvers [Minion], depends on [Base]`.
+impl Display for RuleSetGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for node in &self.nodes {
+            writeln!(f, "{}", node)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for RuleSetNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: order {}, {} rules",
+            self.name, self.order, self.rule_count
+        )?;
+        if !self.solver_families.is_empty() {
+            write!(f, ", solvers [{}]", solver_families(self))?;
+        }
+        if !self.dependencies.is_empty() {
+            write!(f, ", depends on [{}]", self.dependencies.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn solver_families(node: &RuleSetNode) -> String {
+    node.solver_families
+        .iter()
+        .map(|f| f.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Escape a name for use inside a quoted DOT string.
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
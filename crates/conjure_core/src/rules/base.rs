@@ -3,9 +3,10 @@ use conjure_core::ast::{
 };
 use conjure_core::metadata::Metadata;
 use conjure_core::rule_engine::{
-    register_rule, register_rule_set, ApplicationError, ApplicationResult, Reduction,
+    pattern_rule, register_rule, register_rule_set, ApplicationError, ApplicationResult, Reduction,
 };
 use conjure_core::Model;
+use std::sync::Arc;
 use uniplate::uniplate::Uniplate;
 
 /*****************************************************************************/
@@ -23,7 +24,7 @@ register_rule_set!("Base", 150, ());
  * ...
  * ```
 */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(And, Or, Sum, SumEq, SumLeq, SumGeq))]
 fn remove_nothings(expr: &Expr, _: &Model) -> ApplicationResult {
     fn remove_nothings(exprs: Vec<Expr>) -> Result<Vec<Expr>, ApplicationError> {
         let mut changed = false;
@@ -45,33 +46,33 @@ fn remove_nothings(expr: &Expr, _: &Model) -> ApplicationResult {
         }
     }
 
-    fn get_lhs_rhs(sub: Vec<Expr>) -> (Vec<Expr>, Box<Expr>) {
+    fn get_lhs_rhs(sub: Vec<Expr>) -> (Vec<Expr>, Arc<Expr>) {
         if sub.is_empty() {
-            return (Vec::new(), Box::new(Expr::Nothing));
+            return (Vec::new(), Arc::new(Expr::Nothing));
         }
 
         let lhs = sub[..(sub.len() - 1)].to_vec();
-        let rhs = Box::new(sub[sub.len() - 1].clone());
+        let rhs = Arc::new(sub[sub.len() - 1].clone());
         (lhs, rhs)
     }
 
     let new_sub = remove_nothings(expr.children())?;
 
     match expr {
-        Expr::And(md, _) => Ok(Reduction::pure(Expr::And(md.clone(), new_sub))),
-        Expr::Or(md, _) => Ok(Reduction::pure(Expr::Or(md.clone(), new_sub))),
-        Expr::Sum(md, _) => Ok(Reduction::pure(Expr::Sum(md.clone(), new_sub))),
+        Expr::And(md, _) => Ok(Reduction::pure(Expr::And(md.clone(), new_sub.into()))),
+        Expr::Or(md, _) => Ok(Reduction::pure(Expr::Or(md.clone(), new_sub.into()))),
+        Expr::Sum(md, _) => Ok(Reduction::pure(Expr::Sum(md.clone(), new_sub.into()))),
         Expr::SumEq(md, _, _) => {
             let (lhs, rhs) = get_lhs_rhs(new_sub);
-            Ok(Reduction::pure(Expr::SumEq(md.clone(), lhs, rhs)))
+            Ok(Reduction::pure(Expr::SumEq(md.clone(), lhs.into(), rhs)))
         }
         Expr::SumLeq(md, _lhs, _rhs) => {
             let (lhs, rhs) = get_lhs_rhs(new_sub);
-            Ok(Reduction::pure(Expr::SumLeq(md.clone(), lhs, rhs)))
+            Ok(Reduction::pure(Expr::SumLeq(md.clone(), lhs.into(), rhs)))
         }
         Expr::SumGeq(md, _lhs, _rhs) => {
             let (lhs, rhs) = get_lhs_rhs(new_sub);
-            Ok(Reduction::pure(Expr::SumGeq(md.clone(), lhs, rhs)))
+            Ok(Reduction::pure(Expr::SumGeq(md.clone(), lhs.into(), rhs)))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
@@ -105,14 +106,14 @@ fn empty_to_nothing(expr: &Expr, _: &Model) -> ApplicationResult {
  * sum([1, 2, 3]) = 6
  * ```
  */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(Sum))]
 fn sum_constants(expr: &Expr, _: &Model) -> ApplicationResult {
     match expr {
         Expr::Sum(_, exprs) => {
             let mut sum = 0;
             let mut new_exprs = Vec::new();
             let mut changed = false;
-            for e in exprs {
+            for e in exprs.iter() {
                 match e {
                     Expr::Constant(_metadata, Const::Int(i)) => {
                         sum += i;
@@ -126,23 +127,25 @@ fn sum_constants(expr: &Expr, _: &Model) -> ApplicationResult {
             }
             // TODO (kf77): Get existing metadata instead of creating a new one
             new_exprs.push(Expr::Constant(Metadata::new(), Const::Int(sum)));
-            Ok(Reduction::pure(Expr::Sum(Metadata::new(), new_exprs))) // Let other rules handle only one Expr being contained in the sum
+            Ok(Reduction::pure(Expr::Sum(
+                Metadata::new(),
+                new_exprs.into(),
+            ))) // Let other rules handle only one Expr being contained in the sum
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
 }
 
-/**
- * Unwrap trivial sums:
- * ```text
- * sum([a]) = a
- * ```
- */
-#[register_rule(("Base", 100))]
-fn unwrap_sum(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Sum(_, exprs) if (exprs.len() == 1) => Ok(Reduction::pure(exprs[0].clone())),
-        _ => Err(ApplicationError::RuleNotApplicable),
+pattern_rule! {
+    /**
+     * Unwrap trivial sums:
+     * ```text
+     * sum([a]) = a
+     * ```
+     */
+    #[register_rule(("Base", 100))]
+    fn unwrap_sum {
+        sum([x]) => x,
     }
 }
 
@@ -152,17 +155,17 @@ fn unwrap_sum(expr: &Expr, _: &Model) -> ApplicationResult {
  * sum(sum(a, b), c) = sum(a, b, c)
  * ```
  */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(Sum))]
 pub fn flatten_nested_sum(expr: &Expr, _: &Model) -> ApplicationResult {
     match expr {
         Expr::Sum(metadata, exprs) => {
             let mut new_exprs = Vec::new();
             let mut changed = false;
-            for e in exprs {
+            for e in exprs.iter() {
                 match e {
                     Expr::Sum(_, sub_exprs) => {
                         changed = true;
-                        for e in sub_exprs {
+                        for e in sub_exprs.iter() {
                             new_exprs.push(e.clone());
                         }
                     }
@@ -174,7 +177,7 @@ pub fn flatten_nested_sum(expr: &Expr, _: &Model) -> ApplicationResult {
             }
             Ok(Reduction::pure(Expr::Sum(
                 metadata.clone_dirty(),
-                new_exprs,
+                new_exprs.into(),
             )))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
@@ -188,17 +191,17 @@ pub fn flatten_nested_sum(expr: &Expr, _: &Model) -> ApplicationResult {
 * or(or(a, b), c) = or(a, b, c)
 * ```
  */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(Or))]
 fn unwrap_nested_or(expr: &Expr, _: &Model) -> ApplicationResult {
     match expr {
         Expr::Or(metadata, exprs) => {
             let mut new_exprs = Vec::new();
             let mut changed = false;
-            for e in exprs {
+            for e in exprs.iter() {
                 match e {
                     Expr::Or(_, exprs) => {
                         changed = true;
-                        for e in exprs {
+                        for e in exprs.iter() {
                             new_exprs.push(e.clone());
                         }
                     }
@@ -208,7 +211,10 @@ fn unwrap_nested_or(expr: &Expr, _: &Model) -> ApplicationResult {
             if !changed {
                 return Err(ApplicationError::RuleNotApplicable);
             }
-            Ok(Reduction::pure(Expr::Or(metadata.clone_dirty(), new_exprs)))
+            Ok(Reduction::pure(Expr::Or(
+                metadata.clone_dirty(),
+                new_exprs.into(),
+            )))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
@@ -221,17 +227,17 @@ fn unwrap_nested_or(expr: &Expr, _: &Model) -> ApplicationResult {
 * and(and(a, b), c) = and(a, b, c)
 * ```
  */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(And))]
 fn unwrap_nested_and(expr: &Expr, _: &Model) -> ApplicationResult {
     match expr {
         Expr::And(metadata, exprs) => {
             let mut new_exprs = Vec::new();
             let mut changed = false;
-            for e in exprs {
+            for e in exprs.iter() {
                 match e {
                     Expr::And(_, exprs) => {
                         changed = true;
-                        for e in exprs {
+                        for e in exprs.iter() {
                             new_exprs.push(e.clone());
                         }
                     }
@@ -243,66 +249,46 @@ fn unwrap_nested_and(expr: &Expr, _: &Model) -> ApplicationResult {
             }
             Ok(Reduction::pure(Expr::And(
                 metadata.clone_dirty(),
-                new_exprs,
+                new_exprs.into(),
             )))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
 }
 
-/**
-* Remove double negation:
-
-* ```text
-* not(not(a)) = a
-* ```
- */
-#[register_rule(("Base", 100))]
-fn remove_double_negation(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Not(_, contents) => match contents.as_ref() {
-            Expr::Not(_, expr_box) => Ok(Reduction::pure(*expr_box.clone())),
-            _ => Err(ApplicationError::RuleNotApplicable),
-        },
-        _ => Err(ApplicationError::RuleNotApplicable),
+pattern_rule! {
+    /**
+    * Remove double negation:
+
+    * ```text
+    * not(not(a)) = a
+    * ```
+     */
+    #[register_rule(("Base", 100))]
+    fn remove_double_negation {
+        !!x => x,
     }
-}
 
-/**
- * Remove trivial `and` (only one element):
- * ```text
- * and([a]) = a
- * ```
- */
-#[register_rule(("Base", 100))]
-fn remove_trivial_and(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::And(_, exprs) => {
-            if exprs.len() == 1 {
-                return Ok(Reduction::pure(exprs[0].clone()));
-            }
-            Err(ApplicationError::RuleNotApplicable)
-        }
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+     * Remove trivial `and` (only one element):
+     * ```text
+     * and([a]) = a
+     * ```
+     */
+    #[register_rule(("Base", 100))]
+    fn remove_trivial_and {
+        and([x]) => x,
     }
-}
 
-/**
- * Remove trivial `or` (only one element):
- * ```text
- * or([a]) = a
- * ```
- */
-#[register_rule(("Base", 100))]
-fn remove_trivial_or(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Or(_, exprs) => {
-            if exprs.len() == 1 {
-                return Ok(Reduction::pure(exprs[0].clone()));
-            }
-            Err(ApplicationError::RuleNotApplicable)
-        }
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+     * Remove trivial `or` (only one element):
+     * ```text
+     * or([a]) = a
+     * ```
+     */
+    #[register_rule(("Base", 100))]
+    fn remove_trivial_or {
+        or([x]) => x,
     }
 }
 
@@ -313,13 +299,13 @@ fn remove_trivial_or(expr: &Expr, _: &Model) -> ApplicationResult {
  * or([false, a]) = a
  * ```
  */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(Or))]
 fn remove_constants_from_or(expr: &Expr, _: &Model) -> ApplicationResult {
     match expr {
         Expr::Or(metadata, exprs) => {
             let mut new_exprs = Vec::new();
             let mut changed = false;
-            for e in exprs {
+            for e in exprs.iter() {
                 match e {
                     Expr::Constant(metadata, Const::Bool(val)) => {
                         if *val {
@@ -339,7 +325,10 @@ fn remove_constants_from_or(expr: &Expr, _: &Model) -> ApplicationResult {
             if !changed {
                 return Err(ApplicationError::RuleNotApplicable);
             }
-            Ok(Reduction::pure(Expr::Or(metadata.clone_dirty(), new_exprs)))
+            Ok(Reduction::pure(Expr::Or(
+                metadata.clone_dirty(),
+                new_exprs.into(),
+            )))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
@@ -352,13 +341,13 @@ fn remove_constants_from_or(expr: &Expr, _: &Model) -> ApplicationResult {
  * and([false, a]) = false
  * ```
  */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(And))]
 fn remove_constants_from_and(expr: &Expr, _: &Model) -> ApplicationResult {
     match expr {
         Expr::And(metadata, exprs) => {
             let mut new_exprs = Vec::new();
             let mut changed = false;
-            for e in exprs {
+            for e in exprs.iter() {
                 match e {
                     Expr::Constant(metadata, Const::Bool(val)) => {
                         if !*val {
@@ -380,31 +369,25 @@ fn remove_constants_from_and(expr: &Expr, _: &Model) -> ApplicationResult {
             }
             Ok(Reduction::pure(Expr::And(
                 metadata.clone_dirty(),
-                new_exprs,
+                new_exprs.into(),
             )))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
 }
 
-/**
- * Evaluate Not expressions with constant bools
- * ```text
- * not(true) = false
- * not(false) = true
- * ```
- */
-#[register_rule(("Base", 100))]
-fn evaluate_constant_not(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Not(_, contents) => match contents.as_ref() {
-            Expr::Constant(metadata, Const::Bool(val)) => Ok(Reduction::pure(Expr::Constant(
-                metadata.clone_dirty(),
-                Const::Bool(!val),
-            ))),
-            _ => Err(ApplicationError::RuleNotApplicable),
-        },
-        _ => Err(ApplicationError::RuleNotApplicable),
+pattern_rule! {
+    /**
+     * Evaluate Not expressions with constant bools
+     * ```text
+     * not(true) = false
+     * not(false) = true
+     * ```
+     */
+    #[register_rule(("Base", 100))]
+    fn evaluate_constant_not {
+        !true => false,
+        !false => true,
     }
 }
 
@@ -413,28 +396,30 @@ fn evaluate_constant_not(expr: &Expr, _: &Model) -> ApplicationResult {
  * ```text
  * min([a, b]) ~> c ; c <= a & c <= b & (c = a | c = b)
  * ```
+ * If a min equal to this one (up to the order of its operands) was already replaced, its variable is reused
+ * (see [`Reduction::define_aux_var`]).
  */
-#[register_rule(("Base", 100))]
-fn min_to_var(expr: &Expr, mdl: &Model) -> ApplicationResult {
+#[register_rule(("Base", 100), applies_to(Min))]
+pub(super) fn min_to_var(expr: &Expr, mdl: &Model) -> ApplicationResult {
     match expr {
         Expr::Min(metadata, exprs) => {
             let new_name = mdl.gensym();
 
             let mut new_top = Vec::new(); // the new variable must be less than or equal to all the other variables
             let mut disjunction = Vec::new(); // the new variable must be equal to one of the variables
-            for e in exprs {
+            for e in exprs.iter() {
                 new_top.push(Expr::Leq(
                     Metadata::new(),
-                    Box::new(Expr::Reference(Metadata::new(), new_name.clone())),
-                    Box::new(e.clone()),
+                    Arc::new(Expr::Reference(Metadata::new(), new_name.clone())),
+                    Arc::new(e.clone()),
                 ));
                 disjunction.push(Expr::Eq(
                     Metadata::new(),
-                    Box::new(Expr::Reference(Metadata::new(), new_name.clone())),
-                    Box::new(e.clone()),
+                    Arc::new(Expr::Reference(Metadata::new(), new_name.clone())),
+                    Arc::new(e.clone()),
                 ));
             }
-            new_top.push(Expr::Or(Metadata::new(), disjunction));
+            new_top.push(Expr::Or(Metadata::new(), disjunction.into()));
 
             let mut new_vars = SymbolTable::new();
             let domain = expr
@@ -443,10 +428,11 @@ fn min_to_var(expr: &Expr, mdl: &Model) -> ApplicationResult {
             new_vars.insert(new_name.clone(), DecisionVariable::new(domain));
 
             Ok(Reduction::new(
-                Expr::Reference(Metadata::new(), new_name),
-                Expr::And(metadata.clone_dirty(), new_top),
+                Expr::Reference(Metadata::new(), new_name.clone()),
+                Expr::And(metadata.clone_dirty(), new_top.into()),
                 new_vars,
-            ))
+            )
+            .define_aux_var(new_name, expr.clone()))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
@@ -459,7 +445,7 @@ fn min_to_var(expr: &Expr, mdl: &Model) -> ApplicationResult {
 * or(and(a, b), c) = and(or(a, c), or(b, c))
 * ```
  */
-#[register_rule(("Base", 100))]
+#[register_rule(("Base", 100), applies_to(Or))]
 fn distribute_or_over_and(expr: &Expr, _: &Model) -> ApplicationResult {
     fn find_and(exprs: &[Expr]) -> Option<usize> {
         // ToDo: may be better to move this to some kind of utils module?
@@ -474,23 +460,24 @@ fn distribute_or_over_and(expr: &Expr, _: &Model) -> ApplicationResult {
     match expr {
         Expr::Or(_, exprs) => match find_and(exprs) {
             Some(idx) => {
-                let mut rest = exprs.clone();
+                let mut rest = exprs.as_ref().clone();
                 let and_expr = rest.remove(idx);
 
                 match and_expr {
                     Expr::And(metadata, and_exprs) => {
                         let mut new_and_contents = Vec::new();
 
-                        for e in and_exprs {
+                        for e in and_exprs.iter() {
                             // ToDo: Cloning everything may be a bit inefficient - discuss
                             let mut new_or_contents = rest.clone();
                             new_or_contents.push(e.clone());
-                            new_and_contents.push(Expr::Or(metadata.clone_dirty(), new_or_contents))
+                            new_and_contents
+                                .push(Expr::Or(metadata.clone_dirty(), new_or_contents.into()))
                         }
 
                         Ok(Reduction::pure(Expr::And(
                             metadata.clone_dirty(),
-                            new_and_contents,
+                            new_and_contents.into(),
                         )))
                     }
                     _ => Err(ApplicationError::RuleNotApplicable),
@@ -502,64 +489,37 @@ fn distribute_or_over_and(expr: &Expr, _: &Model) -> ApplicationResult {
     }
 }
 
-/**
-* Distribute `not` over `and` (De Morgan's Law):
-
-* ```text
-* not(and(a, b)) = or(not a, not b)
-* ```
- */
-#[register_rule(("Base", 100))]
-fn distribute_not_over_and(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Not(_, contents) => match contents.as_ref() {
-            Expr::And(metadata, exprs) => {
-                if exprs.len() == 1 {
-                    let single_expr = exprs[0].clone();
-                    return Ok(Reduction::pure(Expr::Not(
-                        Metadata::new(),
-                        Box::new(single_expr.clone()),
-                    )));
-                }
-                let mut new_exprs = Vec::new();
-                for e in exprs {
-                    new_exprs.push(Expr::Not(metadata.clone(), Box::new(e.clone())));
-                }
-                Ok(Reduction::pure(Expr::Or(metadata.clone(), new_exprs)))
-            }
-            _ => Err(ApplicationError::RuleNotApplicable),
-        },
-        _ => Err(ApplicationError::RuleNotApplicable),
-    }
+fn negate_all(exprs: &[Expr]) -> Vec<Expr> {
+    exprs
+        .iter()
+        .map(|e| Expr::Not(Metadata::new(), Arc::new(e.clone())))
+        .collect()
 }
 
-/**
-* Distribute `not` over `or` (De Morgan's Law):
+pattern_rule! {
+    /**
+    * Distribute `not` over `and` (De Morgan's Law):
+
+    * ```text
+    * not(and(a, b)) = or(not a, not b)
+    * ```
+     */
+    #[register_rule(("Base", 100))]
+    fn distribute_not_over_and {
+        !and([x]) => !x,
+        !and(xs) => or({ negate_all(xs) }),
+    }
 
-* ```text
-* not(or(a, b)) = and(not a, not b)
-* ```
- */
-#[register_rule(("Base", 100))]
-fn distribute_not_over_or(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Not(_, contents) => match contents.as_ref() {
-            Expr::Or(metadata, exprs) => {
-                if exprs.len() == 1 {
-                    let single_expr = exprs[0].clone();
-                    return Ok(Reduction::pure(Expr::Not(
-                        Metadata::new(),
-                        Box::new(single_expr.clone()),
-                    )));
-                }
-                let mut new_exprs = Vec::new();
-                for e in exprs {
-                    new_exprs.push(Expr::Not(metadata.clone(), Box::new(e.clone())));
-                }
-                Ok(Reduction::pure(Expr::And(metadata.clone(), new_exprs)))
-            }
-            _ => Err(ApplicationError::RuleNotApplicable),
-        },
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+    * Distribute `not` over `or` (De Morgan's Law):
+
+    * ```text
+    * not(or(a, b)) = and(not a, not b)
+    * ```
+     */
+    #[register_rule(("Base", 100))]
+    fn distribute_not_over_or {
+        !or([x]) => !x,
+        !or(xs) => and({ negate_all(xs) }),
     }
 }
@@ -4,31 +4,32 @@ use conjure_core::rule_engine::{
     register_rule, register_rule_set, ApplicationError, ApplicationResult, Reduction,
 };
 use conjure_core::Model;
+use std::sync::Arc;
 use uniplate::uniplate::Uniplate;
 
 register_rule_set!("Bubble", 254, ("Base"));
 
 // Bubble reduction rules
 
-/*
+/**
     Reduce bubbles with a boolean expression to a conjunction with their condition.
 
     e.g. (a / b = c) @ (b != 0) => (a / b = c) & (b != 0)
 */
-#[register_rule(("Bubble", 100))]
+#[register_rule(("Bubble", 100), applies_to(Bubble))]
 fn expand_bubble(expr: &Expression, _: &Model) -> ApplicationResult {
     match expr {
         Expression::Bubble(_, a, b) if a.return_type() == Some(ReturnType::Bool) => {
             Ok(Reduction::pure(Expression::And(
                 Metadata::new(),
-                vec![*a.clone(), *b.clone()],
+                vec![a.as_ref().clone(), b.as_ref().clone()].into(),
             )))
         }
         _ => Err(ApplicationError::RuleNotApplicable),
     }
 }
 
-/*
+/**
     Bring bubbles with a non-boolean expression higher up the tree.
 
     E.g. ((a / b) @ (b != 0)) = c => (a / b = c) @ (b != 0)
@@ -40,8 +41,8 @@ fn bubble_up(expr: &Expression, _: &Model) -> ApplicationResult {
     for e in sub.iter_mut() {
         if let Expression::Bubble(_, a, b) = e {
             if a.return_type() != Some(ReturnType::Bool) {
-                bubbled_conditions.push(*b.clone());
-                *e = *a.clone();
+                bubbled_conditions.push(b.as_ref().clone());
+                *e = a.as_ref().clone();
             }
         }
     }
@@ -50,37 +51,41 @@ fn bubble_up(expr: &Expression, _: &Model) -> ApplicationResult {
     }
     return Ok(Reduction::pure(Expression::Bubble(
         Metadata::new(),
-        Box::new(
+        Arc::new(
             expr.with_children(sub)
                 .or(Err(ApplicationError::RuleNotApplicable))?,
         ),
-        Box::new(Expression::And(Metadata::new(), bubbled_conditions)),
+        Arc::new(Expression::And(Metadata::new(), bubbled_conditions.into())),
     )));
 }
 
 // Bubble applications
 
-/*
-    Convert an unsafe division to a safe division with a bubble condition.
+/**
+    Convert a partial operator to its total counterpart with a bubble condition saying when it is defined.
+
+    Essence uses relational semantics: a boolean expression is false wherever one of its sub-expressions is
+    undefined. The condition is brought up the tree by `bubble_up` and expanded into a conjunction with the
+    first boolean-type expression it is paired with.
 
-    Division by zero is undefined and therefore not allowed, so we add a condition to check for it.
-    This condition is brought up the tree and expanded into a conjunction with the first boolean-type expression it is paired with.
+    See `Expression::definedness_condition` for the conditions of each operator, e.g.
 
-    E.g. a / b => (a / b) @ (b != 0)
+    a / b => (a / b) @ (b != 0)
+    a % b => (a % b) @ (b != 0)
+    a ** b => (a ** b) @ (b >= 0)
+    min([]) => 0 @ false
 
+    This comes before the other rules, which could otherwise rewrite an operator without its condition
+    (e.g. `empty_to_nothing` on `min([])`).
 */
-#[register_rule(("Bubble", 100))]
-fn div_to_bubble(expr: &Expression, _: &Model) -> ApplicationResult {
-    if let Expression::UnsafeDiv(_, a, b) = expr {
-        return Ok(Reduction::pure(Expression::Bubble(
-            Metadata::new(),
-            Box::new(Expression::SafeDiv(Metadata::new(), a.clone(), b.clone())),
-            Box::new(Expression::Neq(
-                Metadata::new(),
-                b.clone(),
-                Box::new(Expression::from(0)),
-            )),
-        )));
-    }
-    return Err(ApplicationError::RuleNotApplicable);
+#[register_rule(("Bubble", 101), applies_to(UnsafeDiv, UnsafeMod, UnsafePow, Min))]
+fn partial_to_bubble(expr: &Expression, _: &Model) -> ApplicationResult {
+    let (Some(safe), Some(condition)) = (expr.safe_version(), expr.definedness_condition()) else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    Ok(Reduction::pure(Expression::Bubble(
+        Metadata::new(),
+        Arc::new(safe),
+        Arc::new(condition),
+    )))
 }
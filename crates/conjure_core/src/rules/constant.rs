@@ -1,9 +1,10 @@
-use conjure_core::ast::{Constant as Const, Expression as Expr};
+use conjure_core::ast::{checked_pow, floor_div, floor_mod, Constant as Const, Expression as Expr};
 use conjure_core::metadata::Metadata;
 use conjure_core::rule_engine::{
     register_rule, register_rule_set, ApplicationError, ApplicationResult, Reduction,
 };
 use conjure_core::Model;
+use itertools::Itertools;
 
 register_rule_set!("Constant", 255, ());
 
@@ -55,21 +56,66 @@ pub fn eval_constant(expr: &Expr) -> Option<Const> {
         Expr::SumLeq(_, exprs, a) => {
             flat_op::<i32, bool>(|e, a| e.iter().sum::<i32>() <= a, exprs, a).map(Const::Bool)
         }
+        Expr::SumEq(_, exprs, a) => {
+            flat_op::<i32, bool>(|e, a| e.iter().sum::<i32>() == a, exprs, a).map(Const::Bool)
+        }
+        Expr::MinEq(_, exprs, a) => {
+            flat_op::<i32, bool>(|e, a| e.iter().min() == Some(&a), exprs, a).map(Const::Bool)
+        }
+        Expr::AllDiff(_, exprs) => {
+            vec_op::<i32, bool>(|e| e.iter().all_unique(), exprs).map(Const::Bool)
+        }
         // Expr::Div(_, a, b) => bin_op::<i32, i32>(|a, b| a / b, a, b).map(Const::Int),
         // Expr::SafeDiv(_, a, b) => bin_op::<i32, i32>(|a, b| a / b, a, b).map(Const::Int),
         Expr::Min(_, exprs) => {
             opt_vec_op::<i32, i32>(|e| e.iter().min().copied(), exprs).map(Const::Int)
         }
         Expr::UnsafeDiv(_, a, b) | Expr::SafeDiv(_, a, b) => {
-            if unwrap_expr::<i32>(b)? == 0 {
-                return None;
-            }
-            bin_op::<i32, i32>(|a, b| a / b, a, b).map(Const::Int)
+            floor_div(unwrap_expr(a)?, unwrap_expr(b)?).map(Const::Int)
+        }
+        Expr::UnsafeMod(_, a, b) | Expr::SafeMod(_, a, b) => {
+            floor_mod(unwrap_expr(a)?, unwrap_expr(b)?).map(Const::Int)
+        }
+        Expr::UnsafePow(_, a, b) | Expr::SafePow(_, a, b) => {
+            checked_pow(unwrap_expr(a)?, unwrap_expr(b)?).map(Const::Int)
         }
         Expr::DivEq(_, a, b, c) => {
-            tern_op::<i32, bool>(|a, b, c| a == b * c, a, b, c).map(Const::Bool)
+            // As Minion's DivUndefZero, which DivEq is lowered to, division by 0 gives 0
+            tern_op::<i32, bool>(
+                |a, b, c| match floor_div(a, b) {
+                    Some(q) => q == c,
+                    None => b == 0 && c == 0,
+                },
+                a,
+                b,
+                c,
+            )
+            .map(Const::Bool)
+        }
+        Expr::ModEq(_, a, b, c) => {
+            // As Minion's ModuloUndefZero, which ModEq is lowered to, modulo by 0 gives 0
+            tern_op::<i32, bool>(
+                |a, b, c| match floor_mod(a, b) {
+                    Some(r) => r == c,
+                    None => b == 0 && c == 0,
+                },
+                a,
+                b,
+                c,
+            )
+            .map(Const::Bool)
+        }
+        Expr::PowEq(_, a, b, c) => {
+            tern_op::<i32, bool>(|a, b, c| checked_pow(a, b) == Some(c), a, b, c).map(Const::Bool)
+        }
+        Expr::Bubble(_, a, b) => {
+            let condition = unwrap_expr::<bool>(b)?;
+            match eval_constant(a)? {
+                Const::Bool(a) => Some(Const::Bool(a && condition)),
+                // A non-boolean bubble is undefined when its condition does not hold
+                c => condition.then_some(c),
+            }
         }
-        Expr::Bubble(_, a, b) => bin_op::<bool, bool>(|a, b| a && b, a, b).map(Const::Bool),
         _ => {
             println!("WARNING: Unimplemented constant eval: {:?}", expr);
             None
@@ -136,14 +182,16 @@ fn unwrap_expr<T: TryFrom<Const>>(expr: &Expr) -> Option<T> {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use conjure_core::ast::{Constant, Expression};
 
     #[test]
     fn div_by_zero() {
         let expr = Expression::UnsafeDiv(
             Default::default(),
-            Box::new(Expression::Constant(Default::default(), Constant::Int(1))),
-            Box::new(Expression::Constant(Default::default(), Constant::Int(0))),
+            Arc::new(Expression::Constant(Default::default(), Constant::Int(1))),
+            Arc::new(Expression::Constant(Default::default(), Constant::Int(0))),
         );
         assert_eq!(super::eval_constant(&expr), None);
     }
@@ -152,9 +200,99 @@ mod tests {
     fn safediv_by_zero() {
         let expr = Expression::SafeDiv(
             Default::default(),
-            Box::new(Expression::Constant(Default::default(), Constant::Int(1))),
-            Box::new(Expression::Constant(Default::default(), Constant::Int(0))),
+            Arc::new(Expression::Constant(Default::default(), Constant::Int(1))),
+            Arc::new(Expression::Constant(Default::default(), Constant::Int(0))),
         );
         assert_eq!(super::eval_constant(&expr), None);
     }
+
+    #[test]
+    fn div_and_mod_round_down_and_are_undefined_on_overflow() {
+        let int = |i| Arc::new(Expression::Constant(Default::default(), Constant::Int(i)));
+        let div =
+            |a, b| super::eval_constant(&Expression::SafeDiv(Default::default(), int(a), int(b)));
+        let modulo =
+            |a, b| super::eval_constant(&Expression::SafeMod(Default::default(), int(a), int(b)));
+        assert_eq!(div(-3, 2), Some(Constant::Int(-2)));
+        assert_eq!(div(3, -2), Some(Constant::Int(-2)));
+        assert_eq!(div(-4, 2), Some(Constant::Int(-2)));
+        assert_eq!(div(i32::MIN, -1), None);
+        assert_eq!(modulo(-3, 2), Some(Constant::Int(1)));
+        assert_eq!(modulo(3, -2), Some(Constant::Int(-1)));
+        assert_eq!(modulo(-4, 2), Some(Constant::Int(0)));
+        assert_eq!(modulo(i32::MAX, i32::MIN), Some(Constant::Int(-1)));
+        assert_eq!(modulo(i32::MIN, -1), None);
+        assert_eq!(modulo(1, 0), None);
+    }
+
+    #[test]
+    fn div_eq_rounds_down_and_is_zero_on_division_by_zero() {
+        let int = |i| Arc::new(Expression::Constant(Default::default(), Constant::Int(i)));
+        let div_eq = |a, b, c| {
+            super::eval_constant(&Expression::DivEq(
+                Default::default(),
+                int(a),
+                int(b),
+                int(c),
+            ))
+        };
+        assert_eq!(div_eq(3, 2, 1), Some(Constant::Bool(true)));
+        assert_eq!(div_eq(-3, 2, -2), Some(Constant::Bool(true)));
+        assert_eq!(div_eq(-3, 2, -1), Some(Constant::Bool(false)));
+        assert_eq!(div_eq(3, -2, -2), Some(Constant::Bool(true)));
+        assert_eq!(div_eq(0, 0, 0), Some(Constant::Bool(true)));
+        assert_eq!(div_eq(5, 0, 0), Some(Constant::Bool(true)));
+        assert_eq!(div_eq(5, 0, 1), Some(Constant::Bool(false)));
+    }
+
+    #[test]
+    fn sum_eq_compares_the_sum() {
+        let int = |i| Expression::Constant(Default::default(), Constant::Int(i));
+        let sum_eq = |xs: Vec<i32>, a| {
+            super::eval_constant(&Expression::SumEq(
+                Default::default(),
+                Arc::new(xs.into_iter().map(int).collect()),
+                Arc::new(int(a)),
+            ))
+        };
+        assert_eq!(sum_eq(vec![1, 2, 3], 6), Some(Constant::Bool(true)));
+        assert_eq!(sum_eq(vec![1, 2, 3], 5), Some(Constant::Bool(false)));
+        assert_eq!(sum_eq(vec![], 0), Some(Constant::Bool(true)));
+    }
+
+    #[test]
+    fn all_diff_needs_distinct_values() {
+        let int = |i| Expression::Constant(Default::default(), Constant::Int(i));
+        let all_diff = |xs: Vec<i32>| {
+            super::eval_constant(&Expression::AllDiff(
+                Default::default(),
+                Arc::new(xs.into_iter().map(int).collect()),
+            ))
+        };
+        assert_eq!(all_diff(vec![1, 2, 3]), Some(Constant::Bool(true)));
+        assert_eq!(all_diff(vec![1, 2, 1]), Some(Constant::Bool(false)));
+        assert_eq!(all_diff(vec![]), Some(Constant::Bool(true)));
+    }
+
+    #[test]
+    fn bubble_is_false_or_undefined_when_its_condition_fails() {
+        let constant = |c| Arc::new(Expression::Constant(Default::default(), c));
+        let bubble = |value, condition| {
+            super::eval_constant(&Expression::Bubble(
+                Default::default(),
+                constant(value),
+                constant(Constant::Bool(condition)),
+            ))
+        };
+        assert_eq!(
+            bubble(Constant::Bool(true), true),
+            Some(Constant::Bool(true))
+        );
+        assert_eq!(
+            bubble(Constant::Bool(true), false),
+            Some(Constant::Bool(false))
+        );
+        assert_eq!(bubble(Constant::Int(3), true), Some(Constant::Int(3)));
+        assert_eq!(bubble(Constant::Int(3), false), None);
+    }
 }
@@ -2,14 +2,15 @@
 /*        Rules for translating to Minion-supported constraints         */
 /************************************************************************/
 
+use std::sync::Arc;
+
+use itertools::Itertools;
 use uniplate::uniplate::Uniplate;
 
-use crate::ast::{
-    Constant as Const, DecisionVariable, Domain, Expression as Expr, Range, SymbolTable,
-};
+use crate::ast::{DecisionVariable, Domain, Expression as Expr, Name, Range, SymbolTable};
 use crate::metadata::Metadata;
 use crate::rule_engine::{
-    register_rule, register_rule_set, ApplicationError, ApplicationResult, Reduction,
+    pattern_rule, register_rule, register_rule_set, ApplicationError, ApplicationResult, Reduction,
 };
 use crate::solver::SolverFamily;
 use crate::Model;
@@ -25,22 +26,6 @@ fn is_nested_sum(exprs: &Vec<Expr>) -> bool {
     false
 }
 
-/**
- * Helper function to get the vector of expressions from a sum (or error if it's a nested sum - we need to flatten it first)
- */
-fn sum_to_vector(expr: &Expr) -> Result<Vec<Expr>, ApplicationError> {
-    match expr {
-        Expr::Sum(_, exprs) => {
-            if is_nested_sum(exprs) {
-                Err(ApplicationError::RuleNotApplicable)
-            } else {
-                Ok(exprs.clone())
-            }
-        }
-        _ => Err(ApplicationError::RuleNotApplicable),
-    }
-}
-
 // /**
 //  * Convert an Eq to a conjunction of Geq and Leq:
 //  * ```text
@@ -61,184 +46,107 @@ fn sum_to_vector(expr: &Expr) -> Result<Vec<Expr>, ApplicationError> {
 //     }
 // }
 
-/**
- * Convert a Geq to a SumGeq if the left hand side is a sum:
- * ```text
- * sum([a, b, c]) >= d => sum_geq([a, b, c], d)
- * ```
- */
-#[register_rule(("Minion", 100))]
-fn flatten_sum_geq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Geq(metadata, a, b) => {
-            let exprs = sum_to_vector(a)?;
-            Ok(Reduction::pure(Expr::SumGeq(
-                metadata.clone_dirty(),
-                exprs,
-                b.clone(),
-            )))
-        }
-        _ => Err(ApplicationError::RuleNotApplicable),
+pattern_rule! {
+    /**
+     * Convert a Geq to a SumGeq if the left hand side is a sum:
+     * ```text
+     * sum([a, b, c]) >= d => sum_geq([a, b, c], d)
+     * ```
+     */
+    #[register_rule(("Minion", 100))]
+    fn flatten_sum_geq {
+        sum(xs) >= y if !is_nested_sum(xs) => sum_geq(xs, y),
     }
-}
 
-/**
- * Convert a Leq to a SumLeq if the left hand side is a sum:
- * ```text
- * sum([a, b, c]) <= d => sum_leq([a, b, c], d)
- * ```
- */
-#[register_rule(("Minion", 100))]
-fn sum_leq_to_sumleq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Leq(metadata, a, b) => {
-            let exprs = sum_to_vector(a)?;
-            Ok(Reduction::pure(Expr::SumLeq(
-                metadata.clone_dirty(),
-                exprs,
-                b.clone(),
-            )))
-        }
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+     * Convert a Leq to a SumLeq if the left hand side is a sum:
+     * ```text
+     * sum([a, b, c]) <= d => sum_leq([a, b, c], d)
+     * ```
+     */
+    #[register_rule(("Minion", 100))]
+    fn sum_leq_to_sumleq {
+        sum(xs) <= y if !is_nested_sum(xs) => sum_leq(xs, y),
     }
-}
 
-/**
- * Convert a 'Eq(Sum([...]))' to a SumEq
- * ```text
- * eq(sum([a, b]), c) => sumeq([a, b], c)
- * ```
-*/
-#[register_rule(("Minion", 100))]
-fn sum_eq_to_sumeq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Eq(metadata, a, b) => {
-            if let Ok(exprs) = sum_to_vector(a) {
-                Ok(Reduction::pure(Expr::SumEq(
-                    metadata.clone_dirty(),
-                    exprs,
-                    b.clone(),
-                )))
-            } else if let Ok(exprs) = sum_to_vector(b) {
-                Ok(Reduction::pure(Expr::SumEq(
-                    metadata.clone_dirty(),
-                    exprs,
-                    a.clone(),
-                )))
-            } else {
-                Err(ApplicationError::RuleNotApplicable)
-            }
-        }
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+     * Convert a 'Eq(Sum([...]))' to a SumEq
+     * ```text
+     * eq(sum([a, b]), c) => sumeq([a, b], c)
+     * ```
+    */
+    #[register_rule(("Minion", 100))]
+    fn sum_eq_to_sumeq {
+        sum(xs) == y if !is_nested_sum(xs) => sum_eq(xs, y),
+        y == sum(xs) if !is_nested_sum(xs) => sum_eq(xs, y),
     }
-}
 
-/**
- * Convert a `SumEq` to an `And(SumGeq, SumLeq)`
- * This is a workaround for Minion not having support for a flat "equals" operation on sums
- * ```text
- * sumeq([a, b], c) -> watched_and({
- *   sumleq([a, b], c),
- *   sumgeq([a, b], c)
- * })
- * ```
- * I. e.
- * ```text
- * ((a + b) >= c) && ((a + b) <= c)
- * a + b = c
- * ```
- */
-#[register_rule(("Minion", 100))]
-fn sumeq_to_minion(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::SumEq(metadata, exprs, eq_to) => Ok(Reduction::pure(Expr::And(
-            Metadata::new(),
-            vec![
-                Expr::SumGeq(Metadata::new(), exprs.clone(), Box::from(*eq_to.clone())),
-                Expr::SumLeq(Metadata::new(), exprs.clone(), Box::from(*eq_to.clone())),
-            ],
-        ))),
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+     * Convert a `SumEq` to an `And(SumGeq, SumLeq)`
+     * This is a workaround for Minion not having support for a flat "equals" operation on sums
+     * ```text
+     * sumeq([a, b], c) -> watched_and({
+     *   sumleq([a, b], c),
+     *   sumgeq([a, b], c)
+     * })
+     * ```
+     * I. e.
+     * ```text
+     * ((a + b) >= c) && ((a + b) <= c)
+     * a + b = c
+     * ```
+     */
+    #[register_rule(("Minion", 100))]
+    fn sumeq_to_minion {
+        sum_eq(xs, y) => and([sum_geq(xs, y), sum_leq(xs, y)]),
     }
-}
 
-/**
-* Convert a Lt to an Ineq:
+    /**
+    * Convert a Lt to an Ineq:
 
-* ```text
-* a < b => a - b < -1
-* ```
-*/
-#[register_rule(("Minion", 100))]
-fn lt_to_ineq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Lt(metadata, a, b) => Ok(Reduction::pure(Expr::Ineq(
-            metadata.clone_dirty(),
-            a.clone(),
-            b.clone(),
-            Box::new(Expr::Constant(Metadata::new(), Const::Int(-1))),
-        ))),
-        _ => Err(ApplicationError::RuleNotApplicable),
+    * ```text
+    * a < b => a - b < -1
+    * ```
+    */
+    #[register_rule(("Minion", 100))]
+    fn lt_to_ineq {
+        a < b => ineq(a, b, -1),
     }
-}
 
-/**
-* Convert a Gt to an Ineq:
-*
-* ```text
-* a > b => b - a < -1
-* ```
-*/
-#[register_rule(("Minion", 100))]
-fn gt_to_ineq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Gt(metadata, a, b) => Ok(Reduction::pure(Expr::Ineq(
-            metadata.clone_dirty(),
-            b.clone(),
-            a.clone(),
-            Box::new(Expr::Constant(Metadata::new(), Const::Int(-1))),
-        ))),
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+    * Convert a Gt to an Ineq:
+    *
+    * ```text
+    * a > b => b - a < -1
+    * ```
+    */
+    #[register_rule(("Minion", 100))]
+    fn gt_to_ineq {
+        a > b => ineq(b, a, -1),
     }
-}
 
-/**
-* Convert a Geq to an Ineq:
-*
-* ```text
-* a >= b => b - a < 0
-* ```
-*/
-#[register_rule(("Minion", 100))]
-fn geq_to_ineq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Geq(metadata, a, b) => Ok(Reduction::pure(Expr::Ineq(
-            metadata.clone_dirty(),
-            b.clone(),
-            a.clone(),
-            Box::new(Expr::Constant(Metadata::new(), Const::Int(0))),
-        ))),
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+    * Convert a Geq to an Ineq:
+    *
+    * ```text
+    * a >= b => b - a < 0
+    * ```
+    */
+    #[register_rule(("Minion", 100))]
+    fn geq_to_ineq {
+        a >= b => ineq(b, a, 0),
     }
-}
 
-/**
-* Convert a Leq to an Ineq:
-*
-* ```text
-* a <= b => a - b < 0
-* ```
-*/
-#[register_rule(("Minion", 100))]
-fn leq_to_ineq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Leq(metadata, a, b) => Ok(Reduction::pure(Expr::Ineq(
-            metadata.clone_dirty(),
-            a.clone(),
-            b.clone(),
-            Box::new(Expr::Constant(Metadata::new(), Const::Int(0))),
-        ))),
-        _ => Err(ApplicationError::RuleNotApplicable),
+    /**
+    * Convert a Leq to an Ineq:
+    *
+    * ```text
+    * a <= b => a - b < 0
+    * ```
+    */
+    #[register_rule(("Minion", 100))]
+    fn leq_to_ineq {
+        a <= b => ineq(a, b, 0),
     }
 }
 
@@ -259,117 +167,157 @@ fn leq_to_ineq(expr: &Expr, _: &Model) -> ApplicationResult {
 // }
 
 /**
- * Since Minion doesn't support some constraints with div (e.g. leq, neq), we add an auxiliary variable to represent the division result.
+ * Since Minion doesn't support some constraints with div, mod or pow (e.g. leq, neq), we add an auxiliary variable
+ * to represent the result, constrained by Minion's `div_undefzero`, `modulo_undefzero` or `pow`.
+ * An operation equal to one that already has a variable reuses it (see [`Reduction::define_aux_var`]).
+ *
+ * Minion's `pow` only supports non-negative operands, so a power is only flattened when both operands are.
 */
-#[register_rule(("Minion", 101))]
-fn flatten_safediv(expr: &Expr, mdl: &Model) -> ApplicationResult {
+#[register_rule(("Minion", 101), applies_to(Eq, Leq, Geq, Neq))]
+fn flatten_safe_arithmetic(expr: &Expr, mdl: &Model) -> ApplicationResult {
     if expr.is_eq() || expr.is_leq() || expr.is_geq() || expr.is_neq() {
         let mut sub = expr.children();
 
         let mut new_vars = SymbolTable::new();
         let mut new_top = vec![];
+        let mut aux_vars: Vec<(Name, Expr)> = vec![];
 
-        // replace every safe div child with a reference to a new variable
+        // replace every safe div, mod or pow child with a reference to a new variable
         for c in sub.iter_mut() {
-            if let Expr::SafeDiv(_, a, b) = c.clone() {
-                let new_name = mdl.gensym();
-                let domain = c
-                    .domain_of(&mdl.variables)
-                    .ok_or(ApplicationError::DomainError)?;
-                new_vars.insert(new_name.clone(), DecisionVariable::new(domain));
+            let (a, b, constraint): (_, _, fn(_, _, _, _) -> Expr) = match c.clone() {
+                Expr::SafeDiv(_, a, b) => (a, b, Expr::DivEq),
+                Expr::SafeMod(_, a, b) => (a, b, Expr::ModEq),
+                Expr::SafePow(_, a, b) if is_non_negative(&a, mdl) && is_non_negative(&b, mdl) => {
+                    (a, b, Expr::PowEq)
+                }
+                _ => continue,
+            };
+            let new_name = mdl.gensym();
+            let domain = c
+                .domain_of(&mdl.variables)
+                .ok_or(ApplicationError::DomainError)?;
+            new_vars.insert(new_name.clone(), DecisionVariable::new(domain));
 
-                new_top.push(Expr::DivEq(
-                    Metadata::new(),
-                    a.clone(),
-                    b.clone(),
-                    Box::new(Expr::Reference(Metadata::new(), new_name.clone())),
-                ));
+            new_top.push(constraint(
+                Metadata::new(),
+                a.clone(),
+                b.clone(),
+                Arc::new(Expr::Reference(Metadata::new(), new_name.clone())),
+            ));
 
-                *c = Expr::Reference(Metadata::new(), new_name.clone());
-            }
+            aux_vars.push((new_name.clone(), c.clone()));
+            *c = Expr::Reference(Metadata::new(), new_name.clone());
         }
-        if !new_top.is_empty() {
-            return Ok(Reduction::new(
+        if sub != expr.children() {
+            let new_top = if new_top.is_empty() {
+                Expr::Nothing
+            } else {
+                Expr::And(Metadata::new(), new_top.into())
+            };
+            let mut reduction = Reduction::new(
                 expr.with_children(sub)
                     .or(Err(ApplicationError::RuleNotApplicable))?,
-                Expr::And(Metadata::new(), new_top),
+                new_top,
                 new_vars,
-            ));
+            );
+            for (name, op) in aux_vars {
+                reduction = reduction.define_aux_var(name, op);
+            }
+            return Ok(reduction);
         }
     }
     Err(ApplicationError::RuleNotApplicable)
 }
 
-#[register_rule(("Minion", 100))]
-fn div_eq_to_diveq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Eq(metadata, a, b) => {
-            if let Expr::SafeDiv(_, x, y) = a.as_ref() {
-                if !(b.is_reference() || b.is_constant()) {
-                    return Err(ApplicationError::RuleNotApplicable);
-                }
-                Ok(Reduction::pure(Expr::DivEq(
-                    metadata.clone_dirty(),
-                    x.clone(),
-                    y.clone(),
-                    b.clone(),
-                )))
-            } else if let Expr::SafeDiv(_, x, y) = b.as_ref() {
-                if !(a.is_reference() || a.is_constant()) {
-                    return Err(ApplicationError::RuleNotApplicable);
-                }
-                Ok(Reduction::pure(Expr::DivEq(
-                    metadata.clone_dirty(),
-                    x.clone(),
-                    y.clone(),
-                    a.clone(),
-                )))
-            } else {
-                Err(ApplicationError::RuleNotApplicable)
-            }
-        }
-        _ => Err(ApplicationError::RuleNotApplicable),
+/// Whether every value of the expression is at least 0.
+fn is_non_negative(expr: &Expr, mdl: &Model) -> bool {
+    expr.domain_of(&mdl.variables)
+        .and_then(|domain| domain.bounds_i32())
+        .is_some_and(|(lo, _)| lo >= 0)
+}
+
+/**
+ * Turn a Min of variables and constants into a new variable, constrained either as in `min_to_var`
+ * or by Minion's `min` constraint:
+ * ```text
+ * min([a, b]) ~> c ; c <= a & c <= b & (c = a | c = b)
+ * min([a, b]) ~> c ; min_eq([a, b], c)
+ * ```
+ */
+#[register_rule(("Minion", 101), applies_to(Min))]
+fn min_to_var_or_min_eq(expr: &Expr, mdl: &Model) -> ApplicationResult {
+    let Expr::Min(metadata, exprs) = expr else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    if !exprs.iter().all(|e| e.is_reference() || e.is_constant()) {
+        return Err(ApplicationError::RuleNotApplicable);
     }
+    let via_aux = super::base::min_to_var(expr, mdl)?;
+    let Expr::Reference(_, name) = &via_aux.new_expression else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    let native = Reduction::new(
+        via_aux.new_expression.clone(),
+        Expr::MinEq(
+            metadata.clone_dirty(),
+            exprs.clone(),
+            Arc::new(via_aux.new_expression.clone()),
+        ),
+        via_aux.symbols.clone(),
+    )
+    .define_aux_var(name.clone(), expr.clone());
+    Reduction::choice([via_aux, native])
 }
 
-#[register_rule(("Minion", 100))]
-fn negated_neq_to_eq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Not(_, a) => match a.as_ref() {
-            Expr::Neq(_, b, c) => {
-                if !b.can_be_undefined() && !c.can_be_undefined() {
-                    Ok(Reduction::pure(Expr::Eq(
-                        Metadata::new(),
-                        b.clone(),
-                        c.clone(),
-                    )))
-                } else {
-                    Err(ApplicationError::RuleNotApplicable)
-                }
-            }
-            _ => Err(ApplicationError::RuleNotApplicable),
-        },
-        _ => Err(ApplicationError::RuleNotApplicable),
+/**
+ * Offer pairwise disequalities as an alternative to Minion's native all-different constraint:
+ * ```text
+ * alldiff([a, b, c]) ~> alldiff([a, b, c])
+ * alldiff([a, b, c]) ~> a != b & a != c & b != c
+ * ```
+ * The native constraint is marked clean, so that this rule does not apply to it again.
+ */
+#[register_rule(("Minion", 100), applies_to(AllDiff))]
+fn all_diff_or_pairwise_neq(expr: &Expr, _: &Model) -> ApplicationResult {
+    let Expr::AllDiff(metadata, exprs) = expr else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    if expr.is_clean() {
+        return Err(ApplicationError::RuleNotApplicable);
     }
+    let mut native = expr.clone();
+    native.set_clean(true);
+    if let [only] = exprs.as_slice() {
+        if only.can_be_undefined() {
+            // A lone operand is in no disequality, so could not make the constraint false
+            return Ok(Reduction::pure(native));
+        }
+    }
+    let pairwise = exprs
+        .iter()
+        .tuple_combinations()
+        .map(|(a, b)| Expr::Neq(Metadata::new(), Arc::new(a.clone()), Arc::new(b.clone())))
+        .collect::<Vec<_>>();
+    Reduction::choice([
+        Reduction::pure(native),
+        Reduction::pure(Expr::And(metadata.clone_dirty(), pairwise.into())),
+    ])
 }
 
-#[register_rule(("Minion", 100))]
-fn negated_eq_to_neq(expr: &Expr, _: &Model) -> ApplicationResult {
-    match expr {
-        Expr::Not(_, a) => match a.as_ref() {
-            Expr::Eq(_, b, c) => {
-                if !b.can_be_undefined() && !c.can_be_undefined() {
-                    Ok(Reduction::pure(Expr::Neq(
-                        Metadata::new(),
-                        b.clone(),
-                        c.clone(),
-                    )))
-                } else {
-                    Err(ApplicationError::RuleNotApplicable)
-                }
-            }
-            _ => Err(ApplicationError::RuleNotApplicable),
-        },
-        _ => Err(ApplicationError::RuleNotApplicable),
+pattern_rule! {
+    #[register_rule(("Minion", 100))]
+    fn div_eq_to_diveq {
+        safe_div(x, y) == b if b.is_reference() || b.is_constant() => div_eq(x, y, b),
+        a == safe_div(x, y) if a.is_reference() || a.is_constant() => div_eq(x, y, a),
+    }
+
+    #[register_rule(("Minion", 100))]
+    fn negated_neq_to_eq {
+        !(a != b) if !a.can_be_undefined() && !b.can_be_undefined() => a == b,
+    }
+
+    #[register_rule(("Minion", 100))]
+    fn negated_eq_to_neq {
+        !(a == b) if !a.can_be_undefined() && !b.can_be_undefined() => a != b,
     }
 }
@@ -5,3 +5,8 @@ mod bubble;
 mod cnf;
 mod constant;
 mod minion;
+mod normalise;
+mod simplify;
+
+#[cfg(test)]
+mod semantics_tests;
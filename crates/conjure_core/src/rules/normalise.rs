@@ -0,0 +1,156 @@
+/************************************************************************/
+/*        Rules for putting expressions into a canonical form           */
+/************************************************************************/
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use conjure_core::ast::{Constant as Const, Expression as Expr};
+use conjure_core::metadata::Metadata;
+use conjure_core::rule_engine::{
+    register_rule, register_rule_set, ApplicationError, ApplicationResult, Reduction,
+};
+use conjure_core::solver::SolverFamily;
+use conjure_core::Model;
+
+// Nested sums, conjunctions and disjunctions are flattened by the Base rules.
+register_rule_set!("Normalise", 160, ("Base"), (SolverFamily::Minion));
+
+/// Sort `exprs` into the canonical order, or return `None` if they already are.
+fn sorted(exprs: &[Expr]) -> Option<Vec<Expr>> {
+    if exprs
+        .windows(2)
+        .all(|pair| pair[0].canonical_cmp(&pair[1]) != Ordering::Greater)
+    {
+        return None;
+    }
+    let mut exprs = exprs.to_vec();
+    exprs.sort_by(Expr::canonical_cmp);
+    Some(exprs)
+}
+
+/**
+ * Fold the constant operands of a sum, conjunction or disjunction into one, and sort the operands into the
+ * canonical order, with the constant last. The summed operands of `SumEq`, `SumLeq` and `SumGeq` are
+ * treated the same way.
+ * ```text
+ * sum([3, b, a, 2]) ~> sum([a, b, 5])
+ * and([q, true, p]) ~> and([p, q])
+ * or([q, true, p]) ~> true
+ * ```
+ */
+#[register_rule(("Normalise", 110), applies_to(Sum, And, Or, SumEq, SumLeq, SumGeq))]
+fn normalise_commutative(expr: &Expr, _: &Model) -> ApplicationResult {
+    let exprs = match expr {
+        Expr::Sum(_, exprs)
+        | Expr::And(_, exprs)
+        | Expr::Or(_, exprs)
+        | Expr::SumEq(_, exprs, _)
+        | Expr::SumLeq(_, exprs, _)
+        | Expr::SumGeq(_, exprs, _)
+            if !exprs.is_empty() =>
+        {
+            exprs
+        }
+        // Empty operators are removed by the Base rules
+        _ => return Err(ApplicationError::RuleNotApplicable),
+    };
+
+    let (constants, others): (Vec<&Expr>, Vec<&Expr>) = exprs.iter().partition(|e| e.is_constant());
+
+    // The constant to keep, if any, or the value of the whole expression if a constant decides it
+    let folded = match expr {
+        Expr::Sum(_, _) | Expr::SumEq(_, _, _) | Expr::SumLeq(_, _, _) | Expr::SumGeq(_, _, _) => {
+            let mut sum: i32 = 0;
+            for c in &constants {
+                let Expr::Constant(_, Const::Int(i)) = c else {
+                    return Err(ApplicationError::RuleNotApplicable);
+                };
+                sum = sum
+                    .checked_add(*i)
+                    .ok_or(ApplicationError::RuleNotApplicable)?;
+            }
+            (sum != 0 || others.is_empty()).then_some(Const::Int(sum))
+        }
+        _ => {
+            // And is decided by a false operand, and Or by a true one
+            let decider = matches!(expr, Expr::Or(_, _));
+            for c in &constants {
+                let Expr::Constant(_, Const::Bool(b)) = c else {
+                    return Err(ApplicationError::RuleNotApplicable);
+                };
+                if *b == decider {
+                    return Ok(Reduction::pure(Expr::Constant(
+                        Metadata::new(),
+                        Const::Bool(decider),
+                    )));
+                }
+            }
+            others.is_empty().then_some(Const::Bool(!decider))
+        }
+    };
+
+    let mut new_exprs: Vec<Expr> = others.into_iter().cloned().collect();
+    new_exprs.sort_by(Expr::canonical_cmp);
+    if let Some(c) = folded {
+        // Keep the original constant if it is already folded, so the expression compares equal
+        new_exprs.push(match constants.as_slice() {
+            [Expr::Constant(m, k)] if *k == c => Expr::Constant(m.clone(), c),
+            _ => Expr::Constant(Metadata::new(), c),
+        });
+    }
+    if new_exprs == **exprs {
+        return Err(ApplicationError::RuleNotApplicable);
+    }
+
+    let new_exprs = Arc::new(new_exprs);
+    Ok(Reduction::pure(match expr {
+        Expr::Sum(m, _) => Expr::Sum(m.clone(), new_exprs),
+        Expr::And(m, _) => Expr::And(m.clone(), new_exprs),
+        Expr::Or(m, _) => Expr::Or(m.clone(), new_exprs),
+        Expr::SumEq(m, _, rhs) => Expr::SumEq(m.clone(), new_exprs, rhs.clone()),
+        Expr::SumLeq(m, _, rhs) => Expr::SumLeq(m.clone(), new_exprs, rhs.clone()),
+        Expr::SumGeq(m, _, rhs) => Expr::SumGeq(m.clone(), new_exprs, rhs.clone()),
+        _ => return Err(ApplicationError::RuleNotApplicable),
+    }))
+}
+
+/**
+ * Sort the operands of an `allDiff` into the canonical order.
+ * ```text
+ * allDiff([b, 1, a]) ~> allDiff([a, b, 1])
+ * ```
+ */
+#[register_rule(("Normalise", 110), applies_to(AllDiff))]
+fn normalise_all_diff(expr: &Expr, _: &Model) -> ApplicationResult {
+    let Expr::AllDiff(metadata, exprs) = expr else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    let exprs = sorted(exprs).ok_or(ApplicationError::RuleNotApplicable)?;
+    Ok(Reduction::pure(Expr::AllDiff(
+        metadata.clone(),
+        Arc::new(exprs),
+    )))
+}
+
+/**
+ * Put the two sides of an equality or disequality into the canonical order.
+ * ```text
+ * 3 = x ~> x = 3
+ * b != a ~> a != b
+ * ```
+ */
+#[register_rule(("Normalise", 110), applies_to(Eq, Neq))]
+fn normalise_eq_neq(expr: &Expr, _: &Model) -> ApplicationResult {
+    let (Expr::Eq(metadata, a, b) | Expr::Neq(metadata, a, b)) = expr else {
+        return Err(ApplicationError::RuleNotApplicable);
+    };
+    if a.canonical_cmp(b) != Ordering::Greater {
+        return Err(ApplicationError::RuleNotApplicable);
+    }
+    let (a, b) = (b.clone(), a.clone());
+    Ok(Reduction::pure(match expr {
+        Expr::Eq(_, _, _) => Expr::Eq(metadata.clone(), a, b),
+        _ => Expr::Neq(metadata.clone(), a, b),
+    }))
+}
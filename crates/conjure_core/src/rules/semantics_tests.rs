@@ -0,0 +1,278 @@
+//! Property tests checking that rules preserve the meaning of the expressions they rewrite.
+//!
+//! Random expressions are generated over a few small variables. Every registered rule is tried on
+//! every subexpression, and each successful reduction is compared to the original under all
+//! assignments to the variables, using [`eval_constant`] under the relational semantics: a boolean
+//! expression over an undefined integer is false, and an undefined integer expression must stay
+//! undefined.
+//!
+//! A reduction may introduce auxiliary variables and new top-level constraints. For each
+//! assignment, every assignment to the auxiliary variables satisfying the new constraints must give
+//! the new expression the original's value, and there must be one unless the original is an
+//! undefined integer.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use proptest::prelude::*;
+use uniplate::uniplate::Uniplate;
+
+use crate::ast::{
+    Constant, DecisionVariable, Domain, Expression, Name, Range, ReturnType, SymbolTable,
+};
+use crate::metadata::Metadata;
+use crate::model::Model;
+use crate::rule_engine::{get_rules, Reduction};
+use crate::rules::eval_constant;
+
+const INT_VARS: [&str; 3] = ["x", "y", "z"];
+const BOOL_VARS: [&str; 2] = ["p", "q"];
+
+/// Assignments to the auxiliary variables of a reduction are sampled beyond this many.
+const MAX_AUX_ASSIGNMENTS: usize = 4096;
+
+type Assignment = HashMap<Name, Constant>;
+
+fn name(var: &str) -> Name {
+    Name::UserName(var.to_string())
+}
+
+fn reference(var: &str) -> Expression {
+    Expression::Reference(Metadata::new(), name(var))
+}
+
+fn int_domain() -> impl Strategy<Value = Domain> {
+    (-3..=2, 0..=3).prop_map(|(lo, len)| Domain::IntDomain(vec![Range::Bounded(lo, lo + len)]))
+}
+
+fn symbol_table() -> impl Strategy<Value = SymbolTable> {
+    prop::collection::vec(int_domain(), INT_VARS.len()).prop_map(|domains| {
+        let mut symbols: SymbolTable = INT_VARS
+            .iter()
+            .zip(domains)
+            .map(|(var, domain)| (name(var), DecisionVariable::new(domain)))
+            .collect();
+        for var in BOOL_VARS {
+            symbols.insert(name(var), DecisionVariable::new(Domain::BoolDomain));
+        }
+        symbols
+    })
+}
+
+fn int_expr() -> impl Strategy<Value = Expression> {
+    let leaf = prop_oneof![
+        (-3..=3).prop_map(|i| Expression::Constant(Metadata::new(), Constant::Int(i))),
+        prop::sample::select(&INT_VARS[..]).prop_map(reference),
+    ];
+    leaf.prop_recursive(3, 16, 3, |inner| {
+        let exprs = prop::collection::vec(inner.clone(), 1..=3);
+        prop_oneof![
+            exprs
+                .clone()
+                .prop_map(|es| Expression::Sum(Metadata::new(), Arc::new(es))),
+            exprs.prop_map(|es| Expression::Min(Metadata::new(), Arc::new(es))),
+            (inner.clone(), inner.clone()).prop_map(|(a, b)| Expression::UnsafeDiv(
+                Metadata::new(),
+                Arc::new(a),
+                Arc::new(b)
+            )),
+            (inner.clone(), inner.clone()).prop_map(|(a, b)| Expression::UnsafeMod(
+                Metadata::new(),
+                Arc::new(a),
+                Arc::new(b)
+            )),
+            (inner.clone(), inner).prop_map(|(a, b)| Expression::UnsafePow(
+                Metadata::new(),
+                Arc::new(a),
+                Arc::new(b)
+            )),
+        ]
+    })
+}
+
+fn bool_expr() -> impl Strategy<Value = Expression> {
+    let comparison = (0..6usize, int_expr(), int_expr()).prop_map(|(op, a, b)| {
+        let (a, b) = (Arc::new(a), Arc::new(b));
+        let md = Metadata::new();
+        match op {
+            0 => Expression::Eq(md, a, b),
+            1 => Expression::Neq(md, a, b),
+            2 => Expression::Lt(md, a, b),
+            3 => Expression::Gt(md, a, b),
+            4 => Expression::Leq(md, a, b),
+            _ => Expression::Geq(md, a, b),
+        }
+    });
+    let leaf = prop_oneof![
+        any::<bool>().prop_map(|b| Expression::Constant(Metadata::new(), Constant::Bool(b))),
+        prop::sample::select(&BOOL_VARS[..]).prop_map(reference),
+        comparison,
+        prop::collection::vec(int_expr(), 1..=3)
+            .prop_map(|es| Expression::AllDiff(Metadata::new(), Arc::new(es))),
+    ];
+    leaf.prop_recursive(3, 16, 3, |inner| {
+        let exprs = prop::collection::vec(inner.clone(), 1..=3);
+        prop_oneof![
+            inner.prop_map(|e| Expression::Not(Metadata::new(), Arc::new(e))),
+            exprs
+                .clone()
+                .prop_map(|es| Expression::And(Metadata::new(), Arc::new(es))),
+            exprs.prop_map(|es| Expression::Or(Metadata::new(), Arc::new(es))),
+        ]
+    })
+}
+
+fn values(domain: &Domain) -> Vec<Constant> {
+    match domain {
+        Domain::BoolDomain => vec![Constant::Bool(false), Constant::Bool(true)],
+        Domain::IntDomain(_) => domain
+            .values_i32()
+            .unwrap_or_default()
+            .into_iter()
+            .map(Constant::Int)
+            .collect(),
+    }
+}
+
+/// All assignments to the given variables, or an evenly spaced sample of at most `limit` of them.
+fn assignments(vars: &SymbolTable, limit: usize) -> Vec<Assignment> {
+    if vars.is_empty() {
+        return vec![Assignment::new()];
+    }
+    let names: Vec<&Name> = vars.keys().collect();
+    let all: Vec<Assignment> = names
+        .iter()
+        .map(|name| values(&vars[*name].domain))
+        .multi_cartesian_product()
+        .map(|vals| names.iter().map(|name| (*name).clone()).zip(vals).collect())
+        .collect();
+    let step = all.len().div_ceil(limit).max(1);
+    all.into_iter().step_by(step).collect()
+}
+
+fn substitute(expr: &Expression, assignment: &Assignment) -> Expression {
+    match expr {
+        Expression::Reference(_, name) => match assignment.get(name) {
+            Some(value) => Expression::Constant(Metadata::new(), value.clone()),
+            None => expr.clone(),
+        },
+        _ => expr
+            .with_children(
+                expr.children()
+                    .iter()
+                    .map(|child| substitute(child, assignment))
+                    .collect(),
+            )
+            .unwrap_or_else(|_| expr.clone()),
+    }
+}
+
+fn is_bool(expr: &Expression) -> bool {
+    match expr {
+        Expression::Bubble(_, a, _) => is_bool(a),
+        _ => expr.return_type() == Some(ReturnType::Bool),
+    }
+}
+
+/// Evaluates a fully assigned expression under the relational semantics, where a boolean
+/// expression is false if any of its integer subexpressions are undefined.
+fn eval_relational(expr: &Expression) -> Option<Constant> {
+    if !is_bool(expr) {
+        return eval_constant(expr);
+    }
+    let children = expr
+        .children()
+        .iter()
+        .map(|child| match is_bool(child) {
+            true => Some(Expression::Constant(
+                Metadata::new(),
+                eval_relational(child)?,
+            )),
+            false => Some(child.clone()),
+        })
+        .collect::<Option<_>>()?;
+    let expr = expr
+        .with_children(children)
+        .unwrap_or_else(|_| expr.clone());
+    Some(eval_constant(&expr).unwrap_or(Constant::Bool(false)))
+}
+
+/// Checks `reduction` against `original` under every assignment to `vars`.
+fn check_reduction(
+    rule: &str,
+    original: &Expression,
+    reduction: &Reduction,
+    vars: &SymbolTable,
+) -> Result<(), String> {
+    let aux_assignments = assignments(&reduction.symbols, MAX_AUX_ASSIGNMENTS);
+    for assignment in assignments(vars, usize::MAX) {
+        let expected = eval_relational(&substitute(original, &assignment));
+
+        let mut satisfiable = false;
+        for aux in &aux_assignments {
+            let mut full = assignment.clone();
+            full.extend(aux.clone());
+
+            if !reduction.new_top.is_nothing()
+                && eval_relational(&substitute(&reduction.new_top, &full))
+                    != Some(Constant::Bool(true))
+            {
+                continue;
+            }
+            satisfiable = true;
+
+            let actual = eval_relational(&substitute(&reduction.new_expression, &full));
+            if actual != expected {
+                return Err(format!(
+                    "rule {rule} rewrote {original} to {} (top: {}); \
+                     under {full:?} the original is {expected:?} but the result is {actual:?}",
+                    reduction.new_expression, reduction.new_top
+                ));
+            }
+        }
+
+        // Top-level constraints cannot be confined to the boolean context of an undefined integer,
+        // so they may fail outright where the original is undefined.
+        if !satisfiable && expected.is_some() {
+            return Err(format!(
+                "rule {rule} rewrote {original} to {} with top-level constraint {}, \
+                 which cannot be satisfied under {assignment:?}",
+                reduction.new_expression, reduction.new_top
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Applies every registered rule to every subexpression of `expr`, checking each reduction and
+/// each of its alternatives.
+fn check_rules(expr: &Expression, vars: &SymbolTable) -> Result<(), String> {
+    let mut model = Model::new_empty(Default::default());
+    model.variables = vars.clone();
+
+    for subexpr in expr.universe() {
+        for rule in get_rules() {
+            if !rule.applies_to(&subexpr) {
+                continue;
+            }
+            if let Ok(reduction) = rule.apply(&subexpr, &model) {
+                for alternative in reduction.into_alternatives() {
+                    check_reduction(rule.name, &subexpr, &alternative, vars)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn rules_preserve_semantics(vars in symbol_table(), expr in bool_expr()) {
+        if let Err(e) = check_rules(&expr, &vars) {
+            prop_assert!(false, "{}", e);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+/************************************************************************/
+/*        Rules for simplifying the whole model once rewritten          */
+/************************************************************************/
+
+use crate::ast::Domain;
+use crate::eliminate::eliminate_variables;
+use crate::propagate::{detect_unsatisfiable, propagate_bounds, Unsatisfiable};
+use crate::rule_engine::{register_rule, register_rule_set, ApplicationError};
+use crate::solver::SolverFamily;
+use crate::Model;
+
+// Model rules are only tried once no other rule applies, so these see the rewritten model.
+// Disabled on the command line with `--no-simplify`.
+register_rule_set!("Simplify", 90, ("Base"), (SolverFamily::Minion));
+
+/**
+ * Narrow the domains of the integer variables to the bounds implied by the top-level constraints
+ * (see [`propagate_bounds`]).
+ *
+ * If the constraints cannot hold, the model is marked unsatisfiable for [`detect_unsatisfiable`]: the empty
+ * domain is kept, or the constraints are replaced by `false`.
+ */
+#[register_rule(("Simplify", 20))]
+fn propagate_domain_bounds(model: &Model) -> Result<Model, ApplicationError> {
+    if detect_unsatisfiable(model).is_some() {
+        return Err(ApplicationError::RuleNotApplicable);
+    }
+
+    let mut new_model = model.clone();
+    match propagate_bounds(&mut new_model) {
+        Ok(()) if new_model.variables == model.variables => {
+            Err(ApplicationError::RuleNotApplicable)
+        }
+        Ok(()) => Ok(new_model),
+        Err(reason) => Ok(unsatisfiable(model, reason)),
+    }
+}
+
+/**
+ * Eliminate the variables that the top-level constraints fix to a constant or another variable (see
+ * [`eliminate_variables`]). Their values are added back to the solutions from [`Model::eliminated`].
+ */
+#[register_rule(("Simplify", 10))]
+fn eliminate_fixed_variables(model: &Model) -> Result<Model, ApplicationError> {
+    let mut new_model = model.clone();
+    eliminate_variables(&mut new_model);
+    if new_model.variables == model.variables
+        && new_model.constraints.structural_eq(&model.constraints)
+    {
+        return Err(ApplicationError::RuleNotApplicable);
+    }
+    Ok(new_model)
+}
+
+/// A copy of `model` that [`detect_unsatisfiable`] reports as unsatisfiable.
+fn unsatisfiable(model: &Model, reason: Unsatisfiable) -> Model {
+    let mut model = model.clone();
+    match reason {
+        Unsatisfiable::EmptyDomain(name)
+            if matches!(model.get_domain(&name), Some(Domain::IntDomain(_))) =>
+        {
+            model.update_domain(&name, Domain::IntDomain(vec![]));
+        }
+        _ => model.constraints = false.into(),
+    }
+    model
+}
@@ -1,14 +1,15 @@
 use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock};
 
 use regex::Regex;
 
 use minion_ast::Model as MinionModel;
 use minion_rs::ast as minion_ast;
 use minion_rs::error::MinionError;
-use minion_rs::{get_from_table, run_minion};
+use minion_rs::options::{MinionOptions, SearchEnd};
+use minion_rs::run_minion_with_options;
 
 use crate::ast as conjure_ast;
+use crate::eliminate::remove_unused_variables;
 use crate::solver::SolverCallback;
 use crate::solver::SolverFamily;
 use crate::solver::SolverMutCallback;
@@ -31,20 +32,15 @@ use super::super::SolverError::*;
 pub struct Minion {
     __non_constructable: private::Internal,
     model: Option<MinionModel>,
+    options: MinionOptions,
 }
 
-static MINION_LOCK: Mutex<()> = Mutex::new(());
-static USER_CALLBACK: OnceLock<Mutex<SolverCallback>> = OnceLock::new();
-static ANY_SOLUTIONS: Mutex<bool> = Mutex::new(false);
-static USER_TERMINATED: Mutex<bool> = Mutex::new(false);
-
+/// Converts a solution from Minion's names and values to Conjure's.
 #[allow(clippy::unwrap_used)]
-fn minion_rs_callback(solutions: HashMap<minion_ast::VarName, minion_ast::Constant>) -> bool {
-    *(ANY_SOLUTIONS.lock().unwrap()) = true;
-    let callback = USER_CALLBACK
-        .get_or_init(|| Mutex::new(Box::new(|x| true)))
-        .lock()
-        .unwrap();
+fn to_conjure_solution(
+    solutions: HashMap<minion_ast::VarName, minion_ast::Constant>,
+) -> HashMap<conjure_ast::Name, conjure_ast::Constant> {
+    let machine_name_re = Regex::new(r"__conjure_machine_name_([0-9]+)").unwrap();
 
     let mut conjure_solutions: HashMap<conjure_ast::Name, conjure_ast::Constant> = HashMap::new();
     for (minion_name, minion_const) in solutions.into_iter() {
@@ -54,7 +50,6 @@ fn minion_rs_callback(solutions: HashMap<minion_ast::VarName, minion_ast::Consta
             _ => todo!(),
         };
 
-        let machine_name_re = Regex::new(r"__conjure_machine_name_([0-9]+)").unwrap();
         let conjure_name = if let Some(caps) = machine_name_re.captures(&minion_name) {
             conjure_ast::Name::MachineName(caps[1].parse::<i32>().unwrap())
         } else {
@@ -64,21 +59,24 @@ fn minion_rs_callback(solutions: HashMap<minion_ast::VarName, minion_ast::Consta
         conjure_solutions.insert(conjure_name, conjure_const);
     }
 
-    let continue_search = (**callback)(conjure_solutions);
-    if !continue_search {
-        *(USER_TERMINATED.lock().unwrap()) = true;
-    }
-
-    continue_search
+    conjure_solutions
 }
 
 impl private::Sealed for Minion {}
 
 impl Minion {
     pub fn new() -> Minion {
+        Minion::with_options(MinionOptions::default())
+    }
+
+    /// Creates a Minion adaptor that searches with the given options.
+    ///
+    /// If a time or node limit is reached, the search status is [`Incomplete(Timeout)`](Timeout).
+    pub fn with_options(options: MinionOptions) -> Minion {
         Minion {
             __non_constructable: private::Internal,
             model: None,
+            options,
         }
     }
 }
@@ -90,29 +88,23 @@ impl Default for Minion {
 }
 
 impl SolverAdaptor for Minion {
-    #[allow(clippy::unwrap_used)]
     fn solve(
         &mut self,
         callback: SolverCallback,
         _: private::Internal,
     ) -> Result<SolveSuccess, SolverError> {
-        // our minion callback is global state, so single threading the adaptor as a whole is
-        // probably a good move...
-        #[allow(clippy::unwrap_used)]
-        let mut minion_lock = MINION_LOCK.lock().unwrap();
-
-        #[allow(clippy::unwrap_used)]
-        let mut user_callback = USER_CALLBACK
-            .get_or_init(|| Mutex::new(Box::new(|x| true)))
-            .lock()
-            .unwrap();
-        *user_callback = callback;
-        drop(user_callback); // release mutex. REQUIRED so that run_minion can use the
-                             // user callback and not deadlock.
+        let mut any_solutions = false;
+        let mut user_terminated = false;
 
-        run_minion(
+        let run_stats = run_minion_with_options(
             self.model.clone().expect("STATE MACHINE ERR"),
-            minion_rs_callback,
+            |solutions| {
+                any_solutions = true;
+                let continue_search = callback(to_conjure_solution(solutions));
+                user_terminated = !continue_search;
+                continue_search
+            },
+            &self.options,
         )
         .map_err(|err| match err {
             MinionError::RuntimeError(x) => Runtime(format!("{:#?}", x)),
@@ -121,14 +113,20 @@ impl SolverAdaptor for Minion {
             x => Runtime(format!("unknown minion_rs error: {:#?}", x)),
         })?;
 
-        let mut status = Complete(HasSolutions);
-        if *(USER_TERMINATED.lock()).unwrap() {
-            status = Incomplete(UserTerminated);
-        } else if *(ANY_SOLUTIONS.lock()).unwrap() {
-            status = Complete(NoSolutions);
-        }
+        let status = if user_terminated {
+            Incomplete(UserTerminated)
+        } else if run_stats.search_end != SearchEnd::Finished {
+            Incomplete(Timeout)
+        } else if any_solutions {
+            Complete(HasSolutions)
+        } else {
+            Complete(NoSolutions)
+        };
         Ok(SolveSuccess {
-            stats: get_solver_stats(),
+            stats: SolverStats {
+                nodes: run_stats.nodes,
+                ..Default::default()
+            },
             status,
         })
     }
@@ -142,6 +140,8 @@ impl SolverAdaptor for Minion {
     }
 
     fn load_model(&mut self, model: ConjureModel, _: private::Internal) -> Result<(), SolverError> {
+        let mut model = model;
+        remove_unused_variables(&mut model);
         let mut minion_model = MinionModel::new();
         parse_vars(&model, &mut minion_model)?;
         parse_exprs(&model, &mut minion_model)?;
@@ -162,7 +162,7 @@ fn parse_vars(
     conjure_model: &ConjureModel,
     minion_model: &mut MinionModel,
 ) -> Result<(), SolverError> {
-    // TODO (niklasdewally): remove unused vars?
+    // Unused auxiliary variables were removed in load_model
     // TODO (niklasdewally): ensure all vars references are used.
 
     for (name, variable) in conjure_model.variables.iter() {
@@ -177,44 +177,75 @@ fn parse_var(
     minion_model: &mut MinionModel,
 ) -> Result<(), SolverError> {
     match &var.domain {
-        conjure_ast::Domain::IntDomain(ranges) => _parse_intdomain_var(name, ranges, minion_model),
+        domain @ conjure_ast::Domain::IntDomain(_) => {
+            _parse_intdomain_var(name, domain, minion_model)
+        }
         conjure_ast::Domain::BoolDomain => _parse_booldomain_var(name, minion_model),
+        #[allow(unreachable_patterns)]
         x => Err(ModelFeatureNotSupported(format!("{:?}", x))),
     }
 }
 
+/// Integer domains spanning at most this many values are DISCRETE variables.
+const MAX_DISCRETE_VALUES: i64 = 10_000;
+
+/// Larger integer domains with at most this many values are SPARSEBOUND variables, and the rest are BOUND
+/// variables. The holes in DISCRETE and BOUND variables are removed with a `w-inintervalset` constraint.
+const MAX_SPARSE_VALUES: i64 = 10_000;
+
 fn _parse_intdomain_var(
     name: &conjure_ast::Name,
-    ranges: &[conjure_ast::Range<i32>],
+    domain: &conjure_ast::Domain,
     minion_model: &mut MinionModel,
 ) -> Result<(), SolverError> {
     let str_name = _name_to_string(name.to_owned());
 
-    if ranges.len() != 1 {
-        return Err(ModelFeatureNotImplemented(format!(
-            "variable {:?} has {:?} ranges. Multiple ranges / SparseBound is not yet supported.",
-            str_name,
-            ranges.len()
+    let domain = domain.simplified();
+    let conjure_ast::Domain::IntDomain(ranges) = &domain else {
+        return Err(ModelInvalid(format!(
+            "variable {:?} does not have an integer domain",
+            str_name
+        )));
+    };
+    let bounds: Vec<(i32, i32)> = ranges.iter().map(conjure_ast::Range::bounds).collect();
+    let (Some((low, _)), Some((_, high))) = (bounds.first(), bounds.last()) else {
+        return Err(ModelInvalid(format!(
+            "variable {:?} has an empty domain",
+            str_name
         )));
+    };
+    let span = i64::from(*high) - i64::from(*low) + 1;
+    let count: i64 = bounds
+        .iter()
+        .map(|(x, y)| i64::from(*y) - i64::from(*x) + 1)
+        .sum();
+
+    let minion_domain = if span <= MAX_DISCRETE_VALUES {
+        minion_ast::VarDomain::Discrete(*low, *high)
+    } else if count <= MAX_SPARSE_VALUES {
+        minion_ast::VarDomain::SparseBound(bounds.iter().flat_map(|(x, y)| *x..=*y).collect())
+    } else {
+        minion_ast::VarDomain::Bound(*low, *high)
+    };
+
+    if bounds.len() > 1 && !matches!(minion_domain, minion_ast::VarDomain::SparseBound(_)) {
+        minion_model
+            .constraints
+            .push(minion_ast::Constraint::WInIntervalSet(
+                minion_ast::Var::NameRef(str_name.clone()),
+                bounds
+                    .iter()
+                    .flat_map(|(x, y)| {
+                        [
+                            minion_ast::Constant::Integer(*x),
+                            minion_ast::Constant::Integer(*y),
+                        ]
+                    })
+                    .collect(),
+            ));
     }
 
-    let range = ranges.first().ok_or(ModelInvalid(format!(
-        "variable {:?} has no range",
-        str_name
-    )))?;
-
-    let (low, high) = match range {
-        conjure_ast::Range::Bounded(x, y) => Ok((x.to_owned(), y.to_owned())),
-        conjure_ast::Range::Single(x) => Ok((x.to_owned(), x.to_owned())),
-        #[allow(unreachable_patterns)]
-        x => Err(ModelFeatureNotSupported(format!("{:?}", x))),
-    }?;
-
-    _try_add_var(
-        str_name.to_owned(),
-        minion_ast::VarDomain::Bound(low, high),
-        minion_model,
-    )
+    _try_add_var(str_name.to_owned(), minion_domain, minion_model)
 }
 
 fn _parse_booldomain_var(
@@ -264,40 +295,119 @@ fn parse_expr(
 fn read_expr(expr: conjure_ast::Expression) -> Result<minion_ast::Constraint, SolverError> {
     match expr {
         conjure_ast::Expression::SumLeq(_metadata, lhs, rhs) => Ok(minion_ast::Constraint::SumLeq(
-            read_vars(lhs)?,
-            read_var(*rhs)?,
+            read_vars(&lhs)?,
+            read_var(rhs.as_ref().clone())?,
         )),
         conjure_ast::Expression::SumGeq(_metadata, lhs, rhs) => Ok(minion_ast::Constraint::SumGeq(
-            read_vars(lhs)?,
-            read_var(*rhs)?,
+            read_vars(&lhs)?,
+            read_var(rhs.as_ref().clone())?,
         )),
         conjure_ast::Expression::Ineq(_metadata, a, b, c) => Ok(minion_ast::Constraint::Ineq(
-            read_var(*a)?,
-            read_var(*b)?,
-            minion_ast::Constant::Integer(read_const(*c)?),
+            read_var(a.as_ref().clone())?,
+            read_var(b.as_ref().clone())?,
+            minion_ast::Constant::Integer(read_const(c.as_ref().clone())?),
+        )),
+        conjure_ast::Expression::Neq(_metadata, a, b) => Ok(minion_ast::Constraint::DisEq(
+            read_var(a.as_ref().clone())?,
+            read_var(b.as_ref().clone())?,
         )),
-        conjure_ast::Expression::Neq(_metadata, a, b) => {
-            Ok(minion_ast::Constraint::DisEq(read_var(*a)?, read_var(*b)?))
+        conjure_ast::Expression::DivEq(_metadata, a, b, c) => {
+            Ok(minion_ast::Constraint::DivUndefZero(
+                (read_var(a.as_ref().clone())?, read_var(b.as_ref().clone())?),
+                read_var(c.as_ref().clone())?,
+            ))
         }
-        conjure_ast::Expression::DivEq(_metadata, a, b, c) => Ok(
-            minion_ast::Constraint::DivUndefZero((read_var(*a)?, read_var(*b)?), read_var(*c)?),
-        ),
-        conjure_ast::Expression::Or(_metadata, exprs) => Ok(minion_ast::Constraint::WatchedOr(
-            exprs
-                .iter()
-                .map(|x| read_expr(x.to_owned()))
-                .collect::<Result<Vec<minion_ast::Constraint>, SolverError>>()?,
+        conjure_ast::Expression::ModEq(_metadata, a, b, c) => {
+            Ok(minion_ast::Constraint::ModuloUndefZero(
+                (read_var(a.as_ref().clone())?, read_var(b.as_ref().clone())?),
+                read_var(c.as_ref().clone())?,
+            ))
+        }
+        conjure_ast::Expression::PowEq(_metadata, a, b, c) => Ok(minion_ast::Constraint::Pow(
+            (read_var(a.as_ref().clone())?, read_var(b.as_ref().clone())?),
+            read_var(c.as_ref().clone())?,
         )),
-        conjure_ast::Expression::Eq(_metadata, a, b) => {
-            Ok(minion_ast::Constraint::Eq(read_var(*a)?, read_var(*b)?))
+        conjure_ast::Expression::Or(_metadata, exprs) => {
+            Ok(minion_ast::Constraint::WatchedOr(read_exprs(&exprs)?))
+        }
+        conjure_ast::Expression::And(_metadata, exprs) => {
+            Ok(minion_ast::Constraint::WatchedAnd(read_exprs(&exprs)?))
+        }
+        conjure_ast::Expression::AllDiff(_metadata, exprs) => {
+            Ok(minion_ast::Constraint::GacAllDiff(read_vars(&exprs)?))
+        }
+        conjure_ast::Expression::MinEq(_metadata, exprs, a) => Ok(minion_ast::Constraint::Min(
+            read_vars(&exprs)?,
+            read_var(a.as_ref().clone())?,
+        )),
+        conjure_ast::Expression::Reference(_metadata, name) => {
+            Ok(minion_ast::Constraint::WLiteral(
+                minion_ast::Var::NameRef(_name_to_string(name)),
+                minion_ast::Constant::Integer(1),
+            ))
+        }
+        conjure_ast::Expression::Not(_metadata, expr) => match expr.as_ref() {
+            conjure_ast::Expression::Reference(_, name) => Ok(minion_ast::Constraint::WNotLiteral(
+                minion_ast::Var::NameRef(_name_to_string(name.clone())),
+                minion_ast::Constant::Integer(1),
+            )),
+            // c <-> false
+            e => Ok(minion_ast::Constraint::Reify(
+                Box::new(read_expr(e.clone())?),
+                minion_ast::Var::ConstantAsVar(0),
+            )),
+        },
+        // An empty conjunction always holds and an empty disjunction never does
+        conjure_ast::Expression::Constant(_metadata, conjure_ast::Constant::Bool(true)) => {
+            Ok(minion_ast::Constraint::WatchedAnd(vec![]))
+        }
+        conjure_ast::Expression::Constant(_metadata, conjure_ast::Constant::Bool(false)) => {
+            Ok(minion_ast::Constraint::WatchedOr(vec![]))
         }
+        conjure_ast::Expression::Eq(_metadata, a, b) => read_eq(a.as_ref(), b.as_ref()),
         x => Err(ModelFeatureNotSupported(format!("{:?}", x))),
     }
 }
-fn read_vars(exprs: Vec<conjure_ast::Expression>) -> Result<Vec<minion_ast::Var>, SolverError> {
+
+fn read_exprs(
+    exprs: &[conjure_ast::Expression],
+) -> Result<Vec<minion_ast::Constraint>, SolverError> {
+    exprs.iter().map(|x| read_expr(x.to_owned())).collect()
+}
+
+/// Read `a = b`, where either side may also be a sum, or a constraint whose truth is given by the other side.
+fn read_eq(
+    a: &conjure_ast::Expression,
+    b: &conjure_ast::Expression,
+) -> Result<minion_ast::Constraint, SolverError> {
+    use conjure_ast::Expression::Sum;
+    match (a, b) {
+        (Sum(_, exprs), x) | (x, Sum(_, exprs)) => {
+            let (vars, total) = (read_vars(exprs)?, read_var(x.clone())?);
+            Ok(minion_ast::Constraint::WatchedAnd(vec![
+                minion_ast::Constraint::SumLeq(vars.clone(), total.clone()),
+                minion_ast::Constraint::SumGeq(vars, total),
+            ]))
+        }
+        _ => match (read_var(a.clone()), read_var(b.clone())) {
+            (Ok(a), Ok(b)) => Ok(minion_ast::Constraint::Eq(a, b)),
+            (Err(_), Ok(var)) => Ok(minion_ast::Constraint::Reify(
+                Box::new(read_expr(a.clone())?),
+                var,
+            )),
+            (Ok(var), Err(_)) => Ok(minion_ast::Constraint::Reify(
+                Box::new(read_expr(b.clone())?),
+                var,
+            )),
+            (Err(x), Err(_)) => Err(x),
+        },
+    }
+}
+
+fn read_vars(exprs: &[conjure_ast::Expression]) -> Result<Vec<minion_ast::Var>, SolverError> {
     let mut minion_vars: Vec<minion_ast::Var> = vec![];
     for expr in exprs {
-        let minion_var = read_var(expr)?;
+        let minion_var = read_var(expr.clone())?;
         minion_vars.push(minion_var);
     }
     Ok(minion_vars)
@@ -330,6 +440,7 @@ fn _read_ref(e: conjure_ast::Expression) -> Result<String, SolverError> {
 fn read_const(e: conjure_ast::Expression) -> Result<i32, SolverError> {
     match e {
         conjure_ast::Expression::Constant(_, conjure_ast::Constant::Int(n)) => Ok(n),
+        conjure_ast::Expression::Constant(_, conjure_ast::Constant::Bool(b)) => Ok(b.into()),
         x => Err(ModelInvalid(format!(
             "expected a constant, but got `{0:?}`",
             x
@@ -344,10 +455,255 @@ fn _name_to_string(name: conjure_ast::Name) -> String {
     }
 }
 
-#[allow(clippy::unwrap_used)]
-fn get_solver_stats() -> SolverStats {
-    SolverStats {
-        nodes: get_from_table("Nodes".into()).map(|x| x.parse::<u64>().unwrap()),
-        ..Default::default()
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use uniplate::uniplate::Uniplate;
+
+    use crate::ast::{Constant, Expression, Name};
+    use crate::metadata::Metadata;
+
+    use super::*;
+
+    fn var(name: &str) -> Expression {
+        Expression::Reference(Metadata::new(), Name::UserName(name.to_string()))
+    }
+
+    fn minion_var(name: &str) -> minion_ast::Var {
+        minion_ast::Var::NameRef(name.to_string())
+    }
+
+    /// A constraint that uses the given Minion-compatible variant.
+    fn example(variant: &str) -> Expression {
+        let m = Metadata::new;
+        let (x, y, z, p, q) = (var("x"), var("y"), var("z"), var("p"), var("q"));
+        match variant {
+            "Constant" => Expression::Constant(m(), Constant::Bool(true)),
+            "Reference" => p,
+            "Sum" => Expression::Eq(
+                m(),
+                Arc::new(Expression::Sum(m(), Arc::new(vec![x, y]))),
+                Arc::new(z),
+            ),
+            "Not" => Expression::Not(m(), Arc::new(p)),
+            "Or" => Expression::Or(m(), Arc::new(vec![p, Expression::Not(m(), Arc::new(q))])),
+            "And" => Expression::And(m(), Arc::new(vec![p, q])),
+            "Eq" => Expression::Eq(m(), Arc::new(x), Arc::new(y)),
+            "Neq" => Expression::Neq(m(), Arc::new(x), Arc::new(y)),
+            "SumGeq" => Expression::SumGeq(m(), Arc::new(vec![x, y]), Arc::new(z)),
+            "SumLeq" => Expression::SumLeq(m(), Arc::new(vec![x, y]), Arc::new(z)),
+            "DivEq" => Expression::DivEq(m(), Arc::new(x), Arc::new(y), Arc::new(z)),
+            "ModEq" => Expression::ModEq(m(), Arc::new(x), Arc::new(y), Arc::new(z)),
+            "PowEq" => Expression::PowEq(m(), Arc::new(x), Arc::new(y), Arc::new(z)),
+            "Ineq" => Expression::Ineq(m(), Arc::new(x), Arc::new(y), Arc::new(1.into())),
+            "AllDiff" => Expression::AllDiff(m(), Arc::new(vec![x, y, z])),
+            "MinEq" => Expression::MinEq(m(), Arc::new(vec![x, y]), Arc::new(z)),
+            _ => panic!("no example constraint for the Minion-compatible variant {variant}"),
+        }
+    }
+
+    fn substitute(expr: &Expression, values: &HashMap<Name, Constant>) -> Expression {
+        match expr {
+            Expression::Reference(m, name) => Expression::Constant(m.clone(), values[name].clone()),
+            _ => expr
+                .with_children(
+                    expr.children()
+                        .iter()
+                        .map(|e| substitute(e, values))
+                        .collect(),
+                )
+                .unwrap(),
+        }
+    }
+
+    /// The numbers of solutions of `expr`, over `x`, `y` and `z` in `0..=2` and booleans `p` and `q`, found by
+    /// Minion and by evaluating it under every assignment.
+    fn solution_counts(expr: Expression) -> (usize, usize) {
+        use std::sync::Mutex;
+
+        use conjure_ast::{DecisionVariable, Domain, Range, SymbolTable};
+
+        use crate::rules::eval_constant;
+        use crate::solver::Solver;
+        use crate::Model;
+
+        let ints = ["x", "y", "z"].map(|n| Name::UserName(n.to_string()));
+        let bools = ["p", "q"].map(|n| Name::UserName(n.to_string()));
+        let mut symbols = SymbolTable::new();
+        for name in &ints {
+            let domain = Domain::IntDomain(vec![Range::Bounded(0, 2)]);
+            symbols.insert(name.clone(), DecisionVariable::new(domain));
+        }
+        for name in &bools {
+            symbols.insert(name.clone(), DecisionVariable::new(Domain::BoolDomain));
+        }
+
+        let mut assignments = vec![HashMap::new()];
+        for name in &ints {
+            let values = (0..=2).map(Constant::Int);
+            assignments = assignments
+                .into_iter()
+                .flat_map(|a| values.clone().map(move |v| (a.clone(), v)))
+                .map(|(mut a, v)| {
+                    a.insert(name.clone(), v);
+                    a
+                })
+                .collect();
+        }
+        for name in &bools {
+            let values = [false, true].map(Constant::Bool);
+            assignments = assignments
+                .into_iter()
+                .flat_map(|a| values.clone().map(move |v| (a.clone(), v)))
+                .map(|(mut a, v)| {
+                    a.insert(name.clone(), v);
+                    a
+                })
+                .collect();
+        }
+        let expected = assignments
+            .iter()
+            .filter(|a| eval_constant(&substitute(&expr, a)) == Some(Constant::Bool(true)))
+            .count();
+
+        let model = Model::new(symbols, expr, Default::default());
+        let count = Arc::new(Mutex::new(0));
+        let count_ref = count.clone();
+        Solver::new(Minion::new())
+            .load_model(model)
+            .unwrap()
+            .solve(Box::new(move |_| {
+                *count_ref.lock().unwrap() += 1;
+                true
+            }))
+            .unwrap();
+        let found = *count.lock().unwrap();
+        (found, expected)
+    }
+
+    #[test]
+    fn every_minion_compatible_variant_is_solved() {
+        for variant in Expression::compatible_variants("Minion") {
+            let expr = example(variant);
+            assert!(expr
+                .universe()
+                .iter()
+                .any(|e| <&'static str>::from(e) == *variant));
+            let (found, expected) = solution_counts(expr);
+            assert_eq!(found, expected, "solutions of the {variant} example");
+        }
+    }
+
+    #[test]
+    fn constant_constraints_are_solved() {
+        let m = Metadata::new;
+        let (all, none) = (3 * 3 * 3 * 2 * 2, 0);
+        assert_eq!(
+            solution_counts(Expression::Constant(m(), Constant::Bool(true))),
+            (all, all)
+        );
+        assert_eq!(
+            solution_counts(Expression::Constant(m(), Constant::Bool(false))),
+            (none, none)
+        );
+    }
+
+    #[test]
+    fn node_limit_stops_search_as_a_timeout() {
+        use conjure_ast::{DecisionVariable, Domain, Range, SymbolTable};
+
+        let mut symbols = SymbolTable::new();
+        for name in ["x", "y", "z"] {
+            let domain = Domain::IntDomain(vec![Range::Bounded(0, 9)]);
+            symbols.insert(Name::UserName(name.into()), DecisionVariable::new(domain));
+        }
+        let model = ConjureModel::new(
+            symbols,
+            Expression::Constant(Metadata::new(), Constant::Bool(true)),
+            Default::default(),
+        );
+
+        let mut minion = Minion::with_options(MinionOptions::new().node_limit(10));
+        minion.load_model(model, private::Internal).unwrap();
+        let result = minion.solve(Box::new(|_| true), private::Internal).unwrap();
+        assert!(matches!(result.status, Incomplete(Timeout)));
+    }
+
+    #[test]
+    fn boolean_constraints_use_literals_and_reification() {
+        let m = Metadata::new;
+        let not_p = Expression::Not(m(), Arc::new(var("p")));
+        assert_eq!(
+            read_expr(not_p).unwrap(),
+            minion_ast::Constraint::WNotLiteral(minion_var("p"), minion_ast::Constant::Integer(1))
+        );
+
+        // p = (x != y)
+        let neq = Expression::Neq(m(), Arc::new(var("x")), Arc::new(var("y")));
+        let reified = Expression::Eq(m(), Arc::new(var("p")), Arc::new(neq));
+        assert_eq!(
+            read_expr(reified).unwrap(),
+            minion_ast::Constraint::Reify(
+                Box::new(minion_ast::Constraint::DisEq(
+                    minion_var("x"),
+                    minion_var("y")
+                )),
+                minion_var("p")
+            )
+        );
+    }
+
+    #[test]
+    fn int_domains_pick_variable_kinds_and_keep_holes() {
+        use conjure_ast::{DecisionVariable, Domain, Range};
+        use minion_ast::VarDomain;
+
+        let interval_set = |bounds: &[i32]| {
+            vec![minion_ast::Constraint::WInIntervalSet(
+                minion_var("x"),
+                bounds
+                    .iter()
+                    .map(|x| minion_ast::Constant::Integer(*x))
+                    .collect(),
+            )]
+        };
+        let cases = [
+            (
+                vec![Range::Bounded(1, 3)],
+                VarDomain::Discrete(1, 3),
+                vec![],
+            ),
+            (
+                vec![Range::Bounded(0, 100_000)],
+                VarDomain::Bound(0, 100_000),
+                vec![],
+            ),
+            (
+                vec![Range::Single(1), Range::Bounded(3, 4)],
+                VarDomain::Discrete(1, 4),
+                interval_set(&[1, 1, 3, 4]),
+            ),
+            (
+                vec![Range::Bounded(0, 20_000), Range::Single(30_000)],
+                VarDomain::Bound(0, 30_000),
+                interval_set(&[0, 20_000, 30_000, 30_000]),
+            ),
+            (
+                vec![Range::Bounded(-1, 1), Range::Single(50_000)],
+                VarDomain::SparseBound(vec![-1, 0, 1, 50_000]),
+                vec![],
+            ),
+        ];
+        for (ranges, expected_domain, expected_constraints) in cases {
+            let mut minion_model = MinionModel::new();
+            let var = DecisionVariable::new(Domain::IntDomain(ranges));
+            parse_var(&Name::UserName("x".into()), &var, &mut minion_model).unwrap();
+            assert_eq!(
+                minion_model.named_variables.get_vartype("x".into()),
+                Some(expected_domain)
+            );
+            assert_eq!(minion_model.constraints, expected_constraints);
+        }
     }
 }
@@ -2,6 +2,7 @@
 //! Primarily, this is CNF related code.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use thiserror::Error;
 
@@ -145,7 +146,10 @@ impl CNFModel {
             expr_clauses.push(self.clause_to_expression(clause)?);
         }
 
-        Ok(conjure_ast::Expression::And(Metadata::new(), expr_clauses))
+        Ok(conjure_ast::Expression::And(
+            Metadata::new(),
+            expr_clauses.into(),
+        ))
     }
 
     /**
@@ -168,14 +172,14 @@ impl CNFModel {
                             conjure_ast::Expression::Reference(Metadata::new(), name.clone());
                         ans.push(conjure_ast::Expression::Not(
                             Metadata::new(),
-                            Box::from(expression),
+                            Arc::new(expression),
                         ))
                     }
                 }
             }
         }
 
-        Ok(conjure_ast::Expression::Or(Metadata::new(), ans))
+        Ok(conjure_ast::Expression::Or(Metadata::new(), ans.into()))
     }
 
     /**
@@ -413,6 +413,7 @@ pub enum SearchStatus {
 
 #[non_exhaustive]
 pub enum SearchIncomplete {
+    /// A time or node limit was reached.
     Timeout,
     UserTerminated,
     #[doc(hidden)]
@@ -1,7 +1,7 @@
 mod rewriter_stats;
 mod solver_stats;
 
-pub use rewriter_stats::RewriterStats;
+pub use rewriter_stats::{RewriterStats, RuleStats};
 use schemars::JsonSchema;
 use serde::Serialize;
 use serde_with::skip_serializing_none;
@@ -1,3 +1,6 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use schemars::JsonSchema;
 use serde::Serialize;
 use serde_with::skip_serializing_none;
@@ -12,4 +15,45 @@ pub struct RewriterStats {
     pub rewriter_run_time: Option<std::time::Duration>,
     pub rewriter_rule_application_attempts: Option<usize>,
     pub rewriter_rule_applications: Option<usize>,
+    /// The number of passes over the model. Each pass applies at most one rule, and the last
+    /// pass finds no rule to apply.
+    pub rewriter_passes: Option<usize>,
+    /// Statistics for each rule that was attempted, by rule name.
+    pub rule_stats: BTreeMap<String, RuleStats>,
+    /// Totals of `rule_stats` over the rules of each enabled rule set, by rule set name.
+    /// A rule in several rule sets counts towards each of them.
+    pub rule_set_stats: BTreeMap<String, RuleStats>,
+}
+
+/// Statistics for one rule, or the total for a rule set, over a rewriter run.
+#[derive(Default, Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleStats {
+    pub attempts: usize,
+    pub applications: usize,
+    /// Total time spent applying the rule, whether or not it applied.
+    pub time: Duration,
+}
+
+impl RewriterStats {
+    /// Record an attempt to apply the named rule, which took `time`.
+    pub fn record_rule_attempt(&mut self, rule: &str, applied: bool, time: Duration) {
+        let stats = match self.rule_stats.get_mut(rule) {
+            Some(stats) => stats,
+            None => self.rule_stats.entry(rule.to_string()).or_default(),
+        };
+        stats.add(RuleStats {
+            attempts: 1,
+            applications: usize::from(applied),
+            time,
+        });
+    }
+}
+
+impl RuleStats {
+    pub fn add(&mut self, other: RuleStats) {
+        self.attempts += other.attempts;
+        self.applications += other.applications;
+        self.time += other.time;
+    }
 }
@@ -26,6 +26,9 @@ pub struct SolverStats {
     pub satisfiable: Option<bool>,
     pub sat_vars: Option<u64>,
     pub sat_clauses: Option<u64>,
+
+    /// Why the model was found to have no solutions after rewriting, without running the solver.
+    pub unsatisfiable_during_rewriting: Option<String>,
 }
 
 impl SolverStats {
@@ -0,0 +1,19 @@
+//! Helpers for building models in unit tests.
+
+use crate::ast::{Domain, Expression, Name, Range};
+use crate::metadata::Metadata;
+
+/// The user variable called `var`.
+pub(crate) fn name(var: &str) -> Name {
+    Name::UserName(var.to_string())
+}
+
+/// A reference to the user variable called `var`.
+pub(crate) fn var(var: &str) -> Expression {
+    Expression::Reference(Metadata::new(), name(var))
+}
+
+/// The integer domain `lo..hi`.
+pub(crate) fn int(lo: i32, hi: i32) -> Domain {
+    Domain::IntDomain(vec![Range::Bounded(lo, hi)])
+}
@@ -5,10 +5,12 @@ use quote::quote;
 use syn::punctuated::Punctuated;
 use syn::token::Comma;
 use syn::{
-    parenthesized, parse::Parse, parse::ParseStream, parse_macro_input, Ident, ItemFn, LitInt,
-    LitStr, Path, Result,
+    parenthesized, parse::Parse, parse::ParseStream, parse_macro_input, Attribute, Expr, ExprLit,
+    Ident, ItemFn, Lit, LitInt, LitStr, Meta, Path, Result,
 };
 
+mod pattern_rule;
+
 #[derive(Debug)]
 struct RuleSetAndPriority {
     rule_set: LitStr,
@@ -29,19 +31,111 @@ impl Parse for RuleSetAndPriority {
 #[derive(Debug)]
 struct RegisterRuleArgs {
     pub rule_sets: Vec<RuleSetAndPriority>,
+    pub applies_to: Option<Vec<Ident>>,
 }
 
 impl Parse for RegisterRuleArgs {
     fn parse(input: ParseStream) -> Result<Self> {
-        let rule_sets = Punctuated::<RuleSetAndPriority, Comma>::parse_terminated(input)?;
+        let mut rule_sets = Vec::new();
+        let mut applies_to = None;
+        while !input.is_empty() {
+            if input.peek(Ident) {
+                let keyword: Ident = input.parse()?;
+                if keyword != "applies_to" {
+                    return Err(syn::Error::new(
+                        keyword.span(),
+                        "expected a (rule set, priority) pair or `applies_to(...)`",
+                    ));
+                }
+                if applies_to.is_some() {
+                    return Err(syn::Error::new(
+                        keyword.span(),
+                        "`applies_to` may only be given once",
+                    ));
+                }
+                let content;
+                parenthesized!(content in input);
+                let variants = Punctuated::<Ident, Comma>::parse_terminated(&content)?;
+                if variants.is_empty() {
+                    return Err(syn::Error::new(
+                        keyword.span(),
+                        "`applies_to` needs at least one expression variant",
+                    ));
+                }
+                applies_to = Some(variants.into_iter().collect());
+            } else {
+                rule_sets.push(input.parse()?);
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Comma>()?;
+        }
         Ok(RegisterRuleArgs {
-            rule_sets: rule_sets.into_iter().collect(),
+            rule_sets,
+            applies_to,
         })
     }
 }
 
+/// Collect the doc comments in `attrs` into one string, as rustdoc would show them.
+///
+/// Block comments lose the leading `*` of each line, and the common indentation is removed.
+fn doc_comment(attrs: &[Attribute]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for attr in attrs {
+        let Meta::NameValue(meta) = &attr.meta else {
+            continue;
+        };
+        let Expr::Lit(ExprLit {
+            lit: Lit::Str(doc), ..
+        }) = &meta.value
+        else {
+            continue;
+        };
+        if !meta.path.is_ident("doc") {
+            continue;
+        }
+
+        let doc = doc.value();
+        if doc.contains('\n') {
+            lines.extend(doc.lines().map(|line| {
+                line.trim_start()
+                    .strip_prefix('*')
+                    .unwrap_or(line)
+                    .to_string()
+            }));
+        } else {
+            lines.push(doc);
+        }
+    }
+
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect();
+
+    let start = lines.iter().position(|line| !line.is_empty());
+    let end = lines.iter().rposition(|line| !line.is_empty());
+    match (start, end) {
+        (Some(start), Some(end)) => lines[start..=end].join("\n"),
+        _ => String::new(),
+    }
+}
+
 /**
  * Register a rule with the given rule sets and priorities.
+ *
+ * Optionally, `applies_to(<Variant>, ...)` restricts the rule to the given `Expression` variants.
+ * The rewriter will not attempt the rule on any other kind of expression.
+ *
+ * A function taking only a `&Model` is registered as a model rule, which cannot use `applies_to`.
  */
 #[proc_macro_attribute]
 pub fn register_rule(arg_tokens: TokenStream, item: TokenStream) -> TokenStream {
@@ -50,7 +144,24 @@ pub fn register_rule(arg_tokens: TokenStream, item: TokenStream) -> TokenStream
     let static_name = format!("CONJURE_GEN_RULE_{}", rule_ident).to_uppercase();
     let static_ident = Ident::new(&static_name, rule_ident.span());
 
+    let doc = doc_comment(&func.attrs);
+
     let args = parse_macro_input!(arg_tokens as RegisterRuleArgs);
+
+    let is_model_rule = func.sig.inputs.len() == 1;
+    if is_model_rule && args.applies_to.is_some() {
+        return syn::Error::new(
+            func.sig.ident.span(),
+            "model rules apply to the whole model, so cannot use `applies_to`",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let application = if is_model_rule {
+        quote! { ::conjure_core::rule_engine::Application::Model(&#rule_ident) }
+    } else {
+        quote! { ::conjure_core::rule_engine::Application::Expression(&#rule_ident) }
+    };
     let rule_sets = args
         .rule_sets
         .iter()
@@ -63,22 +174,55 @@ pub fn register_rule(arg_tokens: TokenStream, item: TokenStream) -> TokenStream
         })
         .collect::<Vec<_>>();
 
+    // Referencing each variant in a pattern makes typos a compile error rather than a rule that silently never fires.
+    let (applicable_variants, variants_check) = match &args.applies_to {
+        Some(variants) => (
+            quote! {
+                Some(&[#(stringify!(#variants)),*])
+            },
+            quote! {
+                const _: () = {
+                    #[allow(dead_code)]
+                    fn check_variants(expr: &::conjure_core::ast::Expression) -> bool {
+                        matches!(expr, #(::conjure_core::ast::Expression::#variants { .. })|*)
+                    }
+                };
+            },
+        ),
+        None => (quote! { None }, quote! {}),
+    };
+
     let expanded = quote! {
         #func
 
+        #variants_check
+
         use ::conjure_core::rule_engine::_dependencies::*; // ToDo idk if we need to explicitly do that?
 
         #[::conjure_core::rule_engine::_dependencies::distributed_slice(::conjure_core::rule_engine::RULES_DISTRIBUTED_SLICE)]
         pub static #static_ident: ::conjure_core::rule_engine::Rule<'static> = ::conjure_core::rule_engine::Rule {
             name: stringify!(#rule_ident),
-            application: #rule_ident,
+            application: #application,
             rule_sets: &[#(#rule_sets),*],
+            applicable_variants: #applicable_variants,
+            doc: #doc,
         };
     };
 
     TokenStream::from(expanded)
 }
 
+/**
+ * Declare and register rules as `<pattern> [if <guard>] => <template>` arms.
+ *
+ * See `conjure_core::rule_engine::pattern_rule` for the syntax.
+ */
+#[proc_macro]
+pub fn pattern_rule(input: TokenStream) -> TokenStream {
+    let rules = parse_macro_input!(input as pattern_rule::PatternRules);
+    TokenStream::from(pattern_rule::expand(rules))
+}
+
 fn parse_parenthesized<T: Parse>(input: ParseStream) -> Result<Vec<T>> {
     let content;
     parenthesized!(content in input);
@@ -0,0 +1,510 @@
+use std::collections::HashSet;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{
+    braced, Attribute, BinOp, Error, Expr, ExprArray, ExprRange, Ident, Lit, Meta, RangeLimits,
+    Result, Token, UnOp, Visibility,
+};
+
+use crate::RegisterRuleArgs;
+
+/// A single `<pattern> [if <guard>] => <template>` arm.
+struct Arm {
+    pattern: Expr,
+    guard: Option<Expr>,
+    template: Expr,
+}
+
+impl Parse for Arm {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let pattern = input.parse()?;
+        let guard = if input.peek(Token![if]) {
+            input.parse::<Token![if]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        input.parse::<Token![=>]>()?;
+        let template = input.parse()?;
+        Ok(Arm {
+            pattern,
+            guard,
+            template,
+        })
+    }
+}
+
+/// A rule declared as `#[register_rule(...)] fn name { <arms> }`.
+struct PatternRule {
+    attrs: Vec<Attribute>,
+    register_args: RegisterRuleArgs,
+    vis: Visibility,
+    name: Ident,
+    arms: Vec<Arm>,
+}
+
+impl Parse for PatternRule {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = input.call(Attribute::parse_outer)?;
+        let Some(register_idx) = attrs
+            .iter()
+            .position(|attr| attr.path().is_ident("register_rule"))
+        else {
+            return Err(input.error("expected a `#[register_rule(...)]` attribute"));
+        };
+        let register_args = match &attrs.remove(register_idx).meta {
+            Meta::Path(_) => RegisterRuleArgs {
+                rule_sets: Vec::new(),
+                applies_to: None,
+            },
+            Meta::List(list) => syn::parse2(list.tokens.clone())?,
+            Meta::NameValue(meta) => {
+                return Err(Error::new(meta.span(), "expected `#[register_rule(...)]`"))
+            }
+        };
+
+        let vis = input.parse()?;
+        input.parse::<Token![fn]>()?;
+        let name: Ident = input.parse()?;
+
+        let content;
+        braced!(content in input);
+        let mut arms = Vec::new();
+        while !content.is_empty() {
+            arms.push(content.parse()?);
+            if content.is_empty() {
+                break;
+            }
+            content.parse::<Token![,]>()?;
+        }
+        if arms.is_empty() {
+            return Err(Error::new(
+                name.span(),
+                "a pattern rule needs at least one arm",
+            ));
+        }
+
+        Ok(PatternRule {
+            attrs,
+            register_args,
+            vis,
+            name,
+            arms,
+        })
+    }
+}
+
+pub(crate) struct PatternRules(Vec<PatternRule>);
+
+impl Parse for PatternRules {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut rules = Vec::new();
+        while !input.is_empty() {
+            rules.push(input.parse()?);
+        }
+        Ok(PatternRules(rules))
+    }
+}
+
+/// `sum_leq` -> `SumLeq`
+fn variant_ident(ident: &Ident) -> Ident {
+    let name: String = ident
+        .to_string()
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    Ident::new(&name, ident.span())
+}
+
+/// If `expr` is a constructor (`f(..)`, a comparison or `!a`), returns its `Expression` variant and arguments.
+fn constructor(expr: &Expr) -> Result<Option<(Ident, Vec<&Expr>)>> {
+    match expr {
+        Expr::Call(call) => match call.func.as_ref() {
+            Expr::Path(path) if path.path.get_ident().is_some() => {
+                let ident = path.path.get_ident().map(variant_ident);
+                Ok(ident.map(|ident| (ident, call.args.iter().collect())))
+            }
+            func => Err(Error::new(
+                func.span(),
+                "expected the snake_case name of an expression variant, e.g. `sum_leq`",
+            )),
+        },
+        Expr::Binary(binary) => {
+            let name = match binary.op {
+                BinOp::Eq(_) => "Eq",
+                BinOp::Ne(_) => "Neq",
+                BinOp::Lt(_) => "Lt",
+                BinOp::Le(_) => "Leq",
+                BinOp::Gt(_) => "Gt",
+                BinOp::Ge(_) => "Geq",
+                _ => return Err(Error::new(
+                    binary.op.span(),
+                    "only comparison operators may be used infix; write other expressions as calls",
+                )),
+            };
+            Ok(Some((
+                Ident::new(name, binary.op.span()),
+                vec![binary.left.as_ref(), binary.right.as_ref()],
+            )))
+        }
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Not(_)) => Ok(Some((
+            Ident::new("Not", unary.op.span()),
+            vec![unary.expr.as_ref()],
+        ))),
+        _ => Ok(None),
+    }
+}
+
+/// If `expr` is an integer or boolean literal, returns the matching `Constant`.
+fn constant(expr: &Expr) -> Option<TokenStream> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Int(_) => Some(quote! { ::conjure_core::ast::Constant::Int(#expr) }),
+            Lit::Bool(_) => Some(quote! { ::conjure_core::ast::Constant::Bool(#expr) }),
+            _ => None,
+        },
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => match unary.expr.as_ref() {
+            Expr::Lit(lit) if matches!(lit.lit, Lit::Int(_)) => {
+                Some(quote! { ::conjure_core::ast::Constant::Int(#expr) })
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `..` or `..tail` inside a list.
+fn list_tail(expr: &Expr) -> Option<&ExprRange> {
+    match expr {
+        Expr::Range(range)
+            if range.start.is_none() && matches!(range.limits, RangeLimits::HalfOpen(_)) =>
+        {
+            Some(range)
+        }
+        _ => None,
+    }
+}
+
+fn single_ident(expr: &Expr) -> Option<&Ident> {
+    match expr {
+        Expr::Path(path) if path.qself.is_none() => path.path.get_ident(),
+        _ => None,
+    }
+}
+
+/// A sub-pattern that is matched after its parent has been destructured.
+enum Nested<'a> {
+    Expr(Ident, &'a Expr),
+    List(Ident, &'a ExprArray),
+}
+
+/// Builds the statements that destructure one arm's pattern, breaking out of the arm on mismatch.
+#[derive(Default)]
+struct PatternGen {
+    bound: HashSet<String>,
+    fresh: usize,
+    stmts: Vec<TokenStream>,
+}
+
+impl PatternGen {
+    fn fresh(&mut self) -> Ident {
+        self.fresh += 1;
+        format_ident!("__p{}", self.fresh)
+    }
+
+    fn bind(&mut self, ident: &Ident) -> Result<()> {
+        if self.bound.insert(ident.to_string()) {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ident.span(),
+                format!("`{}` is bound more than once in this pattern", ident),
+            ))
+        }
+    }
+
+    /// Matches `pattern` against `scrutinee`, a `&Expression`.
+    fn match_expr(&mut self, pattern: &Expr, scrutinee: &Ident) -> Result<()> {
+        if let Expr::Paren(paren) = pattern {
+            return self.match_expr(&paren.expr, scrutinee);
+        }
+        if let Expr::Infer(_) = pattern {
+            return Ok(());
+        }
+        if let Some(ident) = single_ident(pattern) {
+            self.bind(ident)?;
+            self.stmts.push(quote! { let #ident = #scrutinee; });
+            return Ok(());
+        }
+        if let Some(constant) = constant(pattern) {
+            self.stmts.push(quote! {
+                let ::conjure_core::ast::Expression::Constant(_, #constant) = #scrutinee else {
+                    break '__arm;
+                };
+            });
+            return Ok(());
+        }
+        if let Some((variant, args)) = constructor(pattern)? {
+            let mut nested = Vec::new();
+            let fields = args
+                .into_iter()
+                .map(|arg| self.field(arg, &mut nested))
+                .collect::<Result<Vec<_>>>()?;
+            self.stmts.push(quote! {
+                let ::conjure_core::ast::Expression::#variant(_, #(#fields),*) = #scrutinee else {
+                    break '__arm;
+                };
+            });
+            return self.match_nested(nested);
+        }
+        if let Expr::Array(array) = pattern {
+            return Err(Error::new(
+                array.span(),
+                "a list pattern can only be an argument of a constructor",
+            ));
+        }
+        Err(Error::new(
+            pattern.span(),
+            "expected a constructor, comparison, literal, variable or `_`",
+        ))
+    }
+
+    /// The Rust pattern for one field of a constructor. Anything more than a variable is matched later.
+    fn field<'a>(&mut self, arg: &'a Expr, nested: &mut Vec<Nested<'a>>) -> Result<TokenStream> {
+        if let Expr::Infer(_) = arg {
+            return Ok(quote! { _ });
+        }
+        if let Some(ident) = single_ident(arg) {
+            self.bind(ident)?;
+            return Ok(quote! { #ident });
+        }
+        let ident = self.fresh();
+        match arg {
+            Expr::Array(array) => nested.push(Nested::List(ident.clone(), array)),
+            _ => nested.push(Nested::Expr(ident.clone(), arg)),
+        }
+        Ok(quote! { #ident })
+    }
+
+    fn match_nested(&mut self, nested: Vec<Nested>) -> Result<()> {
+        for item in nested {
+            match item {
+                Nested::Expr(ident, pattern) => {
+                    self.stmts.push(quote! {
+                        let #ident = <_ as ::std::borrow::Borrow<::conjure_core::ast::Expression>>::borrow(#ident);
+                    });
+                    self.match_expr(pattern, &ident)?;
+                }
+                Nested::List(ident, array) => {
+                    let mut inner = Vec::new();
+                    let mut elems = Vec::new();
+                    for elem in &array.elems {
+                        if let Some(tail) = list_tail(elem) {
+                            match tail.end.as_deref().map(single_ident) {
+                                None => elems.push(quote! { .. }),
+                                Some(Some(rest)) => {
+                                    self.bind(rest)?;
+                                    elems.push(quote! { #rest @ .. });
+                                }
+                                Some(None) => {
+                                    return Err(Error::new(
+                                        tail.span(),
+                                        "the tail of a list pattern must be `..` or `..name`",
+                                    ))
+                                }
+                            }
+                        } else if let Expr::Array(array) = elem {
+                            return Err(Error::new(
+                                array.span(),
+                                "list patterns cannot be nested directly inside each other",
+                            ));
+                        } else {
+                            elems.push(self.field(elem, &mut inner)?);
+                        }
+                    }
+                    self.stmts.push(quote! {
+                        let [#(#elems),*] = #ident.as_slice() else {
+                            break '__arm;
+                        };
+                    });
+                    self.match_nested(inner)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the expression described by a template.
+///
+/// An expression built at the root of the template takes the metadata of the matched expression,
+/// `root`; everything below it gets fresh metadata.
+fn template(expr: &Expr, root: Option<&Ident>) -> Result<TokenStream> {
+    if let Expr::Paren(paren) = expr {
+        return template(&paren.expr, root);
+    }
+    let metadata = match root {
+        Some(root) => {
+            quote! { ::conjure_core::rule_engine::pattern_rule::root_metadata(#root) }
+        }
+        None => quote! { ::conjure_core::metadata::Metadata::new() },
+    };
+    if let Expr::Block(block) = expr {
+        return Ok(quote! { #block });
+    }
+    if single_ident(expr).is_some() {
+        return Ok(quote! { #expr });
+    }
+    if let Some(constant) = constant(expr) {
+        return Ok(quote! {
+            ::conjure_core::ast::Expression::Constant(#metadata, #constant)
+        });
+    }
+    if let Some((variant, args)) = constructor(expr)? {
+        let args = args
+            .into_iter()
+            .map(|arg| {
+                let arg = template(arg, None)?;
+                Ok(quote! { ::conjure_core::rule_engine::pattern_rule::into_arg(#arg) })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(quote! {
+            ::conjure_core::ast::Expression::#variant(#metadata, #(#args),*)
+        });
+    }
+    if let Expr::Array(array) = expr {
+        let parts = array
+            .elems
+            .iter()
+            .map(|elem| match list_tail(elem) {
+                Some(ExprRange { end: Some(end), .. }) => Ok(quote! {
+                    ::conjure_core::rule_engine::pattern_rule::TemplateList::into_list(#end)
+                }),
+                Some(tail) => Err(Error::new(
+                    tail.span(),
+                    "expected `..name` to splice a list into a template",
+                )),
+                None => {
+                    let elem = template(elem, None)?;
+                    Ok(quote! {
+                        ::std::iter::once::<::conjure_core::ast::Expression>(
+                            ::conjure_core::rule_engine::pattern_rule::into_arg(#elem)
+                        )
+                    })
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(quote! {
+            ::std::iter::empty()
+                #(.chain(#parts))*
+                .collect::<::std::vec::Vec<::conjure_core::ast::Expression>>()
+        });
+    }
+    Err(Error::new(
+        expr.span(),
+        "expected a constructor, comparison, literal, variable, list or `{ ... }` block",
+    ))
+}
+
+fn expand_rule(rule: &PatternRule) -> Result<TokenStream> {
+    let PatternRule {
+        attrs,
+        register_args,
+        vis,
+        name,
+        arms,
+    } = rule;
+
+    let scrutinee = Ident::new("__expr", proc_macro2::Span::call_site());
+    // Guards and template blocks can look at the model, e.g. at the domains of variables
+    let model = Ident::new("model", proc_macro2::Span::call_site());
+    let mut bodies = Vec::new();
+    for arm in arms {
+        let mut gen = PatternGen::default();
+        gen.match_expr(&arm.pattern, &scrutinee)?;
+        let stmts = &gen.stmts;
+        let guard = arm.guard.as_ref().map(|guard| {
+            quote! {
+                if !(#guard) {
+                    break '__arm;
+                }
+            }
+        });
+        let result = template(&arm.template, Some(&scrutinee))?;
+        bodies.push(quote! {
+            '__arm: {
+                #(#stmts)*
+                #guard
+                return Ok(::conjure_core::rule_engine::Reduction::pure(
+                    ::conjure_core::rule_engine::pattern_rule::into_arg::<::conjure_core::ast::Expression, _>(#result),
+                ));
+            }
+        });
+    }
+
+    let rule_sets = register_args.rule_sets.iter().map(|rule_set| {
+        let rule_set_name = &rule_set.rule_set;
+        let priority = &rule_set.priority;
+        quote! { (#rule_set_name, #priority) }
+    });
+
+    // Unless told otherwise, a rule applies to the variants at the top of its patterns.
+    let applies_to = match &register_args.applies_to {
+        Some(variants) => Some(variants.clone()),
+        None => {
+            let mut variants: Vec<Ident> = Vec::new();
+            let mut all_constructors = true;
+            for arm in arms {
+                let mut pattern = &arm.pattern;
+                while let Expr::Paren(paren) = pattern {
+                    pattern = &paren.expr;
+                }
+                match constructor(pattern)? {
+                    Some((variant, _)) if variants.contains(&variant) => {}
+                    Some((variant, _)) => variants.push(variant),
+                    None if constant(pattern).is_some() => {
+                        let constant = Ident::new("Constant", pattern.span());
+                        if !variants.contains(&constant) {
+                            variants.push(constant);
+                        }
+                    }
+                    None => all_constructors = false,
+                }
+            }
+            all_constructors.then_some(variants)
+        }
+    };
+    let applies_to = applies_to.map(|variants| quote! { applies_to(#(#variants),*) });
+    let register_args = rule_sets.chain(applies_to);
+
+    Ok(quote! {
+        #(#attrs)*
+        #[::conjure_core::rule_engine::register_rule(#(#register_args),*)]
+        #[allow(unused_labels, unused_braces)]
+        #vis fn #name(
+            #scrutinee: &::conjure_core::ast::Expression,
+            #model: &::conjure_core::Model,
+        ) -> ::conjure_core::rule_engine::ApplicationResult {
+            let _ = #model;
+            #(#bodies)*
+            Err(::conjure_core::rule_engine::ApplicationError::RuleNotApplicable)
+        }
+    })
+}
+
+pub(crate) fn expand(rules: PatternRules) -> TokenStream {
+    rules
+        .0
+        .iter()
+        .map(|rule| expand_rule(rule).unwrap_or_else(Error::into_compile_error))
+        .collect()
+}
@@ -98,6 +98,23 @@ impl VisitMut for RemoveCompatibleAttrs {
 ///    Sum(Vec<Expression>)
 /// ```
 ///
+/// The same lists are available at runtime through a generated `compatible_variants` function:
+///
+/// ```
+///# use enum_compatability_macro::document_compatibility;
+///#
+///# #[document_compatibility]
+///# pub enum Expression {
+///#    #[compatible(Minion)]
+///#    ConstantInt(i32),
+///#    #[compatible(Chuffed, Minion)]
+///#    Sum(Vec<Expression>)
+///#    }
+/// assert_eq!(Expression::compatible_variants("Minion"), &["ConstantInt", "Sum"]);
+/// assert_eq!(Expression::compatible_variants("Chuffed"), &["Sum"]);
+/// assert!(Expression::compatible_variants("SAT").is_empty());
+/// ```
+///
 /// Two equivalent syntaxes exist for specifying supported solvers:
 ///
 /// ```
@@ -189,8 +206,35 @@ pub fn document_compatibility(_attr: TokenStream, input: TokenStream) -> TokenSt
     }
 
     input.attrs.push(parse_quote!(#[doc = #doc_msg]));
+
+    // The same lists, for use at runtime
+    let solvers: Vec<&String> = nodes_supported_by_solver.keys().sorted().collect();
+    let node_lists: Vec<Vec<String>> = solvers
+        .iter()
+        .map(|solver| {
+            nodes_supported_by_solver
+                .get(*solver)
+                .unwrap()
+                .iter()
+                .map(|x| x.to_string())
+                .collect()
+        })
+        .collect();
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
     let expanded = quote! {
         #input
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The names of the variants marked as compatible with `solver`, in declaration order.
+            pub fn compatible_variants(solver: &str) -> &'static [&'static str] {
+                match solver {
+                    #(#solvers => &[#(#node_lists),*],)*
+                    _ => &[],
+                }
+            }
+        }
     };
 
     TokenStream::from(expanded)
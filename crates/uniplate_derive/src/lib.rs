@@ -89,6 +89,7 @@ fn generate_variant_context_match_arm(variant: &Variant, root_ident: &Ident) ->
 ///
 /// - Deriving `Uniplate` for enum types
 /// - `Box<T>` and `Vec<T>` fields, including nested vectors
+/// - `Rc<T>` and `Arc<T>` fields, e.g. `Arc<Vec<T>>`
 /// - Tuple fields, including nested tuples - e.g. `(Vec<T>, (Box<T>, i32))`
 ///
 /// ## What does not work?
@@ -99,7 +100,7 @@ fn generate_variant_context_match_arm(variant: &Variant, root_ident: &Ident) ->
 /// - Multiple type arguments - e.g. `MyType<T, R>`
 /// - Any complex type arguments, e.g. `MyType<T: MyTrait1 + MyTrait2>`
 /// - Any collection type other than `Vec`
-/// - Any box type other than `Box`
+/// - Any pointer type other than `Box`, `Rc` and `Arc`
 ///
 /// # Usage
 ///
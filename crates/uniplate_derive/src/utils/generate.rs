@@ -27,6 +27,14 @@ fn get_fill(
                     Box::new(#sf_fill) // If it is a box, generate the fill for the inner type and box it
                 };
             }
+            UniplateField::Shared(_, pointer, subfield) => {
+                let sf = subfield.as_ref();
+                let sf_fill = get_fill(sf, exprs_ident, field_ident, root_ident);
+                let pointer_path = shared_pointer_path(pointer);
+                return quote! {
+                    #pointer_path::new(#sf_fill) // Generate the fill for the inner type and wrap it in a new pointer
+                };
+            }
             UniplateField::Vector(_, subfield) => {
                 let sf = subfield.as_ref();
                 let sf_fill = get_fill(sf, exprs_ident, field_ident, root_ident);
@@ -89,6 +97,13 @@ fn get_clone(
                 };
                 return get_clone(sf, box_clone, root_ident); // Then generate the clone for the inner type
             }
+            UniplateField::Shared(_, _, inner) => {
+                let sf = inner.as_ref();
+                let shared_ref = quote! { // Borrow the inner value rather than cloning it, as it may be large
+                    #field_ident.as_ref()
+                };
+                return get_clone(sf, shared_ref, root_ident);
+            }
             UniplateField::Vector(_, inner) => {
                 let sf = inner.as_ref();
 
@@ -130,6 +145,15 @@ fn get_clone(
     None // If the field is not a type we want to clone, return None
 }
 
+/// Get the full path to a shared pointer type, so that it need not be in scope where the macro is used
+fn shared_pointer_path(pointer: &Ident) -> TokenStream2 {
+    if pointer == "Rc" {
+        quote! { ::std::rc::Rc }
+    } else {
+        quote! { ::std::sync::Arc }
+    }
+}
+
 /// Helper function to get the name of a field - if it has no name, use `field{idx}`
 fn get_field_name(field: &Field, idx: usize) -> String {
     match &field.ident {
@@ -19,6 +19,8 @@ pub enum UniplateField {
     Identifier(Ident),
     /// A field consisting of a Box<T>
     Box(Span, Box<UniplateField>),
+    /// A field consisting of a shared pointer (Rc<T> or Arc<T>). The identifier is the pointer type.
+    Shared(Span, Ident, Box<UniplateField>),
     /// A field consisting of a Vec<T>
     Vector(Span, Box<UniplateField>),
     /// A tuple of multiple fields (e.g. `(Box<T>, i32)`)
@@ -35,6 +37,7 @@ impl UniplateField {
         match self {
             UniplateField::Identifier(idnt) => idnt.span(),
             UniplateField::Box(spn, _) => *spn,
+            UniplateField::Shared(spn, _, _) => *spn,
             UniplateField::Vector(spn, _) => *spn,
             UniplateField::Tuple(spn, _) => *spn,
             UniplateField::Array(spn, _, _) => *spn,
@@ -79,6 +82,8 @@ pub fn parse_field_type(field_type: &Type) -> UniplateField {
         let args = &seg.arguments;
 
         let box_ident = &Ident::new("Box", span);
+        let rc_ident = &Ident::new("Rc", span);
+        let arc_ident = &Ident::new("Arc", span);
         let vec_ident = &Ident::new("Vec", span); // ToDo: support other collection types
 
         if ident.eq(box_ident) {
@@ -86,6 +91,15 @@ pub fn parse_field_type(field_type: &Type) -> UniplateField {
                 Ok(inner_seg) => UniplateField::Box(seg.span(), Box::new(parse_type(inner_seg))),
                 Err(_) => UniplateField::Unknown(ident.span()),
             }
+        } else if ident.eq(rc_ident) || ident.eq(arc_ident) {
+            match parse_type_argument(args) {
+                Ok(inner_seg) => UniplateField::Shared(
+                    seg.span(),
+                    ident.clone(),
+                    Box::new(parse_type(inner_seg)),
+                ),
+                Err(_) => UniplateField::Unknown(ident.span()),
+            }
         } else if ident.eq(vec_ident) {
             match parse_type_argument(args) {
                 Ok(inner_seg) => UniplateField::Vector(seg.span(), Box::new(parse_type(inner_seg))),
@@ -118,6 +132,7 @@ pub fn check_field_type(ft: &UniplateField, root_ident: &Ident) -> bool {
     match ft {
         UniplateField::Identifier(ident) => ident.eq(root_ident),
         UniplateField::Box(_, subfield) => check_field_type(subfield.as_ref(), root_ident),
+        UniplateField::Shared(_, _, subfield) => check_field_type(subfield.as_ref(), root_ident),
         UniplateField::Vector(_, subfield) => check_field_type(subfield.as_ref(), root_ident),
         UniplateField::Tuple(_, subfields) => {
             for sft in subfields {
@@ -1,3 +1,6 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
 use uniplate::uniplate::Uniplate;
 use uniplate_derive::Uniplate;
 
@@ -12,6 +15,16 @@ enum TestEnum {
     G((Box<TestEnum>, (Box<TestEnum>, i32))),
     H(Vec<Vec<TestEnum>>),
     I(Vec<TestEnum>, i32, Vec<TestEnum>),
+    J(Rc<TestEnum>),
+    K(Rc<Vec<TestEnum>>),
+}
+
+/// Like [`TestEnum`], but with shared children that are `Send` and `Sync`.
+#[derive(Clone, Debug, PartialEq, Eq, Uniplate)]
+enum ArcEnum {
+    A(i32),
+    B(Arc<ArcEnum>),
+    C(Arc<Vec<ArcEnum>>),
 }
 
 #[test]
@@ -242,3 +255,71 @@ fn derive_children_multiple_vecs() {
         ]
     );
 }
+
+#[test]
+fn derive_context_rc() {
+    let j = TestEnum::J(Rc::new(TestEnum::A(1)));
+    let context = j.uniplate().1;
+    assert_eq!(
+        context(vec![TestEnum::A(2)]).unwrap(),
+        TestEnum::J(Rc::new(TestEnum::A(2)))
+    );
+}
+
+#[test]
+fn derive_children_rc() {
+    let j = TestEnum::J(Rc::new(TestEnum::A(1)));
+    let children = j.uniplate().0;
+    assert_eq!(children, vec![TestEnum::A(1)]);
+}
+
+#[test]
+fn derive_context_rc_vec() {
+    let k = TestEnum::K(Rc::new(vec![TestEnum::A(1), TestEnum::A(2)]));
+    let context = k.uniplate().1;
+    assert_eq!(
+        context(vec![TestEnum::A(3), TestEnum::A(4)]).unwrap(),
+        TestEnum::K(Rc::new(vec![TestEnum::A(3), TestEnum::A(4)]))
+    );
+}
+
+#[test]
+fn derive_children_rc_vec() {
+    let k = TestEnum::K(Rc::new(vec![TestEnum::A(1), TestEnum::A(2)]));
+    let children = k.uniplate().0;
+    assert_eq!(children, vec![TestEnum::A(1), TestEnum::A(2)]);
+}
+
+#[test]
+fn derive_context_arc() {
+    let b = ArcEnum::B(Arc::new(ArcEnum::A(1)));
+    let context = b.uniplate().1;
+    assert_eq!(
+        context(vec![ArcEnum::A(2)]).unwrap(),
+        ArcEnum::B(Arc::new(ArcEnum::A(2)))
+    );
+}
+
+#[test]
+fn derive_children_arc() {
+    let b = ArcEnum::B(Arc::new(ArcEnum::A(1)));
+    let children = b.uniplate().0;
+    assert_eq!(children, vec![ArcEnum::A(1)]);
+}
+
+#[test]
+fn derive_context_arc_vec() {
+    let c = ArcEnum::C(Arc::new(vec![ArcEnum::A(1), ArcEnum::A(2)]));
+    let context = c.uniplate().1;
+    assert_eq!(
+        context(vec![ArcEnum::A(3), ArcEnum::A(4)]).unwrap(),
+        ArcEnum::C(Arc::new(vec![ArcEnum::A(3), ArcEnum::A(4)]))
+    );
+}
+
+#[test]
+fn derive_children_arc_vec() {
+    let c = ArcEnum::C(Arc::new(vec![ArcEnum::A(1), ArcEnum::A(2)]));
+    let children = c.uniplate().0;
+    assert_eq!(children, vec![ArcEnum::A(1), ArcEnum::A(2)]);
+}
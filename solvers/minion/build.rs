@@ -14,8 +14,10 @@ fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
 
     println!("cargo:rustc-link-search=all={}/build", out_dir);
+    println!("cargo:rustc-link-lib=static=minion_shim");
     println!("cargo:rustc-link-lib=static=minion");
     println!("cargo:rerun-if-changed=vendor");
+    println!("cargo:rerun-if-changed=shim");
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=build.sh");
     println!("cargo:rerun-if-env-changed=DEBUG_MINION");
@@ -63,7 +65,7 @@ fn bind() {
     let bindings = bindgen::Builder::default()
         // The input header we would like to generate
         // bindings for.
-        .header("vendor/minion/libwrapper.h")
+        .header("shim/minion_shim.h")
         // Make all templates opaque as reccomended by bindgen
         .opaque_type("std::.*")
         // Manually allow C++ functions to stop bindgen getting confused.
@@ -74,6 +76,7 @@ fn bind() {
         .allowlist_function("tupleList_free")
         .allowlist_function("getVarByName")
         .allowlist_function("newVar_ffi")
+        .allowlist_function("newVarSparse_ffi")
         .allowlist_function("instance_new")
         .allowlist_function("instance_free")
         .allowlist_function("instance_addSearchOrder")
@@ -111,8 +114,13 @@ fn bind() {
         .allowlist_function("vec_vec_int_push_back")
         .allowlist_function("vec_vec_int_free")
         .allowlist_function("TableOut_get")
+        .allowlist_function("TableOut_set_ffi")
+        .allowlist_function("searchOrder_setValOrder_ffi")
+        .allowlist_function("searchOptions_setRestarts_ffi")
+        .allowlist_function("searchMethod_setSeed_ffi")
         .clang_arg(format!("-I{}/build/src/", out_dir)) // generated from configure.py
         .clang_arg("-Ivendor/minion/")
+        .clang_arg("-Ishim/")
         .clang_arg("-DLIBMINION")
         .clang_arg(r"--std=gnu++11")
         .clang_arg(r"-xc++");
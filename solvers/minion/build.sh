@@ -31,3 +31,14 @@ fi
 echo "------ BUILD STEP ------"
 cd "$OUT_DIR/build"
 make
+
+echo "------ SHIM STEP ------"
+cd "$OUT_DIR/build"
+SHIM_FLAGS="-std=gnu++11 -DLIBMINION -I$SCRIPT_DIR/vendor/minion -I$OUT_DIR/build/src"
+if [[ ${DEBUG_MINION-default} != "default" ]]; then
+  SHIM_FLAGS="$SHIM_FLAGS -g -D_GLIBCXX_DEBUG -DMORE_SEARCH_INFO -DMINION_DEBUG"
+else
+  SHIM_FLAGS="$SHIM_FLAGS -O2"
+fi
+"${CXX:-c++}" $SHIM_FLAGS -c "$SCRIPT_DIR/shim/minion_shim.cpp" -o minion_shim.o
+ar rcs libminion_shim.a minion_shim.o
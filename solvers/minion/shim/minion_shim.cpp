@@ -0,0 +1,22 @@
+#include "minion_shim.h"
+
+void newVarSparse_ffi(ProbSpec::CSPInstance& instance, char* name, std::vector<int>* values) {
+  std::vector<DomainInt> domain(values->begin(), values->end());
+  newVar(instance, name, VAR_SPARSEBOUND, domain);
+}
+
+void searchOrder_setValOrder_ffi(SearchOrder* order, ValOrderEnum val_order) {
+  order->val_order.assign(order->var_order.size(), ValOrder(val_order));
+}
+
+void searchOptions_setRestarts_ffi(SearchOptions* options, bool restarts) {
+  options->restart.active = restarts;
+}
+
+void searchMethod_setSeed_ffi(SearchMethod* method, unsigned int seed) {
+  method->random_seed = seed;
+}
+
+void TableOut_set_ffi(char* key, char* value) {
+  getTableOut().set(std::string(key), std::string(value));
+}
@@ -0,0 +1,24 @@
+// Additions to Minion's C++ interface (vendor/minion/libwrapper.h) needed by the Rust bindings.
+// Compiled into libminion_shim.a by build.sh.
+
+#ifndef MINION_SHIM_H
+#define MINION_SHIM_H
+
+#include "libwrapper.h"
+
+// Adds a SPARSEBOUND variable that takes only the given values, in increasing order.
+void newVarSparse_ffi(ProbSpec::CSPInstance& instance, char* name, std::vector<int>* values);
+
+// Tries the values of every variable in the search order in the given order.
+void searchOrder_setValOrder_ffi(SearchOrder* order, ValOrderEnum val_order);
+
+// Turns restarts on or off, as the -restarts flag does.
+void searchOptions_setRestarts_ffi(SearchOptions* options, bool restarts);
+
+// Seeds the random choices of search, as the -randomseed flag does.
+void searchMethod_setSeed_ffi(SearchMethod* method, unsigned int seed);
+
+// Sets a value in the global TableOut, where Minion records the statistics of a run.
+void TableOut_set_ffi(char* key, char* value);
+
+#endif
@@ -3,7 +3,8 @@
 use std::collections::HashMap;
 
 pub type VarName = String;
-pub type Tuple = (Constant, Constant);
+/// A row of a table constraint, with one value for each of its variables.
+pub type Tuple = Vec<Constant>;
 pub type TwoVars = (Var, Var);
 
 /// A Minion model.
@@ -82,7 +83,7 @@ pub enum Constraint {
     LightTable(Vec<Var>, Vec<Tuple>),
     Mddc(Vec<Var>, Vec<Tuple>),
     NegativeMddc(Vec<Var>, Vec<Tuple>),
-    Str2Plus(Vec<Var>, Var),
+    Str2Plus(Vec<Var>, Vec<Tuple>),
     Max(Vec<Var>, Var),
     Min(Vec<Var>, Var),
     NvalueGeq(Vec<Var>, Var),
@@ -135,12 +136,19 @@ pub enum Constant {
 }
 
 /// Representation of variable domains.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+///
+/// These correspond to Minion's variable types: `BOUND` variables only store their bounds and `DISCRETE`
+/// variables store every value in their range. Both take every value between their bounds, so holes in a
+/// domain are removed with a constraint such as [`WInIntervalSet`](Constraint::WInIntervalSet).
+///
+/// `SPARSEBOUND` variables take only the given values, which must be in increasing order, and store their
+/// bounds like `BOUND` variables.
+#[derive(Debug, Clone, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum VarDomain {
     Bound(i32, i32),
     Discrete(i32, i32),
-    SparseBound(i32, i32),
+    SparseBound(Vec<i32>),
     Bool,
 }
 
@@ -29,18 +29,11 @@
 //! use minion_rs::ast::*;
 //! use minion_rs::run_minion;
 //! use std::collections::HashMap;
-//! use std::sync::Mutex;
 //!
 //! // Get solutions out of Minion.
 //! // See the documentation for Callback for details.
 //!
-//! static ALL_SOLUTIONS: Mutex<Vec<HashMap<VarName,Constant>>>  = Mutex::new(vec![]);
-//!
-//! fn callback(solutions: HashMap<VarName,Constant>) -> bool {
-//!     let mut guard = ALL_SOLUTIONS.lock().unwrap();
-//!     guard.push(solutions);
-//!     true
-//! }
+//! let mut all_solutions: Vec<HashMap<VarName,Constant>> = vec![];
 //!
 //! // Build and run the model.
 //! let mut model = Model::new();
@@ -82,18 +75,20 @@
 //! model.constraints.push(geq);
 //! model.constraints.push(ineq);
 //!
-//! let res = run_minion(model, callback);
+//! let res = run_minion(model, |solutions| {
+//!     all_solutions.push(solutions);
+//!     true
+//! });
 //! res.expect("Error occurred");
 //!
 //! // Get solutions
-//! let guard = ALL_SOLUTIONS.lock().unwrap();
-//! let solution_set_1 = &(guard.get(0).unwrap());
+//! let solution_set_1 = all_solutions.get(0).unwrap();
 //!
 //! let x1 = solution_set_1.get("x").unwrap();
 //! let y1 = solution_set_1.get("y").unwrap();
 //! let z1 = solution_set_1.get("z").unwrap();
 //!
-//! assert_eq!(guard.len(),1);
+//! assert_eq!(all_solutions.len(),1);
 //! assert_eq!(*x1,Constant::Integer(1));
 //! assert_eq!(*y1,Constant::Integer(2));
 //! assert_eq!(*z1,Constant::Integer(1));
@@ -111,9 +106,12 @@ pub mod error;
 mod ffi;
 
 pub mod ast;
+pub mod options;
 mod run;
 
 mod scoped_ptr;
 
+mod worker;
+
 mod wrappers;
 pub use wrappers::*;
@@ -0,0 +1,229 @@
+//! Search options for a run of Minion.
+
+use std::time::Duration;
+
+use crate::ffi;
+
+/// The order in which Minion branches on variables.
+///
+/// These correspond to the orderings accepted by Minion's `-varorder` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum VarOrder {
+    /// Branch on variables in the order they were added to the model.
+    #[default]
+    Static,
+    /// Smallest domain first.
+    Sdf,
+    /// Smallest ratio (of domain size to initial domain size) first.
+    Srf,
+    /// Largest domain first.
+    Ldf,
+    /// The order given in the input file, as used by Minion's `-varorder original`.
+    Original,
+    /// Weighted degree.
+    Wdeg,
+    /// Conflict ordering.
+    Conflict,
+    /// Domain size over weighted degree.
+    DomOverWdeg,
+}
+
+impl VarOrder {
+    pub(crate) fn to_raw(self) -> ffi::VarOrderEnum {
+        match self {
+            VarOrder::Static => ffi::VarOrderEnum_ORDER_STATIC,
+            VarOrder::Sdf => ffi::VarOrderEnum_ORDER_SDF,
+            VarOrder::Srf => ffi::VarOrderEnum_ORDER_SRF,
+            VarOrder::Ldf => ffi::VarOrderEnum_ORDER_LDF,
+            VarOrder::Original => ffi::VarOrderEnum_ORDER_ORIGINAL,
+            VarOrder::Wdeg => ffi::VarOrderEnum_ORDER_WDEG,
+            VarOrder::Conflict => ffi::VarOrderEnum_ORDER_CONFLICT,
+            VarOrder::DomOverWdeg => ffi::VarOrderEnum_ORDER_DOMOVERWDEG,
+        }
+    }
+}
+
+/// The order in which Minion tries the values of a variable.
+///
+/// These correspond to the orderings accepted by Minion's `-valorder` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ValOrder {
+    /// Smallest value first.
+    #[default]
+    Ascend,
+    /// Largest value first.
+    Descend,
+    /// A random value, chosen using the [seed](MinionOptions::seed) of the run.
+    Random,
+}
+
+impl ValOrder {
+    pub(crate) fn to_raw(self) -> ffi::ValOrderEnum {
+        match self {
+            ValOrder::Ascend => ffi::ValOrderEnum_VALORDER_ASCEND,
+            ValOrder::Descend => ffi::ValOrderEnum_VALORDER_DESCEND,
+            ValOrder::Random => ffi::ValOrderEnum_VALORDER_RANDOM,
+        }
+    }
+}
+
+/// Options controlling how Minion searches, built up with chained setters.
+///
+/// The default options find all solutions with no limits, branching on variables in the order they
+/// were added to the model.
+///
+/// ```
+/// use std::time::Duration;
+/// use minion_rs::options::{MinionOptions, VarOrder};
+///
+/// let options = MinionOptions::new()
+///     .time_limit(Duration::from_secs(10))
+///     .solution_limit(1)
+///     .var_order(VarOrder::DomOverWdeg);
+/// # assert_eq!(options.get_solution_limit(), Some(1));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MinionOptions {
+    time_limit: Option<Duration>,
+    node_limit: Option<u64>,
+    solution_limit: Option<u64>,
+    var_order: VarOrder,
+    val_order: ValOrder,
+    randomise: bool,
+    restarts: bool,
+    seed: Option<u32>,
+}
+
+impl MinionOptions {
+    pub fn new() -> MinionOptions {
+        MinionOptions::default()
+    }
+
+    /// Stop search once this much time has passed. Minion measures its time limit in whole
+    /// seconds, so this is rounded up.
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Stop search after exploring this many search nodes.
+    pub fn node_limit(mut self, limit: u64) -> Self {
+        self.node_limit = Some(limit);
+        self
+    }
+
+    /// Stop search after finding this many solutions.
+    pub fn solution_limit(mut self, limit: u64) -> Self {
+        self.solution_limit = Some(limit);
+        self
+    }
+
+    pub fn var_order(mut self, order: VarOrder) -> Self {
+        self.var_order = order;
+        self
+    }
+
+    pub fn val_order(mut self, order: ValOrder) -> Self {
+        self.val_order = order;
+        self
+    }
+
+    /// Randomise the variable and value ordering, as Minion's `-randomiseorder` flag does.
+    pub fn randomise(mut self, randomise: bool) -> Self {
+        self.randomise = randomise;
+        self
+    }
+
+    /// Restart search with a growing node limit, as Minion's `-restarts` flag does.
+    pub fn restarts(mut self, restarts: bool) -> Self {
+        self.restarts = restarts;
+        self
+    }
+
+    /// Seed the random choices made by search, as Minion's `-randomseed` flag does.
+    pub fn seed(mut self, seed: u32) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn get_time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    pub fn get_node_limit(&self) -> Option<u64> {
+        self.node_limit
+    }
+
+    pub fn get_solution_limit(&self) -> Option<u64> {
+        self.solution_limit
+    }
+
+    pub fn get_var_order(&self) -> VarOrder {
+        self.var_order
+    }
+
+    pub fn get_val_order(&self) -> ValOrder {
+        self.val_order
+    }
+
+    pub fn get_seed(&self) -> Option<u32> {
+        self.seed
+    }
+
+    /// Writes the limits, randomisation, restarts and seed into Minion's search options and method.
+    ///
+    /// The value ordering belongs to the search order, so is set when the model is converted.
+    pub(crate) unsafe fn apply(
+        &self,
+        search_opts: *mut ffi::SearchOptions,
+        search_method: *mut ffi::SearchMethod,
+    ) {
+        if let Some(limit) = self.time_limit {
+            let secs = limit.as_secs() + u64::from(limit.subsec_nanos() > 0);
+            (*search_opts).timeout = to_raw_limit(secs.max(1));
+        }
+        if let Some(limit) = self.node_limit {
+            (*search_opts).nodelimit = to_raw_limit(limit);
+        }
+        if let Some(limit) = self.solution_limit {
+            (*search_opts).sollimit = to_raw_limit(limit);
+        }
+        (*search_opts).randomise_valvarorder = self.randomise;
+        ffi::searchOptions_setRestarts_ffi(search_opts, self.restarts);
+        if let Some(seed) = self.seed {
+            ffi::searchMethod_setSeed_ffi(search_method, seed);
+        }
+    }
+}
+
+fn to_raw_limit(limit: u64) -> i64 {
+    i64::try_from(limit).unwrap_or(i64::MAX)
+}
+
+/// Why a run of Minion stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SearchEnd {
+    /// Search finished, or was stopped by the solution limit or the callback.
+    Finished,
+    /// The time or node limit was reached before search finished.
+    LimitReached,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_options_have_no_limits() {
+        let options = MinionOptions::new();
+        assert_eq!(options.get_time_limit(), None);
+        assert_eq!(options.get_node_limit(), None);
+        assert_eq!(options.get_solution_limit(), None);
+        assert_eq!(options.get_var_order(), VarOrder::Static);
+        assert_eq!(options.get_val_order(), ValOrder::Ascend);
+        assert_eq!(options.get_seed(), None);
+    }
+}
@@ -1,16 +1,22 @@
 #![allow(unreachable_patterns)]
 
 use std::{
+    any::Any,
+    cell::Cell,
     collections::HashMap,
     ffi::CString,
-    sync::Condvar,
-    sync::{Mutex, MutexGuard},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::{Mutex, MutexGuard, TryLockError},
 };
 
 use anyhow::anyhow;
 
 use crate::ffi::{self};
-use crate::{ast::*, error::*, scoped_ptr::Scoped};
+use crate::options::{MinionOptions, SearchEnd};
+use crate::worker::run_in_worker;
+use crate::wrappers::set_in_table;
+use crate::{ast::*, error::*, get_from_table, scoped_ptr::Scoped};
 
 /// The callback function used to capture results from Minion as they are generated.
 ///
@@ -19,29 +25,20 @@ use crate::{ast::*, error::*, scoped_ptr::Scoped};
 ///
 /// Callbacks should return `true` if search is to continue, `false` otherwise.
 ///
-/// # Examples
-///
-/// Consider using a global mutex (or other static variable) to use returned solutions elsewhere.
+/// Any `FnMut` closure can be used as a callback, so solutions can be collected into local state.
+/// The callback only lives for the duration of its run: nothing is kept between runs. It must not
+/// run Minion itself.
 ///
-/// For example:
+/// # Examples
 ///
 /// ```
 ///   use minion_rs::ast::*;
 ///   use minion_rs::run_minion;
-///   use std::{
-///       collections::HashMap,
-///       sync::{Mutex, MutexGuard},
-///   };
+///   use std::collections::HashMap;
 ///
 ///   // More elaborate data-structures are possible, but for sake of example store
 ///   // a vector of solution sets.
-///   static ALL_SOLUTIONS: Mutex<Vec<HashMap<VarName,Constant>>>  = Mutex::new(vec![]);
-///   
-///   fn callback(solutions: HashMap<VarName,Constant>) -> bool {
-///       let mut guard = ALL_SOLUTIONS.lock().unwrap();
-///       guard.push(solutions);
-///       true
-///   }
+///   let mut all_solutions: Vec<HashMap<VarName,Constant>> = vec![];
 ///    
 ///   // Build and run the model.
 ///   let mut model = Model::new();
@@ -85,126 +82,204 @@ use crate::{ast::*, error::*, scoped_ptr::Scoped};
 /// # model.constraints.push(geq);
 /// # model.constraints.push(ineq);
 ///  
-///   let res = run_minion(model, callback);
+///   let res = run_minion(model, |solutions| {
+///       all_solutions.push(solutions);
+///       true
+///   });
 ///   res.expect("Error occurred");
 ///
 ///   // Get solutions
-///   let guard = ALL_SOLUTIONS.lock().unwrap();
-///   let solution_set_1 = &(guard.get(0).unwrap());
+///   let solution_set_1 = all_solutions.get(0).unwrap();
 ///
 ///   let x1 = solution_set_1.get("x").unwrap();
 ///   let y1 = solution_set_1.get("y").unwrap();
 ///   let z1 = solution_set_1.get("z").unwrap();
 /// #
 /// # // TODO: this test would be better with an example with >1 solution.
-/// # assert_eq!(guard.len(),1);
+/// # assert_eq!(all_solutions.len(),1);
 /// # assert_eq!(*x1,Constant::Integer(1));
 /// # assert_eq!(*y1,Constant::Integer(2));
 /// # assert_eq!(*z1,Constant::Integer(1));
 /// ```
-pub type Callback = fn(solution_set: HashMap<VarName, Constant>) -> bool;
+pub type Callback<'a> = dyn FnMut(HashMap<VarName, Constant>) -> bool + 'a;
+
+/// Statistics about a finished run of Minion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RunStats {
+    /// Why search stopped.
+    pub search_end: SearchEnd,
+    /// The number of search nodes explored, if Minion reported it.
+    pub nodes: Option<u64>,
+}
+
+// Minion keeps the model, search state, and statistics of a run in C++ globals, so only one run can
+// happen at a time in this process. Runs on other threads meanwhile use a worker process.
+static MINION_LOCK: Mutex<()> = Mutex::new(());
 
-// Use globals to pass things between run_minion and the callback function.
-// Minion is (currently) single threaded anyways so the Mutexs' don't matter.
+/// The state of the run in progress, which the callback Minion calls needs.
+struct RunState<'a> {
+    callback: &'a mut Callback<'a>,
 
-// the current callback function
-static CALLBACK: Mutex<Option<Callback>> = Mutex::new(None);
+    // the variables we want to return, and their ordering in the print matrix
+    print_vars: Vec<VarName>,
 
-// the variables we want to return, and their ordering in the print matrix
-static PRINT_VARS: Mutex<Option<Vec<VarName>>> = Mutex::new(None);
+    // a panic in the callback, to be resumed once we are back on the Rust side of runMinion
+    panic: Option<Box<dyn Any + Send>>,
+}
 
-static LOCK: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+// runMinion's callback takes no arguments, so the state of the current run is passed to it
+// through here. Minion calls the callback on the thread that called runMinion.
+thread_local! {
+    static CURRENT_RUN: Cell<*mut RunState<'static>> = const { Cell::new(ptr::null_mut()) };
+}
 
 #[no_mangle]
 unsafe extern "C" fn run_callback() -> bool {
-    // get printvars from static PRINT_VARS if they exist.
-    // if not, return true and continue search.
+    let state_ptr = CURRENT_RUN.with(|x| x.get());
 
-    // Mutex poisoning is probably panic worthy.
-    #[allow(clippy::unwrap_used)]
-    let mut guard: MutexGuard<'_, Option<Vec<VarName>>> = PRINT_VARS.lock().unwrap();
-
-    if guard.is_none() {
+    // Minion is not being run from Rust on this thread, so there is nothing to report to.
+    if state_ptr.is_null() {
         return true;
     }
 
-    let print_vars = match &mut *guard {
-        Some(x) => x,
-        None => unreachable!(),
-    };
+    // Safety: the pointer is only set for the duration of runMinion, during which the state is
+    // borrowed only by this function.
+    let state = &mut *state_ptr;
 
-    if print_vars.is_empty() {
+    if state.print_vars.is_empty() {
         return true;
     }
 
     // build nice solutions view to be used by callback
     let mut solutions: HashMap<VarName, Constant> = HashMap::new();
 
-    for (i, var) in print_vars.iter().enumerate() {
+    for (i, var) in state.print_vars.iter().enumerate() {
         let solution_int: i32 = ffi::printMatrix_getValue(i as _);
         let solution: Constant = Constant::Integer(solution_int);
         solutions.insert(var.to_string(), solution);
     }
 
-    #[allow(clippy::unwrap_used)]
-    match *CALLBACK.lock().unwrap() {
-        None => true,
-        Some(func) => func(solutions),
+    // Panics must not unwind into Minion, so stop search and resume the panic afterwards.
+    match panic::catch_unwind(AssertUnwindSafe(|| (state.callback)(solutions))) {
+        Ok(continue_search) => continue_search,
+        Err(payload) => {
+            state.panic = Some(payload);
+            false
+        }
     }
 }
 
-/// Run Minion on the given [Model].
+/// Run Minion on the given [Model], with the default [MinionOptions].
 ///
 /// The given [callback](Callback) is ran whenever a new solution set is found.
+pub fn run_minion(
+    model: Model,
+    callback: impl FnMut(HashMap<VarName, Constant>) -> bool,
+) -> Result<(), MinionError> {
+    run_minion_with_options(model, callback, &MinionOptions::default()).map(|_| ())
+}
 
-// Turn it into a warning for this function, cant unwarn it directly above callback wierdness
-#[allow(clippy::unwrap_used)]
-pub fn run_minion(model: Model, callback: Callback) -> Result<(), MinionError> {
-    // Mutex poisoning is probably panic worthy.
-    *CALLBACK.lock().unwrap() = Some(callback);
+/// Run Minion on the given [Model] with the given [MinionOptions], returning statistics about
+/// the run.
+///
+/// The given [callback](Callback) is ran whenever a new solution set is found.
+///
+/// Minion keeps the state of a run in C++ globals, so only one run at a time can happen in this
+/// process. If another thread is already running Minion, this run happens in a worker process of its
+/// own, which reports its solutions back to the callback on this thread.
+///
+/// For the same reason, running Minion from inside a callback gives an error.
+pub fn run_minion_with_options(
+    model: Model,
+    callback: impl FnMut(HashMap<VarName, Constant>) -> bool,
+    options: &MinionOptions,
+) -> Result<RunStats, MinionError> {
+    // This thread would wait on the lock it already holds for the run in progress.
+    if CURRENT_RUN.with(|x| !x.get().is_null()) {
+        return Err(anyhow!("Minion cannot be run from inside the callback of another run").into());
+    }
 
-    let (lock, condvar) = &LOCK;
-    let mut _lock_guard = condvar
-        .wait_while(lock.lock().unwrap(), |locked| *locked)
-        .unwrap();
+    // A previous run panicking does not leave any state behind, so a poisoned lock is fine to use.
+    let _minion_guard: MutexGuard<'_, ()> = match MINION_LOCK.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return run_in_worker(model, callback, options),
+    };
 
-    *_lock_guard = true;
+    run_in_this_process(model, callback, options)
+}
 
+/// Runs Minion in this process. The caller must have sole use of Minion's globals.
+pub(crate) fn run_in_this_process(
+    model: Model,
+    mut callback: impl FnMut(HashMap<VarName, Constant>) -> bool,
+    options: &MinionOptions,
+) -> Result<RunStats, MinionError> {
     unsafe {
-        // TODO: something better than a manual spinlock
-        let search_opts = ffi::searchOptions_new();
-        let search_method = ffi::searchMethod_new();
-        let search_instance = ffi::instance_new();
+        // Declared first so that they are freed after the instance, whose constraints refer to them
+        let mut tuple_lists = vec![];
+        let search_opts = Scoped::new(ffi::searchOptions_new(), |x| {
+            ffi::searchOptions_free(x as _)
+        });
+        let search_method =
+            Scoped::new(ffi::searchMethod_new(), |x| ffi::searchMethod_free(x as _));
+        let search_instance = Scoped::new(ffi::instance_new(), |x| ffi::instance_free(x as _));
+
+        options.apply(search_opts.ptr, search_method.ptr);
+        let print_vars =
+            convert_model_to_raw(search_instance.ptr, &model, options, &mut tuple_lists)?;
 
-        convert_model_to_raw(search_instance, &model)?;
+        // Minion only records TimeOut when a limit stops search, so clear the value of the last run.
+        set_in_table("TimeOut".into(), "0".into());
+
+        let mut state = RunState {
+            callback: &mut callback,
+            print_vars,
+            panic: None,
+        };
 
+        CURRENT_RUN.with(|x| x.set((&mut state as *mut RunState<'_>).cast()));
         let res = ffi::runMinion(
-            search_opts,
-            search_method,
-            search_instance,
+            search_opts.ptr,
+            search_method.ptr,
+            search_instance.ptr,
             Some(run_callback),
         );
+        CURRENT_RUN.with(|x| x.set(ptr::null_mut()));
 
-        ffi::searchMethod_free(search_method);
-        ffi::searchOptions_free(search_opts);
-        ffi::instance_free(search_instance);
-
-        *_lock_guard = false;
-        std::mem::drop(_lock_guard);
-
-        condvar.notify_one();
+        if let Some(payload) = state.panic {
+            panic::resume_unwind(payload);
+        }
 
         match res {
-            0 => Ok(()),
+            0 => Ok(run_stats()),
             x => Err(MinionError::from(RuntimeError::from(x))),
         }
     }
 }
 
+/// Reads the statistics of the last run of Minion.
+fn run_stats() -> RunStats {
+    let nodes = get_from_table("Nodes".into()).and_then(|x| x.trim().parse::<u64>().ok());
+
+    // Minion records reaching its time or node limit as a timeout
+    let timed_out = get_from_table("TimeOut".into()).is_some_and(|x| x.trim() == "1");
+    let search_end = if timed_out {
+        SearchEnd::LimitReached
+    } else {
+        SearchEnd::Finished
+    };
+
+    RunStats { search_end, nodes }
+}
+
 unsafe fn convert_model_to_raw(
     instance: *mut ffi::ProbSpec_CSPInstance,
     model: &Model,
-) -> Result<(), MinionError> {
+    options: &MinionOptions,
+    tuple_lists: &mut Vec<Scoped<ffi::TupleList>>,
+) -> Result<Vec<VarName>, MinionError> {
     /*******************************/
     /*        Add variables        */
     /*******************************/
@@ -221,9 +296,7 @@ unsafe fn convert_model_to_raw(
     let search_vars = Scoped::new(ffi::vec_var_new(), |x| ffi::vec_var_free(x as _));
 
     // store variables and the order they will be returned inside rust for later use.
-    #[allow(clippy::unwrap_used)]
-    let mut print_vars_guard = PRINT_VARS.lock().unwrap();
-    *print_vars_guard = Some(vec![]);
+    let mut print_vars: Vec<VarName> = vec![];
 
     for var_name in model.named_variables.get_variable_order() {
         let c_str = CString::new(var_name.clone()).map_err(|_| {
@@ -238,19 +311,40 @@ unsafe fn convert_model_to_raw(
             .get_vartype(var_name.clone())
             .ok_or(anyhow!("Could not get var type for {:?}", var_name.clone()))?;
 
-        let (vartype_raw, domain_low, domain_high) = match vartype {
-            VarDomain::Bound(a, b) => Ok((ffi::VariableType_VAR_BOUND, a, b)),
-            VarDomain::Bool => Ok((ffi::VariableType_VAR_BOOL, 0, 1)), // TODO: will this work?
-            x => Err(MinionError::NotImplemented(format!("{:?}", x))),
-        }?;
-
-        ffi::newVar_ffi(
-            instance,
-            c_str.as_ptr() as _,
-            vartype_raw,
-            domain_low,
-            domain_high,
-        );
+        match vartype {
+            VarDomain::Bound(a, b) => ffi::newVar_ffi(
+                instance,
+                c_str.as_ptr() as _,
+                ffi::VariableType_VAR_BOUND,
+                a,
+                b,
+            ),
+            VarDomain::Discrete(a, b) => ffi::newVar_ffi(
+                instance,
+                c_str.as_ptr() as _,
+                ffi::VariableType_VAR_DISCRETE,
+                a,
+                b,
+            ),
+            // TODO: will this work?
+            VarDomain::Bool => ffi::newVar_ffi(
+                instance,
+                c_str.as_ptr() as _,
+                ffi::VariableType_VAR_BOOL,
+                0,
+                1,
+            ),
+            VarDomain::SparseBound(values) => {
+                // sparse variables are created from their values, not their bounds.
+                let raw_values = Scoped::new(ffi::vec_int_new(), |x| ffi::vec_int_free(x as _));
+                for value in values {
+                    ffi::vec_int_push_back(raw_values.ptr, value);
+                }
+                ffi::newVarSparse_ffi(instance, c_str.as_ptr() as _, raw_values.ptr);
+            }
+            #[allow(unreachable_patterns)]
+            x => return Err(MinionError::NotImplemented(format!("{:?}", x))),
+        }
 
         let var = ffi::getVarByName(instance, c_str.as_ptr() as _);
 
@@ -259,16 +353,16 @@ unsafe fn convert_model_to_raw(
         // add to the print vars stored in rust so to remember
         // the order for callback function.
 
-        #[allow(clippy::unwrap_used)]
-        (*print_vars_guard).as_mut().unwrap().push(var_name.clone());
+        print_vars.push(var_name.clone());
 
         ffi::vec_var_push_back(search_vars.ptr, var);
     }
 
     let search_order = Scoped::new(
-        ffi::searchOrder_new(search_vars.ptr, ffi::VarOrderEnum_ORDER_STATIC, false),
+        ffi::searchOrder_new(search_vars.ptr, options.get_var_order().to_raw(), false),
         |x| ffi::searchOrder_free(x as _),
     );
+    ffi::searchOrder_setValOrder_ffi(search_order.ptr, options.get_val_order().to_raw());
 
     ffi::instance_addSearchOrder(instance, search_order.ptr);
 
@@ -286,11 +380,11 @@ unsafe fn convert_model_to_raw(
             ffi::constraint_free(x as _)
         });
 
-        constraint_add_args(instance, raw_constraint.ptr, constraint)?;
+        constraint_add_args(instance, raw_constraint.ptr, constraint, tuple_lists)?;
         ffi::instance_addConstraint(instance, raw_constraint.ptr);
     }
 
-    Ok(())
+    Ok(print_vars)
 }
 
 unsafe fn get_constraint_type(constraint: &Constraint) -> Result<u32, MinionError> {
@@ -331,7 +425,7 @@ unsafe fn get_constraint_type(constraint: &Constraint) -> Result<u32, MinionErro
         Constraint::LexLeq(_, _) => Ok(ffi::ConstraintType_CT_LEXLEQ),
         Constraint::LexLess(_, _) => Ok(ffi::ConstraintType_CT_LEXLESS),
         Constraint::LexLeqQuick(_, _) => Ok(ffi::ConstraintType_CT_QUICK_LEXLEQ),
-        Constraint::LexLessQuick(_, _) => Ok(ffi::ConstraintType_CT_QUICK_LEXLEQ),
+        Constraint::LexLessQuick(_, _) => Ok(ffi::ConstraintType_CT_QUICK_LEXLESS),
         Constraint::WatchVecNeq(_, _) => Ok(ffi::ConstraintType_CT_WATCHED_VECNEQ),
         Constraint::WatchVecExistsLess(_, _) => Ok(ffi::ConstraintType_CT_WATCHED_VEC_OR_LESS),
         Constraint::Hamming(_, _, _) => Ok(ffi::ConstraintType_CT_WATCHED_HAMMING),
@@ -385,6 +479,7 @@ unsafe fn constraint_add_args(
     i: *mut ffi::ProbSpec_CSPInstance,
     r_constr: *mut ffi::ProbSpec_ConstraintBlob,
     constr: &Constraint,
+    tuple_lists: &mut Vec<Scoped<ffi::TupleList>>,
 ) -> Result<(), MinionError> {
     match constr {
         Constraint::SumGeq(lhs_vars, rhs_var) => {
@@ -456,38 +551,38 @@ unsafe fn constraint_add_args(
             Ok(())
         }
         Constraint::CheckAssign(a) => {
-            read_constraint(i, r_constr, (**a).clone())?;
+            read_constraint(i, r_constr, (**a).clone(), tuple_lists)?;
             Ok(())
         }
         Constraint::CheckGsa(a) => {
-            read_constraint(i, r_constr, (**a).clone())?;
+            read_constraint(i, r_constr, (**a).clone(), tuple_lists)?;
             Ok(())
         }
         Constraint::ForwardChecking(a) => {
-            read_constraint(i, r_constr, (**a).clone())?;
+            read_constraint(i, r_constr, (**a).clone(), tuple_lists)?;
             Ok(())
         }
         Constraint::Reify(a, b) => {
-            read_constraint(i, r_constr, (**a).clone())?;
+            read_constraint(i, r_constr, (**a).clone(), tuple_lists)?;
             read_var(i, r_constr, b)?;
             Ok(())
         }
         Constraint::ReifyImply(a, b) => {
-            read_constraint(i, r_constr, (**a).clone())?;
+            read_constraint(i, r_constr, (**a).clone(), tuple_lists)?;
             read_var(i, r_constr, b)?;
             Ok(())
         }
         Constraint::ReifyImplyQuick(a, b) => {
-            read_constraint(i, r_constr, (**a).clone())?;
+            read_constraint(i, r_constr, (**a).clone(), tuple_lists)?;
             read_var(i, r_constr, b)?;
             Ok(())
         }
         Constraint::WatchedAnd(a) => {
-            read_constraint_list(i, r_constr, a)?;
+            read_constraint_list(i, r_constr, a, tuple_lists)?;
             Ok(())
         }
         Constraint::WatchedOr(a) => {
-            read_constraint_list(i, r_constr, a)?;
+            read_constraint_list(i, r_constr, a, tuple_lists)?;
             Ok(())
         }
         Constraint::GacAllDiff(a) => {
@@ -531,57 +626,236 @@ unsafe fn constraint_add_args(
             read_var(i, r_constr, c)?;
             Ok(())
         }
-        //Constraint::LitSumGeq(_, _, _) => todo!(),
-        //Constraint::Gcc(_, _, _) => todo!(),
-        //Constraint::GccWeak(_, _, _) => todo!(),
-        //Constraint::LexLeqRv(_, _) => todo!(),
-        //Constraint::LexLeq(_, _) => todo!(),
-        //Constraint::LexLess(_, _) => todo!(),
-        //Constraint::LexLeqQuick(_, _) => todo!(),
-        //Constraint::LexLessQuick(_, _) => todo!(),
-        //Constraint::WatchVecNeq(_, _) => todo!(),
-        //Constraint::WatchVecExistsLess(_, _) => todo!(),
-        //Constraint::Hamming(_, _, _) => todo!(),
-        //Constraint::NotHamming(_, _, _) => todo!(),
-        //Constraint::FrameUpdate(_, _, _, _, _) => todo!(),
-        //Constraint::NegativeTable(_, _) => todo!(),
-        //Constraint::Table(_, _) => todo!(),
-        //Constraint::GacSchema(_, _) => todo!(),
-        //Constraint::LightTable(_, _) => todo!(),
-        //Constraint::Mddc(_, _) => todo!(),
-        //Constraint::NegativeMddc(_, _) => todo!(),
-        //Constraint::Str2Plus(_, _) => todo!(),
-        //Constraint::Max(_, _) => todo!(),
-        //Constraint::Min(_, _) => todo!(),
-        //Constraint::NvalueGeq(_, _) => todo!(),
-        //Constraint::NvalueLeq(_, _) => todo!(),
-        //Constraint::Element(_, _, _) => todo!(),
-        //Constraint::ElementOne(_, _, _) => todo!(),
-        //Constraint::ElementUndefZero(_, _, _) => todo!(),
-        //Constraint::WatchElement(_, _, _) => todo!(),
-        //Constraint::WatchElementOne(_, _, _) => todo!(),
-        //Constraint::WatchElementOneUndefZero(_, _, _) => todo!(),
-        //Constraint::WatchElementUndefZero(_, _, _) => todo!(),
-        //Constraint::WLiteral(_, _) => todo!(),
-        //Constraint::WNotLiteral(_, _) => todo!(),
-        //Constraint::WInIntervalSet(_, _) => todo!(),
-        //Constraint::WInRange(_, _) => todo!(),
+        Constraint::LitSumGeq(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_constant_list(r_constr, b)?;
+            read_constant(r_constr, c)?;
+            Ok(())
+        }
+        Constraint::Gcc(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_constant_list(r_constr, b)?;
+            read_list(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::GccWeak(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_constant_list(r_constr, b)?;
+            read_list(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::LexLeqRv(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::LexLeq(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::LexLess(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::LexLeqQuick(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::LexLessQuick(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::WatchVecNeq(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::WatchVecExistsLess(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::Hamming(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            read_constant(r_constr, c)?;
+            Ok(())
+        }
+        Constraint::NotHamming(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            read_constant(r_constr, c)?;
+            Ok(())
+        }
+        Constraint::FrameUpdate(a, b, c, d, e) => {
+            read_list(i, r_constr, a)?;
+            read_list(i, r_constr, b)?;
+            read_list(i, r_constr, c)?;
+            read_list(i, r_constr, d)?;
+            read_constant(r_constr, e)?;
+            Ok(())
+        }
+        Constraint::NegativeTable(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_tuple_list(r_constr, b, tuple_lists)?;
+            Ok(())
+        }
+        Constraint::Table(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_tuple_list(r_constr, b, tuple_lists)?;
+            Ok(())
+        }
+        Constraint::GacSchema(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_tuple_list(r_constr, b, tuple_lists)?;
+            Ok(())
+        }
+        Constraint::LightTable(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_tuple_list(r_constr, b, tuple_lists)?;
+            Ok(())
+        }
+        Constraint::Mddc(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_tuple_list(r_constr, b, tuple_lists)?;
+            Ok(())
+        }
+        Constraint::NegativeMddc(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_tuple_list(r_constr, b, tuple_lists)?;
+            Ok(())
+        }
+        Constraint::Str2Plus(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_tuple_list(r_constr, b, tuple_lists)?;
+            Ok(())
+        }
+        Constraint::Max(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::Min(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::NvalueGeq(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::NvalueLeq(a, b) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::Element(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            read_var(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::ElementOne(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            read_var(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::ElementUndefZero(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            read_var(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::WatchElement(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            read_var(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::WatchElementOne(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            read_var(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::WatchElementOneUndefZero(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            read_var(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::WatchElementUndefZero(a, b, c) => {
+            read_list(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            read_var(i, r_constr, c)?;
+            Ok(())
+        }
+        Constraint::WLiteral(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_constant(r_constr, b)?;
+            Ok(())
+        }
+        Constraint::WNotLiteral(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_constant(r_constr, b)?;
+            Ok(())
+        }
+        Constraint::WInIntervalSet(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_constant_list(r_constr, b)?;
+            Ok(())
+        }
+        Constraint::WInRange(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_constant_list(r_constr, b)?;
+            Ok(())
+        }
         Constraint::WInset(a, b) => {
             read_var(i, r_constr, a)?;
             read_constant_list(r_constr, b)?;
             Ok(())
         }
-        //Constraint::WNotInRange(_, _) => todo!(),
-        //Constraint::WNotInset(_, _) => todo!(),
-        //Constraint::Abs(_, _) => todo!(),
+        Constraint::WNotInRange(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_constant_list(r_constr, b)?;
+            Ok(())
+        }
+        Constraint::WNotInset(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_constant_list(r_constr, b)?;
+            Ok(())
+        }
+        Constraint::Abs(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
         Constraint::DisEq(a, b) => {
             read_var(i, r_constr, a)?;
             read_var(i, r_constr, b)?;
             Ok(())
         }
-        //Constraint::MinusEq(_, _) => todo!(),
-        //Constraint::GacEq(_, _) => todo!(),
-        //Constraint::WatchLess(_, _) => todo!(),
+        Constraint::MinusEq(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::GacEq(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
+        Constraint::WatchLess(a, b) => {
+            read_var(i, r_constr, a)?;
+            read_var(i, r_constr, b)?;
+            Ok(())
+        }
         // TODO: ensure that this is a bool?
         Constraint::WatchNeq(a, b) => {
             read_var(i, r_constr, a)?;
@@ -723,6 +997,38 @@ unsafe fn read_constant_list(
     Ok(())
 }
 
+/// The tuple list is added to `tuple_lists`, which must outlive the run, as the constraint refers to it
+/// until the instance is freed.
+unsafe fn read_tuple_list(
+    raw_constraint: *mut ffi::ProbSpec_ConstraintBlob,
+    tuples: &[Tuple],
+    tuple_lists: &mut Vec<Scoped<ffi::TupleList>>,
+) -> Result<(), MinionError> {
+    let raw_tuples = Scoped::new(ffi::vec_vec_int_new(), |x| ffi::vec_vec_int_free(x as _));
+
+    for tuple in tuples.iter() {
+        let raw_tuple = Scoped::new(ffi::vec_int_new(), |x| ffi::vec_int_free(x as _));
+        for constant in tuple.iter() {
+            let val = match constant {
+                Constant::Integer(n) => Ok(*n),
+                Constant::Bool(true) => Ok(1),
+                Constant::Bool(false) => Ok(0),
+                #[allow(unreachable_patterns)]
+                x => Err(MinionError::NotImplemented(format!("{:?}", x))),
+            }?;
+            ffi::vec_int_push_back(raw_tuple.ptr, val);
+        }
+        ffi::vec_vec_int_push_back(raw_tuples.ptr, raw_tuple.ptr);
+    }
+
+    let tuple_list = Scoped::new(ffi::tupleList_new(raw_tuples.ptr), |x| {
+        ffi::tupleList_free(x as _)
+    });
+    ffi::constraint_setTuples(raw_constraint, tuple_list.ptr);
+    tuple_lists.push(tuple_list);
+    Ok(())
+}
+
 //TODO: check if the inner constraint is listed in the model or not?
 //Does this matter?
 // TODO: type-check inner constraints vars and tuples and so on?
@@ -730,13 +1036,19 @@ unsafe fn read_constraint(
     instance: *mut ffi::ProbSpec_CSPInstance,
     raw_constraint: *mut ffi::ProbSpec_ConstraintBlob,
     inner_constraint: Constraint,
+    tuple_lists: &mut Vec<Scoped<ffi::TupleList>>,
 ) -> Result<(), MinionError> {
     let constraint_type = get_constraint_type(&inner_constraint)?;
     let raw_inner_constraint = Scoped::new(ffi::constraint_new(constraint_type), |x| {
         ffi::constraint_free(x as _)
     });
 
-    constraint_add_args(instance, raw_inner_constraint.ptr, &inner_constraint)?;
+    constraint_add_args(
+        instance,
+        raw_inner_constraint.ptr,
+        &inner_constraint,
+        tuple_lists,
+    )?;
 
     ffi::constraint_addConstraint(raw_constraint, raw_inner_constraint.ptr);
     Ok(())
@@ -746,6 +1058,7 @@ unsafe fn read_constraint_list(
     instance: *mut ffi::ProbSpec_CSPInstance,
     raw_constraint: *mut ffi::ProbSpec_ConstraintBlob,
     inner_constraints: &[Constraint],
+    tuple_lists: &mut Vec<Scoped<ffi::TupleList>>,
 ) -> Result<(), MinionError> {
     let raw_inners = Scoped::new(ffi::vec_constraints_new(), |x| {
         ffi::vec_constraints_free(x as _)
@@ -756,7 +1069,12 @@ unsafe fn read_constraint_list(
             ffi::constraint_free(x as _)
         });
 
-        constraint_add_args(instance, raw_inner_constraint.ptr, inner_constraint)?;
+        constraint_add_args(
+            instance,
+            raw_inner_constraint.ptr,
+            inner_constraint,
+            tuple_lists,
+        )?;
         ffi::vec_constraints_push_back(raw_inners.ptr, raw_inner_constraint.ptr);
     }
 
@@ -0,0 +1,196 @@
+//! Runs of Minion in a worker process, for when another thread is already using Minion's globals.
+//!
+//! The worker is a fork of this process, so it already has the model and options. It reports each
+//! solution to the parent as a line of values in the order of the model's variables, and waits for
+//! the parent to reply whether search should continue. Its last line says how the run ended:
+//!
+//! ```text
+//! solution 1 2 3
+//! end finished 42
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    os::fd::{FromRawFd, RawFd},
+    panic::{self, AssertUnwindSafe},
+};
+
+use anyhow::anyhow;
+
+use crate::ast::{Constant, Model, VarName};
+use crate::error::{MinionError, RuntimeError};
+use crate::ffi;
+use crate::options::{MinionOptions, SearchEnd};
+use crate::run::{run_in_this_process, RunStats};
+
+/// Runs Minion on the given model in a worker process, giving its solutions to the callback.
+pub(crate) fn run_in_worker(
+    model: Model,
+    mut callback: impl FnMut(HashMap<VarName, Constant>) -> bool,
+    options: &MinionOptions,
+) -> Result<RunStats, MinionError> {
+    let (from_worker, to_parent) = pipe()?;
+    let (from_parent, to_worker) = pipe()?;
+
+    // Safety: the worker only runs Minion and writes to its pipe, then exits without returning.
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(anyhow!("could not start a Minion worker process").into());
+    }
+    if pid == 0 {
+        drop(from_worker);
+        drop(to_worker);
+        worker_main(model, options, to_parent, from_parent);
+    }
+    drop(to_parent);
+    drop(from_parent);
+
+    // Kills the worker if we stop reading from it early, e.g. if the callback panics.
+    let worker = Worker { pid };
+    let variables = model.named_variables.get_variable_order();
+    let mut from_worker = BufReader::new(from_worker);
+    let mut to_worker = to_worker;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if from_worker
+            .read_line(&mut line)
+            .map_err(anyhow::Error::from)?
+            == 0
+        {
+            return Err(
+                anyhow!("the Minion worker process exited before finishing its run").into(),
+            );
+        }
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("solution") => {
+                let values = words
+                    .map(|x| x.parse::<i32>().map(Constant::Integer))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(anyhow::Error::from)?;
+                let solution = variables.iter().cloned().zip(values).collect();
+                let reply = if callback(solution) { b"1" } else { b"0" };
+                to_worker.write_all(reply).map_err(anyhow::Error::from)?;
+            }
+            Some("end") => {
+                let search_end = match words.next() {
+                    Some("limit") => SearchEnd::LimitReached,
+                    _ => SearchEnd::Finished,
+                };
+                let nodes = words.next().and_then(|x| x.parse::<u64>().ok());
+                worker.wait();
+                return Ok(RunStats { search_end, nodes });
+            }
+            Some("error") => {
+                worker.wait();
+                return Err(read_error(words.next(), line.trim_end()));
+            }
+            _ => return Err(anyhow!("unexpected output from Minion worker: {:?}", line).into()),
+        }
+    }
+}
+
+/// The body of the worker process, which never returns.
+fn worker_main(model: Model, options: &MinionOptions, to_parent: File, from_parent: File) -> ! {
+    let (mut to_parent, mut from_parent) = (to_parent, from_parent);
+    let variables = model.named_variables.get_variable_order();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        // Minion's globals are copied from the parent, which may be in the middle of a run.
+        unsafe { ffi::resetMinion() };
+
+        run_in_this_process(
+            model,
+            |solution| {
+                let values: Vec<String> = variables
+                    .iter()
+                    .map(|var| match solution.get(var) {
+                        Some(Constant::Bool(x)) => i32::from(*x).to_string(),
+                        Some(Constant::Integer(x)) => x.to_string(),
+                        _ => String::new(),
+                    })
+                    .collect();
+                let mut reply = [0u8; 1];
+                writeln!(to_parent, "solution {}", values.join(" ")).is_ok()
+                    && from_parent.read_exact(&mut reply).is_ok()
+                    && reply[0] == b'1'
+            },
+            options,
+        )
+    }));
+
+    let last_line = match result {
+        Ok(Ok(RunStats { search_end, nodes })) => {
+            let search_end = match search_end {
+                SearchEnd::LimitReached => "limit",
+                _ => "finished",
+            };
+            let nodes = nodes.map_or("-".to_owned(), |x| x.to_string());
+            format!("end {search_end} {nodes}")
+        }
+        Ok(Err(e)) => write_error(&e),
+        Err(_) => "error other the Minion worker process panicked".to_owned(),
+    };
+    let _ = writeln!(to_parent, "{last_line}");
+
+    // Safety: exiting straight away skips the destructors and exit handlers of the parent's state.
+    unsafe { libc::_exit(0) }
+}
+
+/// A running worker process.
+struct Worker {
+    pid: libc::pid_t,
+}
+
+impl Worker {
+    /// Waits for the worker to exit once it has sent its last line.
+    fn wait(self) {
+        unsafe { libc::waitpid(self.pid, std::ptr::null_mut(), 0) };
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        unsafe {
+            libc::kill(self.pid, libc::SIGKILL);
+            libc::waitpid(self.pid, std::ptr::null_mut(), 0);
+        }
+    }
+}
+
+/// Creates a pipe, returning its read and write ends.
+fn pipe() -> Result<(File, File), MinionError> {
+    let mut fds: [RawFd; 2] = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(anyhow!("could not create a pipe to a Minion worker process").into());
+    }
+    // Safety: the pipe has just been created, and is owned by nothing else.
+    unsafe { Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))) }
+}
+
+/// Formats an error of the worker as its last line, to be read back by [`read_error`].
+fn write_error(error: &MinionError) -> String {
+    let message = |x: String| x.replace('\n', " ");
+    match error {
+        MinionError::RuntimeError(RuntimeError::InvalidInstance) => "error invalid-instance".into(),
+        MinionError::RuntimeError(_) => "error unknown".into(),
+        MinionError::NotImplemented(x) => format!("error not-implemented {}", message(x.clone())),
+        x => format!("error other {}", message(x.to_string())),
+    }
+}
+
+/// Reads an error written by [`write_error`], given its kind and the whole line.
+fn read_error(kind: Option<&str>, line: &str) -> MinionError {
+    let message = || line.splitn(3, ' ').nth(2).unwrap_or_default().to_owned();
+    match kind {
+        Some("invalid-instance") => RuntimeError::InvalidInstance.into(),
+        Some("not-implemented") => MinionError::NotImplemented(message()),
+        Some("other") => anyhow!(message()).into(),
+        _ => RuntimeError::UnknownError.into(),
+    }
+}
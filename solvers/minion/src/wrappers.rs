@@ -28,3 +28,14 @@ pub fn get_from_table(key: String) -> Option<String> {
         }
     }
 }
+
+/// Sets a given value in Minion's TableOut.
+pub(crate) fn set_in_table(key: String, value: String) {
+    unsafe {
+        #[allow(clippy::expect_used)]
+        let key = CString::new(key).expect("");
+        #[allow(clippy::expect_used)]
+        let value = CString::new(value).expect("");
+        ffi::TableOut_set_ffi(key.as_ptr() as _, value.as_ptr() as _);
+    }
+}
@@ -0,0 +1,655 @@
+//! One test for each constraint and each kind of variable, checking that Minion finds the expected number of
+//! solutions.
+//!
+//! Each model is small enough that the expected count can be checked by hand, and is given next to the
+//! meaning of the constraint.
+
+use minion_rs::ast::{Constant, Constraint, Model, Tuple, Var, VarDomain};
+use minion_rs::run_minion;
+
+/// Solve a model with the given variables and constraint, returning the number of solutions.
+#[allow(clippy::unwrap_used)]
+fn count_solutions(vars: &[(&str, VarDomain)], constraint: Constraint) -> i32 {
+    let mut model = Model::new();
+    for (name, domain) in vars {
+        model
+            .named_variables
+            .add_var(name.to_string(), domain.clone());
+    }
+    model.constraints.push(constraint);
+
+    let mut count = 0;
+    run_minion(model, |_| {
+        count += 1;
+        true
+    })
+    .unwrap();
+    count
+}
+
+fn var(name: &str) -> Var {
+    Var::NameRef(name.to_string())
+}
+
+fn vars(names: &[&str]) -> Vec<Var> {
+    names.iter().map(|name| var(name)).collect()
+}
+
+fn int(i: i32) -> Constant {
+    Constant::Integer(i)
+}
+
+fn ints(is: &[i32]) -> Vec<Constant> {
+    is.iter().map(|i| int(*i)).collect()
+}
+
+fn tuples(rows: &[&[i32]]) -> Vec<Tuple> {
+    rows.iter().map(|row| ints(row)).collect()
+}
+
+fn bound(names: &[&'static str], lo: i32, hi: i32) -> Vec<(&'static str, VarDomain)> {
+    names
+        .iter()
+        .map(|name| (*name, VarDomain::Bound(lo, hi)))
+        .collect()
+}
+
+fn bools(names: &[&'static str]) -> Vec<(&'static str, VarDomain)> {
+    names.iter().map(|name| (*name, VarDomain::Bool)).collect()
+}
+
+/// x, y in 1..3, z in 1..5
+fn xyz() -> Vec<(&'static str, VarDomain)> {
+    let mut vs = bound(&["x", "y"], 1, 3);
+    vs.push(("z", VarDomain::Bound(1, 5)));
+    vs
+}
+
+/// x, y, z in 0..3, 1..2, 0..3 for division and modulo
+fn divisible() -> Vec<(&'static str, VarDomain)> {
+    vec![
+        ("x", VarDomain::Bound(0, 3)),
+        ("y", VarDomain::Bound(1, 2)),
+        ("z", VarDomain::Bound(0, 3)),
+    ]
+}
+
+/// As [`divisible`], but y may also be 0
+fn divisible_by_zero() -> Vec<(&'static str, VarDomain)> {
+    vec![
+        ("x", VarDomain::Bound(0, 3)),
+        ("y", VarDomain::Bound(0, 2)),
+        ("z", VarDomain::Bound(0, 3)),
+    ]
+}
+
+/// x = y, with x, y in 1..3
+fn x_eq_y() -> Constraint {
+    Constraint::Eq(var("x"), var("y"))
+}
+
+/// Two vectors [a, b] and [c, d] of values in 1..2
+fn two_vectors() -> Vec<(&'static str, VarDomain)> {
+    bound(&["a", "b", "c", "d"], 1, 2)
+}
+
+/// The rows (1, 2), (2, 3) and (3, 1) over x, y in 1..3
+fn table_rows() -> Vec<Tuple> {
+    tuples(&[&[1, 2], &[2, 3], &[3, 1]])
+}
+
+/// x[i] = e, with x and y in 1..3 and e in 1..3
+fn element_vars(lo: i32, hi: i32) -> Vec<(&'static str, VarDomain)> {
+    let mut vs = bound(&["x", "y", "e"], 1, 3);
+    vs.push(("i", VarDomain::Bound(lo, hi)));
+    vs
+}
+
+#[test]
+fn sum_geq() {
+    // x + y >= z
+    let c = Constraint::SumGeq(vars(&["x", "y"]), var("z"));
+    assert_eq!(count_solutions(&xyz(), c), 35);
+}
+
+#[test]
+fn sum_leq() {
+    // x + y <= z
+    let c = Constraint::SumLeq(vars(&["x", "y"]), var("z"));
+    assert_eq!(count_solutions(&xyz(), c), 18);
+}
+
+#[test]
+fn ineq() {
+    // x <= y - 1
+    let c = Constraint::Ineq(var("x"), var("y"), int(-1));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn eq() {
+    // x = y, with y in 2..4
+    let vs = vec![("x", VarDomain::Bound(1, 3)), ("y", VarDomain::Bound(2, 4))];
+    assert_eq!(count_solutions(&vs, x_eq_y()), 2);
+}
+
+#[test]
+fn difference() {
+    // |x - y| = z, with z in 0..2
+    let mut vs = bound(&["x", "y"], 1, 3);
+    vs.push(("z", VarDomain::Bound(0, 2)));
+    let c = Constraint::Difference((var("x"), var("y")), var("z"));
+    assert_eq!(count_solutions(&vs, c), 9);
+}
+
+#[test]
+fn div() {
+    // x / y = z
+    let c = Constraint::Div((var("x"), var("y")), var("z"));
+    assert_eq!(count_solutions(&divisible(), c), 8);
+}
+
+#[test]
+fn div_undef_zero() {
+    // x / y = z, or z = 0 if y = 0
+    let c = Constraint::DivUndefZero((var("x"), var("y")), var("z"));
+    assert_eq!(count_solutions(&divisible_by_zero(), c), 12);
+}
+
+#[test]
+fn modulo() {
+    // x % y = z
+    let c = Constraint::Modulo((var("x"), var("y")), var("z"));
+    assert_eq!(count_solutions(&divisible(), c), 8);
+}
+
+#[test]
+fn modulo_undef_zero() {
+    // x % y = z, or z = 0 if y = 0
+    let c = Constraint::ModuloUndefZero((var("x"), var("y")), var("z"));
+    assert_eq!(count_solutions(&divisible_by_zero(), c), 12);
+}
+
+#[test]
+fn pow() {
+    // x ** y = z, with x in 1..2, y in 0..2, z in 0..4
+    let vs = vec![
+        ("x", VarDomain::Bound(1, 2)),
+        ("y", VarDomain::Bound(0, 2)),
+        ("z", VarDomain::Bound(0, 4)),
+    ];
+    let c = Constraint::Pow((var("x"), var("y")), var("z"));
+    assert_eq!(count_solutions(&vs, c), 6);
+}
+
+#[test]
+fn product() {
+    // x * y = z, with x, y in 0..2, z in 0..4
+    let mut vs = bound(&["x", "y"], 0, 2);
+    vs.push(("z", VarDomain::Bound(0, 4)));
+    let c = Constraint::Product((var("x"), var("y")), var("z"));
+    assert_eq!(count_solutions(&vs, c), 9);
+}
+
+#[test]
+fn weighted_sum_geq() {
+    // 2x + y >= z, with x, y in 0..2, z in 0..6
+    let mut vs = bound(&["x", "y"], 0, 2);
+    vs.push(("z", VarDomain::Bound(0, 6)));
+    let c = Constraint::WeightedSumGeq(ints(&[2, 1]), vars(&["x", "y"]), var("z"));
+    assert_eq!(count_solutions(&vs, c), 36);
+}
+
+#[test]
+fn weighted_sum_leq() {
+    // 2x + y <= z, with x, y in 0..2, z in 0..6
+    let mut vs = bound(&["x", "y"], 0, 2);
+    vs.push(("z", VarDomain::Bound(0, 6)));
+    let c = Constraint::WeightedSumLeq(ints(&[2, 1]), vars(&["x", "y"]), var("z"));
+    assert_eq!(count_solutions(&vs, c), 36);
+}
+
+#[test]
+fn check_assign() {
+    let c = Constraint::CheckAssign(Box::new(x_eq_y()));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn check_gsa() {
+    let c = Constraint::CheckGsa(Box::new(x_eq_y()));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn forward_checking() {
+    let c = Constraint::ForwardChecking(Box::new(x_eq_y()));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn reify() {
+    // (x = y) <-> r
+    let mut vs = bound(&["x", "y"], 1, 3);
+    vs.push(("r", VarDomain::Bool));
+    let c = Constraint::Reify(Box::new(x_eq_y()), var("r"));
+    assert_eq!(count_solutions(&vs, c), 9);
+}
+
+#[test]
+fn reify_imply() {
+    // r -> (x = y)
+    let mut vs = bound(&["x", "y"], 1, 3);
+    vs.push(("r", VarDomain::Bool));
+    let c = Constraint::ReifyImply(Box::new(x_eq_y()), var("r"));
+    assert_eq!(count_solutions(&vs, c), 12);
+}
+
+#[test]
+fn reify_imply_quick() {
+    // r -> (x = y)
+    let mut vs = bound(&["x", "y"], 1, 3);
+    vs.push(("r", VarDomain::Bool));
+    let c = Constraint::ReifyImplyQuick(Box::new(x_eq_y()), var("r"));
+    assert_eq!(count_solutions(&vs, c), 12);
+}
+
+#[test]
+fn watched_and() {
+    // x != y /\ x != 2
+    let c = Constraint::WatchedAnd(vec![
+        Constraint::DisEq(var("x"), var("y")),
+        Constraint::WNotLiteral(var("x"), int(2)),
+    ]);
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 4);
+}
+
+#[test]
+fn watched_or() {
+    // x = y \/ x = 2
+    let c = Constraint::WatchedOr(vec![x_eq_y(), Constraint::WLiteral(var("x"), int(2))]);
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 5);
+}
+
+#[test]
+fn gac_all_diff() {
+    let c = Constraint::GacAllDiff(vars(&["x", "y", "z"]));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z"], 1, 3), c), 6);
+}
+
+#[test]
+fn all_diff() {
+    let c = Constraint::AllDiff(vars(&["x", "y", "z"]));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z"], 1, 3), c), 6);
+}
+
+#[test]
+fn all_diff_matrix() {
+    // In the 2x2 matrix [[a, b], [c, d]], some matching of rows to columns has 1 in every matched cell
+    let c = Constraint::AllDiffMatrix(vars(&["a", "b", "c", "d"]), int(1));
+    assert_eq!(count_solutions(&two_vectors(), c), 7);
+}
+
+#[test]
+fn watch_sum_geq() {
+    // a + b + c >= 2
+    let c = Constraint::WatchSumGeq(vars(&["a", "b", "c"]), int(2));
+    assert_eq!(count_solutions(&bools(&["a", "b", "c"]), c), 4);
+}
+
+#[test]
+fn watch_sum_leq() {
+    // a + b + c <= 1
+    let c = Constraint::WatchSumLeq(vars(&["a", "b", "c"]), int(1));
+    assert_eq!(count_solutions(&bools(&["a", "b", "c"]), c), 4);
+}
+
+#[test]
+fn occurrence_geq() {
+    // 1 occurs in [x, y, z] at least twice
+    let c = Constraint::OccurrenceGeq(vars(&["x", "y", "z"]), int(1), int(2));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z"], 1, 2), c), 4);
+}
+
+#[test]
+fn occurrence_leq() {
+    // 1 occurs in [x, y, z] at most once
+    let c = Constraint::OccurrenceLeq(vars(&["x", "y", "z"]), int(1), int(1));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z"], 1, 2), c), 4);
+}
+
+#[test]
+fn occurrence() {
+    // 1 occurs in [x, y] exactly n times, with n in 0..2
+    let mut vs = bound(&["x", "y"], 1, 2);
+    vs.push(("n", VarDomain::Bound(0, 2)));
+    let c = Constraint::Occurrence(vars(&["x", "y"]), int(1), var("n"));
+    assert_eq!(count_solutions(&vs, c), 4);
+}
+
+#[test]
+fn lit_sum_geq() {
+    // At least two of x = 1, y = 2, z = 1
+    let c = Constraint::LitSumGeq(vars(&["x", "y", "z"]), ints(&[1, 2, 1]), int(2));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z"], 1, 2), c), 4);
+}
+
+#[test]
+fn gcc() {
+    // 1 occurs in [x, y] n1 times and 2 occurs n2 times, with n1, n2 in 0..2
+    let mut vs = bound(&["x", "y"], 1, 2);
+    vs.extend(bound(&["n1", "n2"], 0, 2));
+    let c = Constraint::Gcc(vars(&["x", "y"]), ints(&[1, 2]), vars(&["n1", "n2"]));
+    assert_eq!(count_solutions(&vs, c), 4);
+}
+
+#[test]
+fn gcc_weak() {
+    let mut vs = bound(&["x", "y"], 1, 2);
+    vs.extend(bound(&["n1", "n2"], 0, 2));
+    let c = Constraint::GccWeak(vars(&["x", "y"]), ints(&[1, 2]), vars(&["n1", "n2"]));
+    assert_eq!(count_solutions(&vs, c), 4);
+}
+
+#[test]
+fn lex_leq_rv() {
+    // [a, b] <=lex [c, d]
+    let c = Constraint::LexLeqRv(vars(&["a", "b"]), vars(&["c", "d"]));
+    assert_eq!(count_solutions(&two_vectors(), c), 10);
+}
+
+#[test]
+fn lex_leq() {
+    let c = Constraint::LexLeq(vars(&["a", "b"]), vars(&["c", "d"]));
+    assert_eq!(count_solutions(&two_vectors(), c), 10);
+}
+
+#[test]
+fn lex_less() {
+    // [a, b] <lex [c, d]
+    let c = Constraint::LexLess(vars(&["a", "b"]), vars(&["c", "d"]));
+    assert_eq!(count_solutions(&two_vectors(), c), 6);
+}
+
+#[test]
+fn lex_leq_quick() {
+    let c = Constraint::LexLeqQuick(vars(&["a", "b"]), vars(&["c", "d"]));
+    assert_eq!(count_solutions(&two_vectors(), c), 10);
+}
+
+#[test]
+fn lex_less_quick() {
+    let c = Constraint::LexLessQuick(vars(&["a", "b"]), vars(&["c", "d"]));
+    assert_eq!(count_solutions(&two_vectors(), c), 6);
+}
+
+#[test]
+fn watch_vec_neq() {
+    // [a, b] != [c, d]
+    let c = Constraint::WatchVecNeq(vars(&["a", "b"]), vars(&["c", "d"]));
+    assert_eq!(count_solutions(&two_vectors(), c), 12);
+}
+
+#[test]
+fn watch_vec_exists_less() {
+    // a < c \/ b < d
+    let c = Constraint::WatchVecExistsLess(vars(&["a", "b"]), vars(&["c", "d"]));
+    assert_eq!(count_solutions(&two_vectors(), c), 7);
+}
+
+#[test]
+fn hamming() {
+    // [a, b] and [c, d] differ in at least 2 places
+    let c = Constraint::Hamming(vars(&["a", "b"]), vars(&["c", "d"]), int(2));
+    assert_eq!(count_solutions(&two_vectors(), c), 4);
+}
+
+#[test]
+fn not_hamming() {
+    // [a, b] and [c, d] differ in at most 1 place
+    let c = Constraint::NotHamming(vars(&["a", "b"]), vars(&["c", "d"]), int(1));
+    assert_eq!(count_solutions(&two_vectors(), c), 12);
+}
+
+#[test]
+fn frame_update() {
+    // Block 0 of [1, 2] is moved to block 1 of [a, b], and the other block fills the rest: [a, b] = [2, 1]
+    let c = Constraint::FrameUpdate(
+        vec![Var::ConstantAsVar(1), Var::ConstantAsVar(2)],
+        vars(&["a", "b"]),
+        vec![Var::ConstantAsVar(0)],
+        vec![Var::ConstantAsVar(1)],
+        int(1),
+    );
+    assert_eq!(count_solutions(&bound(&["a", "b"], 1, 2), c), 1);
+}
+
+#[test]
+fn negative_table() {
+    let c = Constraint::NegativeTable(vars(&["x", "y"]), table_rows());
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 6);
+}
+
+#[test]
+fn table() {
+    let c = Constraint::Table(vars(&["x", "y"]), table_rows());
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn gac_schema() {
+    let c = Constraint::GacSchema(vars(&["x", "y"]), table_rows());
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn light_table() {
+    let c = Constraint::LightTable(vars(&["x", "y"]), table_rows());
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn mddc() {
+    let c = Constraint::Mddc(vars(&["x", "y"]), table_rows());
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn negative_mddc() {
+    let c = Constraint::NegativeMddc(vars(&["x", "y"]), table_rows());
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 6);
+}
+
+#[test]
+fn str2plus() {
+    let c = Constraint::Str2Plus(vars(&["x", "y"]), table_rows());
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn max() {
+    // max([x, y]) = z
+    let c = Constraint::Max(vars(&["x", "y"]), var("z"));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z"], 1, 3), c), 9);
+}
+
+#[test]
+fn min() {
+    // min([x, y]) = z
+    let c = Constraint::Min(vars(&["x", "y"]), var("z"));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z"], 1, 3), c), 9);
+}
+
+#[test]
+fn nvalue_geq() {
+    // [x, y, z] has at least n distinct values
+    let c = Constraint::NvalueGeq(vars(&["x", "y", "z"]), var("n"));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z", "n"], 1, 3), c), 57);
+}
+
+#[test]
+fn nvalue_leq() {
+    // [x, y, z] has at most n distinct values
+    let c = Constraint::NvalueLeq(vars(&["x", "y", "z"]), var("n"));
+    assert_eq!(count_solutions(&bound(&["x", "y", "z", "n"], 1, 3), c), 51);
+}
+
+#[test]
+fn element() {
+    // [x, y][i] = e, indexed from 0
+    let c = Constraint::Element(vars(&["x", "y"]), var("i"), var("e"));
+    assert_eq!(count_solutions(&element_vars(0, 1), c), 18);
+}
+
+#[test]
+fn element_one() {
+    // [x, y][i] = e, indexed from 1
+    let c = Constraint::ElementOne(vars(&["x", "y"]), var("i"), var("e"));
+    assert_eq!(count_solutions(&element_vars(1, 2), c), 18);
+}
+
+#[test]
+fn element_undef_zero() {
+    let c = Constraint::ElementUndefZero(vars(&["x", "y"]), var("i"), var("e"));
+    assert_eq!(count_solutions(&element_vars(0, 1), c), 18);
+}
+
+#[test]
+fn watch_element() {
+    let c = Constraint::WatchElement(vars(&["x", "y"]), var("i"), var("e"));
+    assert_eq!(count_solutions(&element_vars(0, 1), c), 18);
+}
+
+#[test]
+fn watch_element_one() {
+    let c = Constraint::WatchElementOne(vars(&["x", "y"]), var("i"), var("e"));
+    assert_eq!(count_solutions(&element_vars(1, 2), c), 18);
+}
+
+#[test]
+fn watch_element_one_undef_zero() {
+    let c = Constraint::WatchElementOneUndefZero(vars(&["x", "y"]), var("i"), var("e"));
+    assert_eq!(count_solutions(&element_vars(1, 2), c), 18);
+}
+
+#[test]
+fn watch_element_undef_zero() {
+    let c = Constraint::WatchElementUndefZero(vars(&["x", "y"]), var("i"), var("e"));
+    assert_eq!(count_solutions(&element_vars(0, 1), c), 18);
+}
+
+#[test]
+fn w_literal() {
+    let c = Constraint::WLiteral(var("x"), int(2));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn w_not_literal() {
+    let c = Constraint::WNotLiteral(var("x"), int(2));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 6);
+}
+
+#[test]
+fn w_in_interval_set() {
+    // x in 1..2 or 4..5
+    let c = Constraint::WInIntervalSet(var("x"), ints(&[1, 2, 4, 5]));
+    assert_eq!(count_solutions(&bound(&["x"], 1, 6), c), 4);
+}
+
+#[test]
+fn w_in_range() {
+    // x in 2..4
+    let c = Constraint::WInRange(var("x"), ints(&[2, 4]));
+    assert_eq!(count_solutions(&bound(&["x"], 1, 6), c), 3);
+}
+
+#[test]
+fn w_inset() {
+    let c = Constraint::WInset(var("x"), ints(&[1, 3, 6]));
+    assert_eq!(count_solutions(&bound(&["x"], 1, 6), c), 3);
+}
+
+#[test]
+fn w_not_in_range() {
+    let c = Constraint::WNotInRange(var("x"), ints(&[2, 4]));
+    assert_eq!(count_solutions(&bound(&["x"], 1, 6), c), 3);
+}
+
+#[test]
+fn w_not_inset() {
+    let c = Constraint::WNotInset(var("x"), ints(&[1, 3, 6]));
+    assert_eq!(count_solutions(&bound(&["x"], 1, 6), c), 3);
+}
+
+#[test]
+fn abs() {
+    // x = |y|, with x in 0..2, y in -2..2
+    let vs = vec![
+        ("x", VarDomain::Bound(0, 2)),
+        ("y", VarDomain::Bound(-2, 2)),
+    ];
+    let c = Constraint::Abs(var("x"), var("y"));
+    assert_eq!(count_solutions(&vs, c), 5);
+}
+
+#[test]
+fn dis_eq() {
+    let c = Constraint::DisEq(var("x"), var("y"));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 6);
+}
+
+#[test]
+fn minus_eq() {
+    // x = -y, with x in -2..2, y in -1..3
+    let vs = vec![
+        ("x", VarDomain::Bound(-2, 2)),
+        ("y", VarDomain::Bound(-1, 3)),
+    ];
+    let c = Constraint::MinusEq(var("x"), var("y"));
+    assert_eq!(count_solutions(&vs, c), 4);
+}
+
+#[test]
+fn gac_eq() {
+    let vs = vec![("x", VarDomain::Bound(1, 3)), ("y", VarDomain::Bound(2, 4))];
+    let c = Constraint::GacEq(var("x"), var("y"));
+    assert_eq!(count_solutions(&vs, c), 2);
+}
+
+#[test]
+fn watch_less() {
+    // x < y
+    let c = Constraint::WatchLess(var("x"), var("y"));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 3);
+}
+
+#[test]
+fn watch_neq() {
+    let c = Constraint::WatchNeq(var("x"), var("y"));
+    assert_eq!(count_solutions(&bound(&["x", "y"], 1, 3), c), 6);
+}
+
+#[test]
+fn discrete_domain() {
+    let vs = vec![
+        ("x", VarDomain::Discrete(1, 3)),
+        ("y", VarDomain::Discrete(1, 3)),
+    ];
+    let c = Constraint::WatchLess(var("x"), var("y"));
+    assert_eq!(count_solutions(&vs, c), 3);
+}
+
+#[test]
+fn discrete_domain_with_interval_set_keeps_holes() {
+    // x < y, with x in {1, 3, 5} and y in 1..5
+    let vs = vec![
+        ("x", VarDomain::Discrete(1, 5)),
+        ("y", VarDomain::Discrete(1, 5)),
+    ];
+    let c = Constraint::WatchedAnd(vec![
+        Constraint::WInIntervalSet(var("x"), ints(&[1, 1, 3, 3, 5, 5])),
+        Constraint::WatchLess(var("x"), var("y")),
+    ]);
+    assert_eq!(count_solutions(&vs, c), 6);
+}
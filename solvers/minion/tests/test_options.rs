@@ -0,0 +1,61 @@
+//! Limits set in the [MinionOptions] of a run should stop that run only.
+
+use minion_rs::ast::{Constant, Constraint, Model, Var, VarDomain};
+use minion_rs::options::{MinionOptions, SearchEnd, ValOrder};
+use minion_rs::run_minion_with_options;
+
+/// x, y, z in 0..9 with x != y, which has 900 solutions.
+fn model() -> Model {
+    let mut model = Model::new();
+    for name in ["x", "y", "z"] {
+        model
+            .named_variables
+            .add_var(name.to_owned(), VarDomain::Bound(0, 9));
+    }
+    model.constraints.push(Constraint::DisEq(
+        Var::NameRef("x".to_owned()),
+        Var::NameRef("y".to_owned()),
+    ));
+    model
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn reaching_a_limit_does_not_affect_the_next_run() {
+    let limited = MinionOptions::new().node_limit(10);
+    let stats = run_minion_with_options(model(), |_| true, &limited).unwrap();
+    assert_eq!(stats.search_end, SearchEnd::LimitReached);
+
+    let mut count = 0;
+    let stats = run_minion_with_options(
+        model(),
+        |_| {
+            count += 1;
+            true
+        },
+        &MinionOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(stats.search_end, SearchEnd::Finished);
+    assert_eq!(count, 900);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn descending_value_order_finds_the_largest_values_first() {
+    let mut first = None;
+    let options = MinionOptions::new().val_order(ValOrder::Descend);
+    run_minion_with_options(
+        model(),
+        |solution| {
+            first = Some(solution);
+            false
+        },
+        &options,
+    )
+    .unwrap();
+
+    let first = first.unwrap();
+    assert_eq!(first["x"], Constant::Integer(9));
+    assert_eq!(first["z"], Constant::Integer(9));
+}
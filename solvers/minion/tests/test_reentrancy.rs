@@ -0,0 +1,131 @@
+//! Runs of Minion should not share any state: each run reports its own solutions to its own
+//! callback, whether runs happen one after another or on several threads at once. Starting a run
+//! from inside the callback of another is an error.
+//!
+//! A run started while another thread is running Minion happens in a worker process, so does not wait
+//! for the other run to finish.
+
+use std::sync::mpsc;
+use std::thread;
+
+use minion_rs::ast::{Constant, Constraint, Model, Var, VarDomain};
+use minion_rs::run_minion;
+
+/// x, y in 1..n with x != y, which has n * (n - 1) solutions.
+fn all_different_pair(n: i32) -> Model {
+    let mut model = Model::new();
+    model
+        .named_variables
+        .add_var("x".to_owned(), VarDomain::Bound(1, n));
+    model
+        .named_variables
+        .add_var("y".to_owned(), VarDomain::Bound(1, n));
+    model.constraints.push(Constraint::DisEq(
+        Var::NameRef("x".to_owned()),
+        Var::NameRef("y".to_owned()),
+    ));
+    model
+}
+
+#[allow(clippy::unwrap_used)]
+fn count_solutions(model: Model) -> usize {
+    let mut solutions = vec![];
+    run_minion(model, |solution| {
+        solutions.push(solution);
+        true
+    })
+    .unwrap();
+    solutions.len()
+}
+
+#[test]
+fn sequential_runs_have_separate_state() {
+    assert_eq!(count_solutions(all_different_pair(3)), 6);
+    assert_eq!(count_solutions(all_different_pair(4)), 12);
+    assert_eq!(count_solutions(all_different_pair(3)), 6);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn stopping_early_does_not_affect_the_next_run() {
+    let mut seen = 0;
+    run_minion(all_different_pair(4), |_| {
+        seen += 1;
+        false
+    })
+    .unwrap();
+    assert_eq!(seen, 1);
+
+    assert_eq!(count_solutions(all_different_pair(4)), 12);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn concurrent_runs_report_their_own_solutions() {
+    let handles: Vec<_> = (2..8)
+        .map(|n| thread::spawn(move || (n, count_solutions(all_different_pair(n)))))
+        .collect();
+
+    for handle in handles {
+        let (n, count) = handle.join().unwrap();
+        assert_eq!(count, (n * (n - 1)) as usize);
+    }
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn solutions_are_given_to_the_callback_of_their_run() {
+    let mut xs = vec![];
+    run_minion(all_different_pair(2), |solution| {
+        xs.push(solution["x"]);
+        true
+    })
+    .unwrap();
+    assert_eq!(xs.len(), 2);
+    assert!(xs.contains(&Constant::Integer(1)));
+    assert!(xs.contains(&Constant::Integer(2)));
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn running_from_a_callback_is_an_error() {
+    let mut inner = None;
+    run_minion(all_different_pair(3), |_| {
+        inner = Some(run_minion(all_different_pair(3), |_| true));
+        false
+    })
+    .unwrap();
+    assert!(matches!(inner, Some(Err(_))));
+
+    assert_eq!(count_solutions(all_different_pair(3)), 6);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn a_run_does_not_wait_for_the_run_in_progress() {
+    let (started_tx, started_rx) = mpsc::channel();
+    let (resume_tx, resume_rx) = mpsc::channel::<()>();
+
+    // pause the first run in its callback until the second run has finished.
+    let paused = thread::spawn(move || {
+        let mut first = true;
+        let mut seen = 0;
+        run_minion(all_different_pair(3), |_| {
+            if first {
+                first = false;
+                started_tx.send(()).unwrap();
+                resume_rx.recv().unwrap();
+            }
+            seen += 1;
+            true
+        })
+        .unwrap();
+        seen
+    });
+
+    started_rx.recv().unwrap();
+    assert_eq!(count_solutions(all_different_pair(4)), 12);
+    resume_tx.send(()).unwrap();
+
+    assert_eq!(paused.join().unwrap(), 6);
+}